@@ -0,0 +1,57 @@
+//! Mutating one function in a large generated file
+//!
+//! `prepare_mutation` only parses the items that mention the target and only
+//! regenerates the one containing the mutation; `full_file` is the old
+//! approach of parsing and pretty-printing the whole file.
+
+use std::path::PathBuf;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use mutation_testing_rust::codegen::{generate_source, mutate_ast, prepare_mutation};
+use mutation_testing_rust::MutationConfig;
+
+/// About 10k lines: 2000 five-line functions, with the target in the middle
+fn large_source() -> String {
+    let mut source = String::new();
+    for i in 0..2000 {
+        let name = if i == 1000 { "target".to_string() } else { format!("f{}", i) };
+        source.push_str(&format!(
+            "pub fn {}(a: i32, b: i32) -> i32 {{\n    let c = a * {};\n    c + b\n}}\n\n",
+            name, i
+        ));
+    }
+    source
+}
+
+fn mutation() -> MutationConfig {
+    MutationConfig {
+        file: PathBuf::from("src/generated.rs"),
+        function: "target".to_string(),
+        original: "c + b".to_string(),
+        replacement: "c - b".to_string(),
+        id: "bench".to_string(),
+        ..Default::default()
+    }
+}
+
+fn bench_large_file(c: &mut Criterion) {
+    let source = large_source();
+    let mutation = mutation();
+
+    let mut group = c.benchmark_group("large_file");
+    group.sample_size(20);
+    group.bench_function("prepare_mutation", |b| {
+        b.iter(|| prepare_mutation(black_box(&source), &mutation).unwrap())
+    });
+    group.bench_function("full_file", |b| {
+        b.iter(|| {
+            let mut ast = syn::parse_file(black_box(&source)).unwrap();
+            mutate_ast(&mut ast, &mutation).unwrap();
+            generate_source(&ast)
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_large_file);
+criterion_main!(benches);
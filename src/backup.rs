@@ -0,0 +1,444 @@
+//! Backups of files while they are mutated
+//!
+//! Before writing a mutant the runner copies the original file into
+//! `.mutation-backup/` under the crate directory, and removes the copy once the
+//! original is restored. If the process is hard-killed in between, the backup
+//! is left behind and the `clean` command can put the original back.
+//!
+//! A run also holds `.mutation-lock` in the crate directory, so a second run
+//! started in the same crate fails instead of mutating and restoring the same
+//! files underneath the first.
+
+use std::io::Write;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+
+use crate::error::{MutationError, Result};
+
+/// Name of the backup directory, relative to the crate directory
+pub const BACKUP_DIR: &str = ".mutation-backup";
+
+/// Name of the lock file held during a run, relative to the crate directory
+pub const LOCK_FILE: &str = ".mutation-lock";
+
+/// Suffix of the file next to each backup recording the path it belongs to
+const TARGET_SUFFIX: &str = ".mutation-target";
+
+/// Advisory lock keeping two runs from mutating the same crate at once
+///
+/// Taken by creating the lock file, which fails if it already exists, and
+/// released by removing it when dropped, including while unwinding. A run
+/// that is hard-killed leaves the file behind; the next run takes it over once
+/// the pid recorded in it is no longer alive.
+#[derive(Debug)]
+pub struct RunLock {
+    path: PathBuf,
+}
+
+impl RunLock {
+    /// Take the lock for `crate_dir`, failing with
+    /// [`MutationError::RunInProgress`] if a live run holds it
+    ///
+    /// A lock whose holder is no longer alive is stale and taken over.
+    pub fn acquire(crate_dir: &Path) -> Result<Self> {
+        let path = crate_dir.join(LOCK_FILE);
+        let mut took_over = false;
+        loop {
+            match std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Ok(mut file) => {
+                    // The holder's pid, so later runs can tell whether it's alive
+                    let _ = write!(file, "{}", std::process::id());
+                    return Ok(RunLock { path });
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    let pid = lock_holder(&path);
+                    // Only retry once, so two runs racing for a stale lock
+                    // can't keep removing each other's
+                    if took_over || !is_stale(pid) {
+                        return Err(MutationError::RunInProgress { lock: path, pid });
+                    }
+                    let _ = std::fs::remove_file(&path);
+                    took_over = true;
+                }
+                Err(e) => {
+                    return Err(MutationError::WriteError {
+                        file: path,
+                        error: e.to_string(),
+                    })
+                }
+            }
+        }
+    }
+}
+
+impl Drop for RunLock {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Remove a lock left behind by a run that was killed, returning whether
+/// there was one
+///
+/// A lock whose holder is still alive is only removed with `force`; otherwise
+/// this fails with [`MutationError::RunInProgress`].
+pub fn remove_stale_lock(crate_dir: &Path, force: bool) -> Result<bool> {
+    let path = crate_dir.join(LOCK_FILE);
+    if !path.exists() {
+        return Ok(false);
+    }
+    let pid = lock_holder(&path);
+    if !force && !is_stale(pid) {
+        return Err(MutationError::RunInProgress { lock: path, pid });
+    }
+    Ok(std::fs::remove_file(&path).is_ok())
+}
+
+/// The pid recorded in a lock file
+fn lock_holder(path: &Path) -> Option<u32> {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|pid| pid.trim().parse().ok())
+}
+
+/// Whether a lock held by `pid` can be taken over
+///
+/// A lock without a pid may be one another run has created but not written
+/// yet, so it counts as held.
+fn is_stale(pid: Option<u32>) -> bool {
+    pid.is_some_and(|pid| !process_alive(pid))
+}
+
+/// Whether a process with `pid` is running
+#[cfg(unix)]
+fn process_alive(pid: u32) -> bool {
+    let Ok(pid) = libc::pid_t::try_from(pid) else {
+        return false;
+    };
+    // Signal 0 only checks that the process exists; EPERM means it does but
+    // belongs to another user
+    // SAFETY: kill with signal 0 sends nothing
+    let exists = unsafe { libc::kill(pid, 0) } == 0;
+    exists || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Whether a process with `pid` is running
+#[cfg(windows)]
+fn process_alive(pid: u32) -> bool {
+    Command::new("tasklist")
+        .args(["/FI", &format!("PID eq {}", pid), "/NH"])
+        .output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).contains(&pid.to_string()))
+        // Without tasklist there's no telling, so keep the lock
+        .unwrap_or(true)
+}
+
+/// Whether a process with `pid` is running
+#[cfg(not(any(unix, windows)))]
+fn process_alive(_pid: u32) -> bool {
+    true
+}
+
+/// Path of the backup for `file` (relative to `crate_dir`)
+///
+/// Root and `..` components are spelled `@root` and `@up`, so the backup
+/// always stays inside the backup directory, even for absolute paths, and
+/// `../src/lib.rs` doesn't share a backup with `src/lib.rs`.
+pub fn backup_path(crate_dir: &Path, file: &Path) -> PathBuf {
+    let relative: PathBuf = file
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(name) => Some(name),
+            Component::ParentDir => Some("@up".as_ref()),
+            Component::RootDir | Component::Prefix(_) => Some("@root".as_ref()),
+            Component::CurDir => None,
+        })
+        .collect();
+    crate_dir.join(BACKUP_DIR).join(relative)
+}
+
+/// The file next to `backup` recording the path it is restored to
+fn target_path(backup: &Path) -> PathBuf {
+    let mut path = backup.as_os_str().to_owned();
+    path.push(TARGET_SUFFIX);
+    PathBuf::from(path)
+}
+
+/// Save the original content of `file` before it is mutated
+///
+/// `file` itself is recorded next to the backup, so `clean` restores it to
+/// exactly that path.
+pub fn write_backup(crate_dir: &Path, file: &Path, content: &str) -> Result<PathBuf> {
+    let path = backup_path(crate_dir, file);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| MutationError::WriteError {
+            file: path.clone(),
+            error: e.to_string(),
+        })?;
+    }
+    let target = target_path(&path);
+    std::fs::write(&target, file.to_string_lossy().as_bytes()).map_err(|e| {
+        MutationError::WriteError {
+            file: target.clone(),
+            error: e.to_string(),
+        }
+    })?;
+    std::fs::write(&path, content).map_err(|e| MutationError::WriteError {
+        file: path.clone(),
+        error: e.to_string(),
+    })?;
+    Ok(path)
+}
+
+/// Remove a backup once the original has been restored, along with any
+/// directories that become empty
+pub fn remove_backup(crate_dir: &Path, backup: &Path) {
+    let _ = std::fs::remove_file(backup);
+    let _ = std::fs::remove_file(target_path(backup));
+
+    let root = crate_dir.join(BACKUP_DIR);
+    let mut dir = backup.parent();
+    while let Some(d) = dir {
+        if !d.starts_with(&root) || std::fs::remove_dir(d).is_err() {
+            break;
+        }
+        if d == root {
+            break;
+        }
+        dir = d.parent();
+    }
+}
+
+/// Restore every backed-up file under `crate_dir` and delete the backups
+///
+/// Returns the restored paths as they were configured: relative to
+/// `crate_dir`, or absolute.
+pub fn restore_backups(crate_dir: &Path) -> Result<Vec<PathBuf>> {
+    let root = crate_dir.join(BACKUP_DIR);
+    let mut restored = Vec::new();
+    if !root.exists() {
+        return Ok(restored);
+    }
+
+    let mut pending = vec![root.clone()];
+    while let Some(dir) = pending.pop() {
+        let entries = std::fs::read_dir(&dir).map_err(|e| MutationError::FileReadError {
+            file: dir.clone(),
+            error: e.to_string(),
+        })?;
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                pending.push(path);
+                continue;
+            }
+            if path.to_string_lossy().ends_with(TARGET_SUFFIX) {
+                continue;
+            }
+            // Backups written before targets were recorded mirror the path
+            let file = match std::fs::read_to_string(target_path(&path)) {
+                Ok(file) => PathBuf::from(file),
+                Err(_) => path.strip_prefix(&root).unwrap_or(&path).to_path_buf(),
+            };
+            let target = crate_dir.join(&file);
+            std::fs::copy(&path, &target).map_err(|e| MutationError::WriteError {
+                file: target.clone(),
+                error: e.to_string(),
+            })?;
+            restored.push(file);
+        }
+    }
+
+    std::fs::remove_dir_all(&root).map_err(|e| MutationError::WriteError {
+        file: root,
+        error: e.to_string(),
+    })?;
+
+    restored.sort();
+    Ok(restored)
+}
+
+/// List which of `files` differ from `HEAD` according to git
+///
+/// Returns `None` if git isn't available or `crate_dir` isn't in a repository.
+pub fn modified_in_git(crate_dir: &Path, files: &[PathBuf]) -> Option<Vec<PathBuf>> {
+    let output = Command::new("git")
+        .args(["diff", "--name-only", "--relative", "HEAD", "--"])
+        .args(files)
+        .current_dir(crate_dir)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(PathBuf::from)
+            .collect(),
+    )
+}
+
+/// `git --version`, or `None` if git isn't available
+pub fn git_version() -> Option<String> {
+    let output = Command::new("git").arg("--version").output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// The commit `HEAD` points at, or `None` outside a git repository
+pub fn git_head(dir: &Path) -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .current_dir(dir)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Reset `files` to their content at `HEAD`
+pub fn git_checkout(crate_dir: &Path, files: &[PathBuf]) -> Result<()> {
+    let output = Command::new("git")
+        .args(["checkout", "HEAD", "--"])
+        .args(files)
+        .current_dir(crate_dir)
+        .output()
+        .map_err(|e| MutationError::TestExecutionError {
+            error: format!("Failed to run git: {}", e),
+        })?;
+    if !output.status.success() {
+        return Err(MutationError::TestExecutionError {
+            error: format!(
+                "git checkout failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backup_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let crate_dir = dir.path();
+        let file = Path::new("src/math.rs");
+        std::fs::create_dir_all(crate_dir.join("src")).unwrap();
+        std::fs::write(crate_dir.join(file), "mutated").unwrap();
+
+        let backup = write_backup(crate_dir, file, "original").unwrap();
+        assert_eq!(backup, crate_dir.join(".mutation-backup/src/math.rs"));
+
+        // Simulate a crash: the mutated file and the backup are left behind
+        let restored = restore_backups(crate_dir).unwrap();
+        assert_eq!(restored, vec![PathBuf::from("src/math.rs")]);
+        assert_eq!(std::fs::read_to_string(crate_dir.join(file)).unwrap(), "original");
+        assert!(!crate_dir.join(BACKUP_DIR).exists());
+    }
+
+    #[test]
+    fn test_backup_outside_crate_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let crate_dir = dir.path().join("app");
+        std::fs::create_dir_all(crate_dir.join("src")).unwrap();
+        std::fs::create_dir_all(dir.path().join("shared/src")).unwrap();
+        let inside = Path::new("src/lib.rs");
+        let outside = Path::new("../shared/src/lib.rs");
+        let absolute = dir.path().join("shared/src/abs.rs");
+        for file in [inside, outside, &absolute] {
+            std::fs::write(crate_dir.join(file), "mutated").unwrap();
+        }
+
+        write_backup(&crate_dir, inside, "inside").unwrap();
+        let backup = write_backup(&crate_dir, outside, "outside").unwrap();
+        assert_eq!(backup, crate_dir.join(".mutation-backup/@up/shared/src/lib.rs"));
+        write_backup(&crate_dir, &absolute, "absolute").unwrap();
+
+        let restored = restore_backups(&crate_dir).unwrap();
+        assert_eq!(restored.len(), 3);
+        assert!(restored.contains(&outside.to_path_buf()));
+        assert!(restored.contains(&absolute));
+        assert_eq!(std::fs::read_to_string(crate_dir.join(inside)).unwrap(), "inside");
+        let shared = dir.path().join("shared/src/lib.rs");
+        assert_eq!(std::fs::read_to_string(shared).unwrap(), "outside");
+        assert_eq!(std::fs::read_to_string(&absolute).unwrap(), "absolute");
+        assert!(!crate_dir.join("shared").exists());
+    }
+
+    #[test]
+    fn test_run_lock() {
+        let dir = tempfile::tempdir().unwrap();
+        let crate_dir = dir.path();
+
+        let lock = RunLock::acquire(crate_dir).unwrap();
+        match RunLock::acquire(crate_dir) {
+            Err(MutationError::RunInProgress { lock, pid }) => {
+                assert_eq!(lock, crate_dir.join(LOCK_FILE));
+                assert_eq!(pid, Some(std::process::id()));
+            }
+            other => panic!("expected RunInProgress, got {:?}", other),
+        }
+
+        // Released on drop
+        drop(lock);
+        assert!(!crate_dir.join(LOCK_FILE).exists());
+        let lock = RunLock::acquire(crate_dir).unwrap();
+
+        // A live holder's lock is only removed with force
+        std::mem::forget(lock);
+        assert!(matches!(
+            remove_stale_lock(crate_dir, false),
+            Err(MutationError::RunInProgress { .. })
+        ));
+        assert!(remove_stale_lock(crate_dir, true).unwrap());
+        assert!(!remove_stale_lock(crate_dir, false).unwrap());
+        assert!(RunLock::acquire(crate_dir).is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_stale_lock_is_taken_over() {
+        let dir = tempfile::tempdir().unwrap();
+        let crate_dir = dir.path();
+
+        // A pid that has exited
+        let mut child = Command::new("true").spawn().unwrap();
+        let dead = child.id();
+        child.wait().unwrap();
+
+        std::fs::write(crate_dir.join(LOCK_FILE), dead.to_string()).unwrap();
+        let lock = RunLock::acquire(crate_dir).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(crate_dir.join(LOCK_FILE)).unwrap(),
+            std::process::id().to_string()
+        );
+        drop(lock);
+
+        std::fs::write(crate_dir.join(LOCK_FILE), dead.to_string()).unwrap();
+        assert!(remove_stale_lock(crate_dir, false).unwrap());
+    }
+
+    #[test]
+    fn test_remove_backup_cleans_empty_dirs() {
+        let dir = tempfile::tempdir().unwrap();
+        let crate_dir = dir.path();
+
+        let backup = write_backup(crate_dir, Path::new("src/math.rs"), "original").unwrap();
+        remove_backup(crate_dir, &backup);
+
+        assert!(!crate_dir.join(BACKUP_DIR).exists());
+        assert!(restore_backups(crate_dir).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,149 @@
+//! Accepted survivors
+//!
+//! A baseline lists the mutants that survived when it was written. A run
+//! checked against it only fails on survivors the baseline doesn't list, so a
+//! project can adopt mutation testing without first killing every existing
+//! survivor, and still be alerted when new ones appear.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{MutationError, Result};
+use crate::report::MutationReport;
+use crate::runner::{MutationResult, MutationStatus};
+
+/// Survivors accepted as known
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Baseline {
+    pub survivors: Vec<BaselineEntry>,
+}
+
+/// A surviving mutant, identified by its id and the change it makes
+///
+/// A mutation whose code changed under the same id is a different mutant, so
+/// it isn't accepted by an entry for the old code.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BaselineEntry {
+    pub mutation_id: String,
+    pub file: PathBuf,
+    pub function: String,
+    pub original: String,
+    pub replacement: String,
+}
+
+impl BaselineEntry {
+    fn of(result: &MutationResult) -> Self {
+        BaselineEntry {
+            mutation_id: result.mutation_id.clone(),
+            file: result.file.clone(),
+            function: result.function.clone(),
+            original: result.original.clone(),
+            replacement: result.replacement.clone(),
+        }
+    }
+}
+
+impl Baseline {
+    /// The survivors of a run
+    pub fn from_report(report: &MutationReport) -> Self {
+        Baseline {
+            survivors: survivors(report).map(BaselineEntry::of).collect(),
+        }
+    }
+
+    /// Whether the baseline accepts `result` as a known survivor
+    pub fn accepts(&self, result: &MutationResult) -> bool {
+        self.survivors.contains(&BaselineEntry::of(result))
+    }
+
+    /// The survivors of a run that the baseline doesn't list
+    pub fn new_survivors<'a>(&self, report: &'a MutationReport) -> Vec<&'a MutationResult> {
+        survivors(report).filter(|result| !self.accepts(result)).collect()
+    }
+
+    /// Load a baseline written by [`Baseline::write`]
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path).map_err(|e| MutationError::FileReadError {
+            file: path.to_path_buf(),
+            error: e.to_string(),
+        })?;
+        serde_json::from_str(&json).map_err(|e| MutationError::ConfigError {
+            message: format!("Invalid baseline '{}': {}", path.display(), e),
+        })
+    }
+
+    /// Write the baseline as JSON, replacing the file
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self).expect("baseline serializes to JSON");
+        std::fs::write(path, json).map_err(|e| MutationError::WriteError {
+            file: path.to_path_buf(),
+            error: e.to_string(),
+        })
+    }
+}
+
+fn survivors(report: &MutationReport) -> impl Iterator<Item = &MutationResult> {
+    report
+        .results
+        .iter()
+        .filter(|result| result.status == MutationStatus::Survived)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::MutationConfig;
+    use crate::runner::not_run_result;
+    use std::time::Duration;
+
+    fn result(id: &str, replacement: &str, status: MutationStatus) -> MutationResult {
+        let mutation = MutationConfig {
+            id: id.to_string(),
+            file: PathBuf::from("src/lib.rs"),
+            function: "f".to_string(),
+            original: "a + b".to_string(),
+            replacement: replacement.to_string(),
+            ..Default::default()
+        };
+        MutationResult {
+            status,
+            duration: Duration::from_millis(10),
+            line: Some(2),
+            column: Some(5),
+            ..not_run_result(&mutation)
+        }
+    }
+
+    #[test]
+    fn test_new_survivors() {
+        let accepted = MutationReport::new(vec![
+            result("plus", "a - b", MutationStatus::Survived),
+            result("times", "a * b", MutationStatus::Killed),
+        ]);
+        let baseline = Baseline::from_report(&accepted);
+        assert_eq!(baseline.survivors.len(), 1);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("baseline.json");
+        baseline.write(&path).unwrap();
+        let baseline = Baseline::load(&path).unwrap();
+
+        let report = MutationReport::new(vec![
+            result("plus", "a - b", MutationStatus::Survived),
+            // Newly surviving
+            result("times", "a * b", MutationStatus::Survived),
+            result("minus", "a - b", MutationStatus::Killed),
+        ]);
+        let new: Vec<&str> = baseline
+            .new_survivors(&report)
+            .iter()
+            .map(|r| r.mutation_id.as_str())
+            .collect();
+        assert_eq!(new, ["times"]);
+
+        // The same id with a different replacement is a different mutant
+        let changed = MutationReport::new(vec![result("plus", "a * b", MutationStatus::Survived)]);
+        assert_eq!(baseline.new_survivors(&changed).len(), 1);
+    }
+}
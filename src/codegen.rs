@@ -1,19 +1,42 @@
 //! Code generation from AST
 //!
-//! This module handles converting mutated ASTs back to source code.
+//! This module handles converting mutated ASTs back to source code. Only the
+//! top-level item containing the mutation is regenerated; the rest of the file
+//! is kept byte-for-byte, so large files aren't reflowed on every mutant.
 
+use std::cmp::Reverse;
 use std::path::Path;
 
+use proc_macro2::{Delimiter, Spacing, Span, TokenStream, TokenTree};
+use quote::ToTokens;
+use syn::spanned::Spanned;
+
 use crate::config::MutationConfig;
-use crate::error::{MutationError, Result};
-use crate::matcher::{collect_function_names, find_expression_in_function, MatchedSite};
-use crate::mutator::Mutator;
+use crate::error::{MutationError, NearMatch, Result};
+use crate::matcher::{
+    anchor_lines, byte_offset, collect_function_names, expression_on_line, expressions_in_function,
+    find_block_in_function_with, find_expression_in_function_with, find_near_matches, macro_sites,
+    target_exists, target_exists_with, MatchOptions, MatchedSite, TargetName,
+};
+use crate::mutator::{BlockMutator, Mutator};
 
 /// Generate source code from AST
 pub fn generate_source(ast: &syn::File) -> String {
     prettyplease::unparse(ast)
 }
 
+/// Generate source code from AST, checking that it parses back
+///
+/// Returns `None` when `prettyplease` can't print the AST faithfully. Syntax
+/// `syn` only keeps as verbatim tokens, like some nightly-only items, makes it
+/// panic or print code that no longer parses.
+fn try_generate_source(ast: &syn::File) -> Option<String> {
+    let generated =
+        std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| generate_source(ast))).ok()?;
+    syn::parse_file(&generated).ok()?;
+    Some(generated)
+}
+
 /// Result of preparing a mutation
 pub struct PreparedMutation {
     /// The mutated source code
@@ -23,76 +46,595 @@ pub struct PreparedMutation {
 }
 
 /// Prepare a mutation: parse, find, apply, and generate mutated source
+///
+/// This is the supported entry point for running the tests yourself: it only
+/// works on the `source` text it is given and never touches the filesystem or
+/// runs anything, so writing the mutant, testing it and putting the original
+/// back are up to the caller. `mutation.file` is only used in errors.
 pub fn prepare_mutation(
     source: &str,
     mutation: &MutationConfig,
 ) -> Result<PreparedMutation> {
-    // Parse the source file
+    let anchored;
+    let mutation = if mutation.anchor.is_some() {
+        anchored = resolve_anchor(source, mutation)?;
+        &anchored
+    } else {
+        mutation
+    };
+
+    if let Some(prepared) = prepare_in_candidates(source, mutation) {
+        return Ok(prepared);
+    }
+
+    // Parse the whole source file; this also produces the definitive error
+    // when the mutation can't be applied
     let mut ast = syn::parse_file(source).map_err(|e| MutationError::ParseError {
         file: mutation.file.clone(),
         error: e.to_string(),
     })?;
 
-    // Parse original expression
-    let original_expr: syn::Expr =
-        syn::parse_str(&mutation.original).map_err(|e| MutationError::InvalidOriginal {
-            code: mutation.original.clone(),
-            parse_error: e.to_string(),
+    // Item spans are taken before mutating, while every token still points
+    // into `source`
+    let item_spans: Vec<Span> = ast.items.iter().map(Spanned::span).collect();
+
+    let mut site = mutate_ast(&mut ast, mutation)?;
+    site.locate(source);
+
+    // Generate the mutated source
+    let sites = std::slice::from_ref(&site);
+    let mutated_source = match splice_items(source, &ast, &item_spans, sites) {
+        Some(spliced) => spliced,
+        None => regenerate_file(source, &ast, sites, &[mutation])?,
+    };
+    Ok(PreparedMutation {
+        mutated_source,
+        site,
+    })
+}
+
+/// Prepare several mutations of the same file as one mutant, parsing it once
+///
+/// Every mutation must match `source` itself and must not overlap another,
+/// which fails with [`MutationError::OverlappingMutations`]. They are applied
+/// in order, last in the file first, so each is applied where it was matched.
+/// Returns the mutated source and each mutation's site.
+pub(crate) fn prepare_mutations(
+    source: &str,
+    mutations: &[&MutationConfig],
+) -> Result<(String, Vec<MatchedSite>)> {
+    let anchored = mutations
+        .iter()
+        .map(|mutation| resolve_anchor(source, mutation))
+        .collect::<Result<Vec<_>>>()?;
+    let mutations: Vec<&MutationConfig> = anchored.iter().collect();
+
+    let mut ast = syn::parse_file(source).map_err(|e| MutationError::ParseError {
+        file: mutations.first().map(|m| m.file.clone()).unwrap_or_default(),
+        error: e.to_string(),
+    })?;
+    let item_spans: Vec<Span> = ast.items.iter().map(Spanned::span).collect();
+
+    let mut parsed = Vec::new();
+    let mut sites: Vec<MatchedSite> = Vec::new();
+    for mutation in &mutations {
+        let code = ParsedMutation::parse(mutation)?;
+        let mut site = code.find(&ast, mutation)?;
+        site.locate(source);
+        if let Some(other) = sites.iter().position(|s| s.overlaps(&site)) {
+            return Err(MutationError::OverlappingMutations {
+                file: mutation.file.clone(),
+                mutations: vec![mutations[other].id.clone(), mutation.id.clone()],
+                locations: vec![sites[other].to_location(), site.to_location()],
+            });
+        }
+        parsed.push(code);
+        sites.push(site);
+    }
+
+    // A match index counts the matches before it, which a change earlier in
+    // the file could add to or remove
+    for i in last_first(&sites) {
+        parsed[i].apply(&mut ast, mutations[i], &sites[i])?;
+    }
+
+    let mutated_source = match splice_items(source, &ast, &item_spans, &sites) {
+        Some(spliced) => spliced,
+        None => regenerate_file(source, &ast, &sites, &mutations)?,
+    };
+    Ok((mutated_source, sites))
+}
+
+/// Indices of `sites`, ordered from the last in the file to the first
+fn last_first(sites: &[MatchedSite]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..sites.len()).collect();
+    order.sort_by_key(|&i| Reverse((sites[i].line, sites[i].column)));
+    order
+}
+
+/// Point a mutation with an `anchor` at the code its marker comment labels
+///
+/// The mutation's `line` becomes the line after the `// mutate: <anchor>`
+/// comment. Without an `original`, the outermost expression on that line is
+/// used, as written in the source.
+pub fn resolve_anchor(source: &str, mutation: &MutationConfig) -> Result<MutationConfig> {
+    let Some(anchor) = &mutation.anchor else {
+        return Ok(mutation.clone());
+    };
+    let line = match anchor_lines(source, anchor)[..] {
+        [line] => line,
+        [] => {
+            return Err(MutationError::AnchorNotFound {
+                file: mutation.file.clone(),
+                anchor: anchor.clone(),
+            })
+        }
+        ref lines => {
+            return Err(MutationError::AmbiguousAnchor {
+                file: mutation.file.clone(),
+                anchor: anchor.clone(),
+                lines: lines.to_vec(),
+            })
+        }
+    };
+
+    let mut resolved = mutation.clone();
+    resolved.line = Some(line);
+    if resolved.original.trim().is_empty() {
+        let ast = syn::parse_file(source).map_err(|e| MutationError::ParseError {
+            file: mutation.file.clone(),
+            error: e.to_string(),
+        })?;
+        check_function_exists(&ast, mutation)?;
+        let site = expression_on_line(&ast, &mutation.function, line).ok_or_else(|| {
+            MutationError::FailedToApply {
+                reason: format!(
+                    "no expression on line {} of '{}', marked by '// mutate: {}'",
+                    line, mutation.function, anchor
+                ),
+            }
+        })?;
+        resolved.original = site
+            .expr
+            .span()
+            .source_text()
+            .unwrap_or_else(|| site.expr.to_token_stream().to_string());
+    }
+    Ok(resolved)
+}
+
+/// Fast path of [`prepare_mutation`] that only parses the items mentioning
+/// the target
+///
+/// Returns `None` whenever it can't be sure of the result, including every
+/// error, so the caller can retry with the whole file.
+fn prepare_in_candidates(source: &str, mutation: &MutationConfig) -> Option<PreparedMutation> {
+    let name = TargetName::parse(&mutation.function).name;
+    let mut ast = parse_candidates(source, &name)?;
+    let item_spans: Vec<Span> = ast.items.iter().map(Spanned::span).collect();
+
+    let mut site = mutate_ast(&mut ast, mutation).ok()?;
+    site.locate(source);
+    let mutated_source = splice_items(source, &ast, &item_spans, std::slice::from_ref(&site))?;
+    Some(PreparedMutation {
+        mutated_source,
+        site,
+    })
+}
+
+/// Parse only the top-level items whose tokens mention `name`
+///
+/// Lexing a file is cheap but building its syntax tree is not, which matters
+/// for large generated files. Items are split at top-level `;` and brace
+/// groups, except that a brace group after an initializer's `=` or in a `use`
+/// doesn't end the item. A split that merges items is harmless; one that cuts
+/// an item in two fails to parse, and `None` is returned.
+fn parse_candidates(source: &str, name: &str) -> Option<syn::File> {
+    let tokens: TokenStream = source.parse().ok()?;
+
+    let mut chunks = Vec::new();
+    let mut current = Vec::new();
+    let mut after_eq = false;
+    let mut is_use = false;
+    // Depth of `<...>` generics, where `=` introduces a default instead
+    let mut angle_depth = 0usize;
+    let mut prev_joint_minus = false;
+    for token in tokens {
+        let mut ends_item = false;
+        match &token {
+            TokenTree::Punct(p) => match p.as_char() {
+                ';' => ends_item = true,
+                '=' if angle_depth == 0 => after_eq = true,
+                '<' => angle_depth += 1,
+                // Skip the `>` of `->`
+                '>' if !prev_joint_minus => angle_depth = angle_depth.saturating_sub(1),
+                _ => {}
+            },
+            TokenTree::Ident(ident) if ident == "use" => is_use = true,
+            TokenTree::Group(g) => {
+                ends_item = g.delimiter() == Delimiter::Brace && !after_eq && !is_use;
+            }
+            _ => {}
+        }
+        prev_joint_minus = matches!(&token, TokenTree::Punct(p)
+            if p.as_char() == '-' && p.spacing() == Spacing::Joint);
+        current.push(token);
+        if ends_item {
+            chunks.push(std::mem::take(&mut current));
+            after_eq = false;
+            is_use = false;
+            angle_depth = 0;
+        }
+    }
+    chunks.push(current);
+
+    let mut items = Vec::new();
+    for chunk in chunks {
+        let chunk: TokenStream = chunk.into_iter().collect();
+        if mentions(&chunk, name) {
+            items.extend(syn::parse2::<syn::File>(chunk).ok()?.items);
+        }
+    }
+
+    if items.is_empty() {
+        return None;
+    }
+    Some(syn::File {
+        shebang: None,
+        attrs: Vec::new(),
+        items,
+    })
+}
+
+/// Whether `tokens` contain the identifier `name` at any depth
+fn mentions(tokens: &TokenStream, name: &str) -> bool {
+    tokens.clone().into_iter().any(|token| match token {
+        TokenTree::Ident(ident) => ident == name,
+        TokenTree::Group(g) => mentions(&g.stream(), name),
+        _ => false,
+    })
+}
+
+/// Regenerate only the top-level items containing `sites` and splice them
+/// into `source` in place of the original items
+///
+/// Items are found by the sites' byte ranges, since several items can share
+/// a line. Returns `None` if a site wasn't located in `source`, if no item
+/// contains it, or if a regenerated item doesn't parse back, in which case
+/// the caller falls back to [`regenerate_file`].
+fn splice_items(
+    source: &str,
+    ast: &syn::File,
+    item_spans: &[Span],
+    sites: &[MatchedSite],
+) -> Option<String> {
+    let mut items = Vec::new();
+    for site in sites {
+        let (site_start, site_end) = (site.start_byte?, site.end_byte?);
+        let item = item_spans.iter().enumerate().find_map(|(index, span)| {
+            let (start, end) = (span.start(), span.end());
+            let start = byte_offset(source, start.line, start.column + 1)?;
+            let end = byte_offset(source, end.line, end.column + 1)?;
+            (start <= site_start && site_end <= end).then_some((index, start, end))
         })?;
+        if !items.contains(&item) {
+            items.push(item);
+        }
+    }
+
+    // Last in the file first, so splicing one doesn't move the others
+    items.sort_by_key(|&(_, start, _)| Reverse(start));
+    let mut spliced = source.to_string();
+    for (index, start, end) in items {
+        let item = syn::File {
+            shebang: None,
+            attrs: Vec::new(),
+            items: vec![ast.items[index].clone()],
+        };
+        let generated = try_generate_source(&item)?;
+        spliced.replace_range(start..end, generated.trim_end());
+    }
+    Some(spliced)
+}
+
+/// Regenerate the whole mutated file, for when [`splice_items`] can't be used
+///
+/// If the regenerated file doesn't parse back, the mutations' replacements
+/// are spliced into `source` as text in place of the matched code instead, so
+/// a broken file is never written. `sites` and `mutations` correspond.
+fn regenerate_file(
+    source: &str,
+    ast: &syn::File,
+    sites: &[MatchedSite],
+    mutations: &[&MutationConfig],
+) -> Result<String> {
+    if let Some(generated) = try_generate_source(ast) {
+        return Ok(generated);
+    }
+    last_first(sites)
+        .into_iter()
+        .try_fold(source.to_string(), |spliced, i| {
+            splice_text(&spliced, &sites[i], &mutations[i].replacement)
+        })
+        .filter(|spliced| syn::parse_file(spliced).is_ok())
+        .ok_or_else(|| MutationError::FailedToApply {
+            reason: format!(
+                "the mutated {} doesn't parse back after regenerating it, and the \
+                 replacement couldn't be spliced in as text",
+                mutations.first().map_or(Path::new(""), |m| m.file.as_path()).display()
+            ),
+        })
+}
+
+/// Replace the code of `site` in `source` with `replacement`, as text
+///
+/// Expressions that could bind differently in their new surroundings are
+/// parenthesized. Returns `None` if the site's source text is unknown or no
+/// longer where the site says.
+fn splice_text(source: &str, site: &MatchedSite, replacement: &str) -> Option<String> {
+    let original = site.source.as_deref()?;
+    let start = byte_offset(source, site.line, site.column)?;
+    if !source[start..].starts_with(original) {
+        return None;
+    }
 
-    // Parse replacement expression
-    let replacement_expr: syn::Expr =
-        syn::parse_str(&mutation.replacement).map_err(|e| MutationError::InvalidReplacement {
+    let replacement = match syn::parse_str::<syn::Expr>(replacement) {
+        Ok(
+            syn::Expr::Path(_)
+            | syn::Expr::Lit(_)
+            | syn::Expr::Call(_)
+            | syn::Expr::MethodCall(_)
+            | syn::Expr::Field(_)
+            | syn::Expr::Index(_)
+            | syn::Expr::Paren(_)
+            | syn::Expr::Macro(_)
+            | syn::Expr::Tuple(_)
+            | syn::Expr::Block(_),
+        )
+        | Err(_) => replacement.to_string(),
+        Ok(_) => format!("({})", replacement),
+    };
+
+    let mut spliced = String::with_capacity(source.len() + replacement.len());
+    spliced.push_str(&source[..start]);
+    spliced.push_str(&replacement);
+    spliced.push_str(&source[start + original.len()..]);
+    Some(spliced)
+}
+
+/// Apply a mutation to an already-parsed AST in place
+///
+/// Parses the original and replacement expressions, finds the single matching
+/// site in the target function and replaces it. Returns the site that was
+/// mutated; the AST is left untouched on error.
+///
+/// An `original` written as a block (`{ ... }`) is matched against every block
+/// in the function, including loop and `if` bodies, and its replacement must
+/// also be a block.
+pub fn mutate_ast(ast: &mut syn::File, mutation: &MutationConfig) -> Result<MatchedSite> {
+    let code = ParsedMutation::parse(mutation)?;
+    let site = code.find(ast, mutation)?;
+    code.apply(ast, mutation, &site)?;
+    Ok(site)
+}
+
+/// A mutation's `original` and `replacement`, parsed
+enum ParsedMutation {
+    Expr(Box<syn::Expr>, Box<syn::Expr>),
+    /// An `original` written as a block, replaced by a whole block
+    Block(syn::Block, syn::Block),
+}
+
+impl ParsedMutation {
+    /// Parse the code, as blocks if `original` is written as one
+    fn parse(mutation: &MutationConfig) -> Result<Self> {
+        let invalid_original = |e: syn::Error| MutationError::InvalidOriginal {
+            code: mutation.original.clone(),
+            parse_error: e.to_string(),
+        };
+        let invalid_replacement = |e: syn::Error| MutationError::InvalidReplacement {
             code: mutation.replacement.clone(),
             parse_error: e.to_string(),
-        })?;
+        };
+        if is_block(&mutation.original) {
+            Ok(ParsedMutation::Block(
+                syn::parse_str(&mutation.original).map_err(invalid_original)?,
+                syn::parse_str(&mutation.replacement).map_err(invalid_replacement)?,
+            ))
+        } else {
+            Ok(ParsedMutation::Expr(
+                Box::new(syn::parse_str(&mutation.original).map_err(invalid_original)?),
+                Box::new(syn::parse_str(&mutation.replacement).map_err(invalid_replacement)?),
+            ))
+        }
+    }
+
+    /// Find the single site in the target function the mutation applies to
+    fn find(&self, ast: &syn::File, mutation: &MutationConfig) -> Result<MatchedSite> {
+        check_function_exists(ast, mutation)?;
 
-    // Check function exists
-    let functions = collect_function_names(&ast);
-    if !functions.contains(&mutation.function) {
+        let options = mutation.match_options();
+        match self {
+            ParsedMutation::Expr(original, _) => {
+                let matches =
+                    find_expression_in_function_with(ast, &mutation.function, original, &options);
+                select_match(matches, mutation).map_err(|e| match e {
+                    MutationError::NoMatch { file, function, original: code, .. } => {
+                        MutationError::NoMatch {
+                            suggestions: suggest_expressions(ast, &function, original),
+                            file,
+                            function,
+                            original: code,
+                        }
+                    }
+                    e => e,
+                })
+            }
+            ParsedMutation::Block(original, _) => {
+                let matches =
+                    find_block_in_function_with(ast, &mutation.function, original, &options);
+                select_match(matches, mutation)
+            }
+        }
+    }
+
+    /// Replace the code at `site`, as found by [`ParsedMutation::find`]
+    fn apply(
+        &self,
+        ast: &mut syn::File,
+        mutation: &MutationConfig,
+        site: &MatchedSite,
+    ) -> Result<()> {
+        let options = mutation.match_options();
+        match self {
+            ParsedMutation::Expr(original, replacement) => Mutator::apply_with(
+                ast,
+                &mutation.function,
+                original,
+                replacement,
+                site,
+                &options,
+            ),
+            ParsedMutation::Block(original, replacement) => BlockMutator::apply_with(
+                ast,
+                &mutation.function,
+                original,
+                replacement,
+                site,
+                &options,
+            ),
+        }
+    }
+}
+
+/// Whether a mutation's code is written as a block rather than an expression
+pub fn is_block(code: &str) -> bool {
+    code.trim_start().starts_with('{')
+}
+
+/// Check that the mutation's target function exists in the file
+fn check_function_exists(ast: &syn::File, mutation: &MutationConfig) -> Result<()> {
+    let target = TargetName::parse(&mutation.function);
+    if target_exists_with(ast, &target, &mutation.match_options()) {
+        return Ok(());
+    }
+    if !target_exists(ast, &target) {
         return Err(MutationError::FunctionNotFound {
             file: mutation.file.clone(),
             function: mutation.function.clone(),
-            available_functions: functions,
+            available_functions: collect_function_names(ast),
+            macro_sites: macro_sites(ast),
         });
     }
+    let outside_tests = MatchOptions {
+        skip_tests: true,
+        ..Default::default()
+    };
+    if !mutation.mutate_tests && !target_exists_with(ast, &target, &outside_tests) {
+        return Err(MutationError::ConfigError {
+            message: format!(
+                "'{}' in {} is test code, which is only mutated with --mutate-tests \
+                 (settings.mutate_tests)",
+                mutation.function,
+                mutation.file.display()
+            ),
+        });
+    }
+    Err(MutationError::ConfigError {
+        message: format!(
+            "'{}' in {} is not public, and only public functions are mutated (pub_only)",
+            mutation.function,
+            mutation.file.display()
+        ),
+    })
+}
 
-    // Find the original expression in the function
-    let matches = find_expression_in_function(&ast, &mutation.function, &original_expr);
-
-    let target = match matches.len() {
-        0 => {
-            return Err(MutationError::NoMatch {
-                file: mutation.file.clone(),
-                function: mutation.function.clone(),
-                original: mutation.original.clone(),
-            });
-        }
-        1 => matches.into_iter().next().unwrap(),
-        n => {
-            return Err(MutationError::AmbiguousMatch {
-                function: mutation.function.clone(),
-                original: mutation.original.clone(),
-                match_count: n,
-                locations: matches.iter().map(|m| m.to_location()).collect(),
-            });
+/// Pick the single site to mutate, honoring the mutation's `line` if set
+fn select_match(matches: Vec<MatchedSite>, mutation: &MutationConfig) -> Result<MatchedSite> {
+    // Narrow down to the requested line, if any
+    let matches = match mutation.line {
+        Some(line) if !matches.is_empty() => {
+            let found_lines: Vec<usize> = matches.iter().map(|m| m.line).collect();
+            let on_line: Vec<MatchedSite> =
+                matches.into_iter().filter(|m| m.line == line).collect();
+            if on_line.is_empty() {
+                return Err(MutationError::NoMatchOnLine {
+                    function: mutation.function.clone(),
+                    original: mutation.original.clone(),
+                    line,
+                    found_lines,
+                });
+            }
+            on_line
         }
+        _ => matches,
+    };
+
+    match matches.len() {
+        0 => Err(MutationError::NoMatch {
+            file: mutation.file.clone(),
+            function: mutation.function.clone(),
+            original: mutation.original.clone(),
+            suggestions: Vec::new(),
+        }),
+        1 => Ok(matches.into_iter().next().unwrap()),
+        n => Err(MutationError::AmbiguousMatch {
+            function: mutation.function.clone(),
+            original: mutation.original.clone(),
+            match_count: n,
+            locations: matches.iter().map(|m| m.to_location()).collect(),
+        }),
+    }
+}
+
+/// How many expressions a `NoMatch` error suggests at most
+const MAX_SUGGESTIONS: usize = 5;
+
+/// Expressions in a function that resemble `original`, for a `NoMatch` error
+///
+/// Expressions of the same kind as `original` (e.g. binary operations) come
+/// first, those with the same operator ahead of the rest. Plain paths and
+/// literals are only suggested when `original` is one. The picks are listed
+/// in source order.
+fn suggest_expressions(
+    ast: &syn::File,
+    function: &str,
+    original: &syn::Expr,
+) -> Vec<NearMatch> {
+    let trivial = |expr: &syn::Expr| matches!(expr, syn::Expr::Path(_) | syn::Expr::Lit(_));
+    let rank = |expr: &syn::Expr| match (original, expr) {
+        (syn::Expr::Binary(a), syn::Expr::Binary(b)) if a.op == b.op => 0,
+        _ if std::mem::discriminant(original) == std::mem::discriminant(expr) => 1,
+        _ => 2,
     };
 
-    // Apply the mutation
-    Mutator::apply(
-        &mut ast,
-        &mutation.function,
-        &original_expr,
-        &replacement_expr,
-        &target,
-    )?;
+    let mut sites: Vec<_> = expressions_in_function(ast, function)
+        .into_iter()
+        .filter(|site| trivial(original) || !trivial(&site.expr))
+        .map(|site| (rank(&site.expr), site))
+        .collect();
+    sites.sort_by_key(|(rank, site)| (*rank, site.line, site.column));
 
-    // Generate the mutated source
-    Ok(PreparedMutation {
-        mutated_source: generate_source(&ast),
-        site: target,
-    })
+    let mut suggestions: Vec<NearMatch> = Vec::new();
+    for (_, site) in sites {
+        let code = site
+            .expr
+            .span()
+            .source_text()
+            .unwrap_or_else(|| site.expr.to_token_stream().to_string());
+        if !suggestions.iter().any(|s| s.code == code) {
+            suggestions.push(NearMatch {
+                line: site.line,
+                column: site.column,
+                code,
+            });
+        }
+        if suggestions.len() == MAX_SUGGESTIONS {
+            break;
+        }
+    }
+    suggestions.sort_by_key(|s| (s.line, s.column));
+    suggestions
 }
 
 /// Apply a mutation to a file and return the mutated content
@@ -109,6 +651,85 @@ pub fn apply_mutation_to_file(
     prepare_mutation(&source, mutation)
 }
 
+/// Explain a failed exact match by listing near-matches of `original`
+///
+/// Returns `None` when nothing loosely matches, or when `original` is a block.
+/// The candidates are only reported; nothing is ever mutated loosely.
+pub fn near_match_error(source: &str, mutation: &MutationConfig) -> Option<MutationError> {
+    if is_block(&mutation.original) {
+        return None;
+    }
+    let ast = syn::parse_file(source).ok()?;
+    let original_expr: syn::Expr = syn::parse_str(&mutation.original).ok()?;
+
+    let candidates: Vec<NearMatch> = find_near_matches(&ast, &mutation.function, &original_expr)
+        .into_iter()
+        .map(|site| NearMatch {
+            line: site.line,
+            column: site.column,
+            code: site
+                .expr
+                .span()
+                .source_text()
+                .unwrap_or_else(|| site.expr.to_token_stream().to_string()),
+        })
+        .collect();
+
+    if candidates.is_empty() {
+        return None;
+    }
+    Some(MutationError::NearMatches {
+        function: mutation.function.clone(),
+        original: mutation.original.clone(),
+        candidates,
+    })
+}
+
+/// Whether a mutation's replacement is its original with only formatting or
+/// redundant parentheses changed, e.g. `(a + b)` for `a + b`
+///
+/// Such a mutant compiles to the original code, so it always survives.
+/// Parentheses that change precedence, as in `(a + b) * c`, are kept.
+pub fn is_formatting_only(mutation: &MutationConfig) -> bool {
+    match (normalize_code(&mutation.original), normalize_code(&mutation.replacement)) {
+        (Some(original), Some(replacement)) => original == replacement,
+        _ => false,
+    }
+}
+
+/// Pretty-print an expression or block without redundant parentheses
+///
+/// This is the form matching compares: `a+b` and `(a + b)` both normalize to
+/// `a + b`. All parentheses are removed from the AST and prettyplease puts
+/// back the ones precedence requires. Returns `None` if `code` doesn't parse.
+pub fn normalize_code(code: &str) -> Option<String> {
+    struct StripParens;
+
+    impl syn::visit_mut::VisitMut for StripParens {
+        fn visit_expr_mut(&mut self, expr: &mut syn::Expr) {
+            while let syn::Expr::Paren(paren) = expr {
+                *expr = (*paren.expr).clone();
+            }
+            syn::visit_mut::visit_expr_mut(self, expr);
+        }
+    }
+
+    let expr: syn::Expr = syn::parse_str(code).ok()?;
+    let mut file: syn::File = syn::parse_quote! { fn normalized() { #expr } };
+    syn::visit_mut::VisitMut::visit_file_mut(&mut StripParens, &mut file);
+
+    // Keep the function body, dedented
+    let source = generate_source(&file);
+    let lines: Vec<&str> = source.lines().collect();
+    let body = lines.get(1..lines.len().saturating_sub(1))?;
+    Some(
+        body.iter()
+            .map(|line| line.strip_prefix("    ").unwrap_or(line))
+            .collect::<Vec<_>>()
+            .join("\n"),
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -127,10 +748,239 @@ fn add(a: i32, b: i32) -> i32 {
             original: "a + b".to_string(),
             replacement: "a - b".to_string(),
             id: "test".to_string(),
+            ..Default::default()
+        };
+
+        let result = prepare_mutation(source, &mutation).unwrap();
+        assert!(result.mutated_source.contains("a - b"));
+    }
+
+    #[test]
+    fn test_only_mutated_item_is_regenerated() {
+        let source = r#"// Header comment
+fn  untouched( x:i32 )->i32 { x   *  2 }  // keep me
+
+/// Adds two numbers
+fn add(a: i32, b: i32) -> i32 {
+    a + b
+}
+
+const ALSO_UNTOUCHED : u8=1;
+"#;
+        let mutation = MutationConfig {
+            file: PathBuf::from("test.rs"),
+            function: "add".to_string(),
+            original: "a + b".to_string(),
+            replacement: "a - b".to_string(),
+            id: "test".to_string(),
+            ..Default::default()
+        };
+
+        let result = prepare_mutation(source, &mutation).unwrap();
+        assert_eq!(
+            result.mutated_source,
+            r#"// Header comment
+fn  untouched( x:i32 )->i32 { x   *  2 }  // keep me
+
+/// Adds two numbers
+fn add(a: i32, b: i32) -> i32 {
+    a - b
+}
+
+const ALSO_UNTOUCHED : u8=1;
+"#
+        );
+    }
+
+    #[test]
+    fn test_prepare_mutations() {
+        let source = "fn f(a: i32, b: i32, x: i32) -> i32 {\n    let y = x;\n    a + b + y\n}\n\n\
+                      fn  g( x:i32 )->i32 { x   *  2 }\n";
+        let mutation = |function: &str, original: &str, replacement: &str| MutationConfig {
+            file: PathBuf::from("src/lib.rs"),
+            function: function.to_string(),
+            original: original.to_string(),
+            replacement: replacement.to_string(),
+            ..Default::default()
+        };
+
+        // The first member's replacement adds an `a + b` before the one the
+        // second member matched in the unmodified file
+        let first = mutation("f", "x", "a + b");
+        let second = mutation("f", "a + b", "a - b");
+        let third = mutation("g", "x * 2", "x + 2");
+        let (mutated, sites) = prepare_mutations(source, &[&first, &second, &third]).unwrap();
+        assert_eq!(
+            mutated,
+            "fn f(a: i32, b: i32, x: i32) -> i32 {\n    let y = a + b;\n    a - b + y\n}\n\n\
+             fn g(x: i32) -> i32 {\n    x + 2\n}\n"
+        );
+        let lines: Vec<usize> = sites.iter().map(|site| site.line).collect();
+        assert_eq!(lines, [2, 3, 6]);
+    }
+
+    #[test]
+    fn test_items_sharing_a_line() {
+        let source = "fn helper() -> i32 { add(1, 2) } fn add(a: i32, b: i32) -> i32 { a + b }\n";
+        let mutation = MutationConfig {
+            file: PathBuf::from("test.rs"),
+            function: "add".to_string(),
+            original: "a + b".to_string(),
+            replacement: "a - b".to_string(),
+            id: "test".to_string(),
+            ..Default::default()
         };
 
+        // The item holding the site is regenerated, not the first on its line
         let result = prepare_mutation(source, &mutation).unwrap();
         assert!(result.mutated_source.contains("a - b"));
+        assert!(!result.mutated_source.contains("a + b"));
+        assert!(result.mutated_source.contains("add(1, 2)"));
+    }
+
+    #[test]
+    fn test_is_formatting_only() {
+        let mutation = |original: &str, replacement: &str| MutationConfig {
+            file: PathBuf::from("test.rs"),
+            function: "f".to_string(),
+            original: original.to_string(),
+            replacement: replacement.to_string(),
+            id: "test".into(),
+            ..Default::default()
+        };
+
+        assert!(is_formatting_only(&mutation("a + b", "(a + b)")));
+        assert!(is_formatting_only(&mutation("a+b*c", "a + (b * c)")));
+        assert!(is_formatting_only(&mutation("{ x += 1; }", "{\n    x += 1;\n}")));
+        // Parentheses that change precedence make a real mutation
+        assert!(!is_formatting_only(&mutation("a + b * c", "(a + b) * c")));
+        // Commuted operands are a different AST, not a formatting change
+        assert!(!is_formatting_only(&mutation("a + b", "b + a")));
+        assert!(!is_formatting_only(&mutation("a + b", "a - b")));
+    }
+
+    #[test]
+    fn test_normalize_code() {
+        assert_eq!(normalize_code("a+b").as_deref(), Some("a + b"));
+        assert_eq!(normalize_code("((a + b))").as_deref(), Some("a + b"));
+        assert_eq!(normalize_code("(a+b)*c").as_deref(), Some("(a + b) * c"));
+        assert_eq!(normalize_code("x.foo( 1 )").as_deref(), Some("x.foo(1)"));
+        assert_eq!(normalize_code("{ x += 1; }").as_deref(), Some("{\n    x += 1;\n}"));
+        assert_eq!(normalize_code("a +"), None);
+    }
+
+    #[test]
+    fn test_matched_source_text() {
+        let source = "fn add(a: i32, b: i32) -> i32 {\n    a+b\n}\n";
+        let mutation = MutationConfig {
+            file: PathBuf::from("test.rs"),
+            function: "add".to_string(),
+            original: "a + b".to_string(),
+            replacement: "a - b".to_string(),
+            id: "test".to_string(),
+            ..Default::default()
+        };
+
+        // The site keeps the code as written, not as normalized
+        let result = prepare_mutation(source, &mutation).unwrap();
+        assert_eq!(result.site.source.as_deref(), Some("a+b"));
+    }
+
+    #[test]
+    fn test_site_byte_offsets() {
+        // Multi-byte characters before the match, which spans two lines
+        let source =
+            "// größe\nfn area(w: u32, h: u32) -> u32 {\n    let _ = \"→\"; w *\n        h\n}\n";
+        let mutation = MutationConfig {
+            file: PathBuf::from("test.rs"),
+            function: "area".to_string(),
+            original: "w * h".to_string(),
+            replacement: "w + h".to_string(),
+            id: "test".to_string(),
+            ..Default::default()
+        };
+
+        let site = prepare_mutation(source, &mutation).unwrap().site;
+        let (start, end) = (site.start_byte.unwrap(), site.end_byte.unwrap());
+        assert_eq!(&source[start..end], "w *\n        h");
+        assert_eq!((site.end_line, site.end_column), (4, 10));
+    }
+
+    #[test]
+    fn test_unparsable_regeneration_falls_back_to_text() {
+        let source = "fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n";
+        let mutation = MutationConfig {
+            file: PathBuf::from("test.rs"),
+            function: "add".to_string(),
+            original: "a + b".to_string(),
+            replacement: "a - b".to_string(),
+            id: "test".to_string(),
+            ..Default::default()
+        };
+        let mut ast = syn::parse_file(source).unwrap();
+        let site = mutate_ast(&mut ast, &mutation).unwrap();
+        // Syntax `syn` doesn't know is kept as verbatim tokens, which
+        // `prettyplease` can't print
+        ast.items.push(syn::Item::Verbatim(quote::quote! { struct; }));
+        assert!(try_generate_source(&ast).is_none());
+
+        let mutated = regenerate_file(source, &ast, std::slice::from_ref(&site), &[&mutation]).unwrap();
+        assert_eq!(mutated, "fn add(a: i32, b: i32) -> i32 {\n    (a - b)\n}\n");
+
+        // Without the site's text, there's nothing left to fall back to
+        let unknown = MatchedSite {
+            source: None,
+            ..site
+        };
+        assert!(matches!(
+            regenerate_file(source, &ast, &[unknown], &[&mutation]),
+            Err(MutationError::FailedToApply { reason }) if reason.contains("test.rs")
+        ));
+    }
+
+    #[test]
+    fn test_parse_candidates() {
+        let source = r#"
+use std::{fmt, io};
+const ORIGIN: Point = Point { x: 0, y: 0 };
+struct Grid<const N: usize = 3> {}
+fn add(a: i32, b: i32) -> i32 { a + b }
+fn double(a: i32) -> i32 { add(a, a) }
+macro_rules! noop { () => {} }
+"#;
+        let ast = parse_candidates(source, "add").unwrap();
+        let names: Vec<String> = ast
+            .items
+            .iter()
+            .map(|item| match item {
+                syn::Item::Fn(f) => f.sig.ident.to_string(),
+                _ => "other".to_string(),
+            })
+            .collect();
+        assert_eq!(names, vec!["add", "double"]);
+        assert!(parse_candidates(source, "missing").is_none());
+    }
+
+    #[test]
+    fn test_mutate_ast() {
+        let source = r#"
+fn add(a: i32, b: i32) -> i32 {
+    a + b
+}
+"#;
+        let mut ast = syn::parse_file(source).unwrap();
+        let mutation = MutationConfig {
+            file: PathBuf::from("test.rs"),
+            function: "add".to_string(),
+            original: "a + b".to_string(),
+            replacement: "a - b".to_string(),
+            id: "test".to_string(),
+            ..Default::default()
+        };
+
+        let site = mutate_ast(&mut ast, &mutation).unwrap();
+        assert_eq!(site.line, 3);
+        assert!(generate_source(&ast).contains("a - b"));
     }
 
     #[test]
@@ -146,6 +996,7 @@ fn add(a: i32, b: i32) -> i32 {
             original: "a + b".to_string(),
             replacement: "a - b".to_string(),
             id: "test".to_string(),
+            ..Default::default()
         };
 
         let result = prepare_mutation(source, &mutation);
@@ -165,9 +1016,769 @@ fn add(a: i32, b: i32) -> i32 {
             original: "x + y".to_string(),
             replacement: "x - y".to_string(),
             id: "test".to_string(),
+            ..Default::default()
         };
 
         let result = prepare_mutation(source, &mutation);
         assert!(matches!(result, Err(MutationError::NoMatch { .. })));
     }
+
+    #[test]
+    fn test_no_match_suggests_expressions() {
+        let source = r#"
+fn score(hits: u32, misses: u32) -> u32 {
+    let total = hits + misses;
+    if total > 0 { hits * 100 / total } else { 0 }
+}
+"#;
+        let mutation = MutationConfig {
+            file: PathBuf::from("test.rs"),
+            function: "score".to_string(),
+            original: "hits * 10".to_string(),
+            replacement: "hits / 10".to_string(),
+            id: "test".to_string(),
+            ..Default::default()
+        };
+
+        let Err(error @ MutationError::NoMatch { .. }) = prepare_mutation(source, &mutation) else {
+            panic!("expected NoMatch");
+        };
+        let MutationError::NoMatch { suggestions, .. } = &error else {
+            unreachable!()
+        };
+        let codes: Vec<&str> = suggestions.iter().map(|s| s.code.as_str()).collect();
+        // Binary expressions are picked before others, and listed in source
+        // order; paths and literals aren't suggested
+        assert_eq!(
+            codes,
+            vec![
+                "hits + misses",
+                "if total > 0 { hits * 100 / total } else { 0 }",
+                "total > 0",
+                "hits * 100",
+                "hits * 100 / total",
+            ]
+        );
+        assert_eq!(suggestions[0].line, 3);
+        assert!(error.to_string().contains("line 4, column 20: hits * 100"));
+    }
+
+    #[test]
+    fn test_test_functions_need_mutate_tests() {
+        let source = r#"
+#[test]
+fn test_add() {
+    let sum = 2 + 2;
+    assert_eq!(sum, 4);
+}
+"#;
+        let mut mutation = MutationConfig {
+            file: PathBuf::from("test.rs"),
+            function: "test_add".to_string(),
+            original: "2 + 2".to_string(),
+            replacement: "2 - 2".to_string(),
+            id: "test".to_string(),
+            ..Default::default()
+        };
+
+        let Err(error) = prepare_mutation(source, &mutation) else {
+            panic!("test code was mutated");
+        };
+        assert!(error.to_string().contains("'test_add' in test.rs is test code"));
+
+        mutation.mutate_tests = true;
+        let prepared = prepare_mutation(source, &mutation).unwrap();
+        assert!(prepared.mutated_source.contains("2 - 2"));
+    }
+
+    #[test]
+    fn test_near_matches_are_reported_not_mutated() {
+        let source = r#"
+fn total(a: i64, b: i32) -> i64 {
+    let sum = a + b as i64;
+    sum * 2
+}
+"#;
+        let mutation = MutationConfig {
+            file: PathBuf::from("test.rs"),
+            function: "total".to_string(),
+            original: "a + b".to_string(),
+            replacement: "a - b".to_string(),
+            id: "test".to_string(),
+            ..Default::default()
+        };
+
+        let result = prepare_mutation(source, &mutation);
+        assert!(matches!(result, Err(MutationError::NoMatch { .. })));
+
+        match near_match_error(source, &mutation) {
+            Some(MutationError::NearMatches { candidates, .. }) => {
+                assert_eq!(candidates.len(), 1);
+                assert_eq!(candidates[0].line, 3);
+                assert_eq!(candidates[0].code, "a + b as i64");
+            }
+            other => panic!("expected near-matches, got {:?}", other),
+        }
+
+        let unrelated = MutationConfig {
+            original: "x * y".to_string(),
+            ..mutation
+        };
+        assert!(near_match_error(source, &unrelated).is_none());
+    }
+
+    #[test]
+    fn test_select_match_by_line() {
+        let source = r#"
+fn calc(a: i32, b: i32) -> i32 {
+    let x = a + b;
+    let y = a + b;
+    x * y
+}
+"#;
+        let mut mutation = MutationConfig {
+            file: PathBuf::from("test.rs"),
+            function: "calc".to_string(),
+            original: "a + b".to_string(),
+            replacement: "a - b".to_string(),
+            id: "test".to_string(),
+            line: Some(4),
+            ..Default::default()
+        };
+
+        let result = prepare_mutation(source, &mutation).unwrap();
+        assert_eq!(result.site.line, 4);
+        assert!(result.mutated_source.contains("let x = a + b;"));
+        assert!(result.mutated_source.contains("let y = a - b;"));
+
+        mutation.line = Some(5);
+        let result = prepare_mutation(source, &mutation);
+        assert!(matches!(
+            result,
+            Err(MutationError::NoMatchOnLine { ref found_lines, .. }) if found_lines == &[3, 4]
+        ));
+    }
+
+    #[test]
+    fn test_mutate_array_element() {
+        let source = r#"
+fn weights() -> [i32; 3] {
+    [1, 2, 3]
+}
+"#;
+        let mut mutation = MutationConfig {
+            file: PathBuf::from("test.rs"),
+            function: "weights".to_string(),
+            original: "2".to_string(),
+            replacement: "0".to_string(),
+            id: "test".to_string(),
+            ..Default::default()
+        };
+
+        // A single element is reachable on its own
+        let result = prepare_mutation(source, &mutation).unwrap();
+        assert!(result.mutated_source.contains("[1, 0, 3]"));
+
+        // The whole array still matches as one expression
+        mutation.original = "[1, 2, 3]".to_string();
+        mutation.replacement = "[3, 2, 1]".to_string();
+        let result = prepare_mutation(source, &mutation).unwrap();
+        assert!(result.mutated_source.contains("[3, 2, 1]"));
+    }
+
+    #[test]
+    fn test_mutate_tuple_element_ambiguous() {
+        let source = r#"
+fn pair() -> (i32, i32) {
+    (2, 2)
+}
+"#;
+        let mut mutation = MutationConfig {
+            file: PathBuf::from("test.rs"),
+            function: "pair".to_string(),
+            original: "2".to_string(),
+            replacement: "0".to_string(),
+            id: "test".to_string(),
+            ..Default::default()
+        };
+
+        // Repeated literals on one line are ambiguous, even with `line` set
+        let result = prepare_mutation(source, &mutation);
+        assert!(matches!(
+            result,
+            Err(MutationError::AmbiguousMatch { match_count: 2, .. })
+        ));
+        mutation.line = Some(3);
+        let result = prepare_mutation(source, &mutation);
+        assert!(matches!(result, Err(MutationError::AmbiguousMatch { .. })));
+
+        // Matching the enclosing tuple picks out one element unambiguously
+        mutation.original = "(2, 2)".to_string();
+        mutation.replacement = "(2, 0)".to_string();
+        let result = prepare_mutation(source, &mutation).unwrap();
+        assert!(result.mutated_source.contains("(2, 0)"));
+    }
+
+    #[test]
+    fn test_replace_loop_body_with_empty_block() {
+        let source = r#"
+fn sum(values: &[i32]) -> i32 {
+    let mut total = 0;
+    for v in values {
+        total += v;
+        println!("{}", total);
+    }
+    total
+}
+"#;
+        let mutation = MutationConfig {
+            file: PathBuf::from("test.rs"),
+            function: "sum".to_string(),
+            original: "{\n    total += v;\n    println!(\"{}\", total);\n}".to_string(),
+            replacement: "{}".to_string(),
+            id: "test".to_string(),
+            ..Default::default()
+        };
+
+        let result = prepare_mutation(source, &mutation).unwrap();
+        assert_eq!(result.site.line, 4);
+        assert!(result.mutated_source.contains("for v in values {}"));
+    }
+
+    #[test]
+    fn test_mutate_method_name() {
+        let source = r#"
+fn clamp(x: i32, lo: i32, hi: i32) -> i32 {
+    x.max(lo).min(hi)
+}
+"#;
+        let mutation = MutationConfig {
+            file: PathBuf::from("test.rs"),
+            function: "clamp".to_string(),
+            original: "x.max(lo)".to_string(),
+            replacement: "x.min(lo)".to_string(),
+            id: "test".to_string(),
+            ..Default::default()
+        };
+
+        let result = prepare_mutation(source, &mutation).unwrap();
+        assert!(result.mutated_source.contains("x.min(lo).min(hi)"));
+
+        // Same receiver and argument, but a different method: no match
+        let mutation = MutationConfig {
+            original: "x.min(lo)".to_string(),
+            replacement: "x.max(lo)".to_string(),
+            ..mutation
+        };
+        let result = prepare_mutation(source, &mutation);
+        assert!(matches!(result, Err(MutationError::NoMatch { .. })));
+    }
+
+    #[test]
+    fn test_function_not_found_lists_names_once_sorted() {
+        let source = r#"
+impl Square {
+    fn area(&self) -> f64 { self.side * self.side }
+    fn scale(&mut self, k: f64) { self.side *= k; }
+}
+
+impl Circle {
+    fn area(&self) -> f64 { 3.14 * self.r * self.r }
+}
+
+fn build() {}
+"#;
+        let mutation = MutationConfig {
+            file: PathBuf::from("test.rs"),
+            function: "perimeter".to_string(),
+            original: "a + b".to_string(),
+            replacement: "a - b".to_string(),
+            id: "test".to_string(),
+            ..Default::default()
+        };
+
+        let error = prepare_mutation(source, &mutation).err().unwrap();
+        let MutationError::FunctionNotFound { available_functions, .. } = &error else {
+            panic!("expected FunctionNotFound, got {:?}", error);
+        };
+        assert_eq!(available_functions, &["area", "build", "scale"]);
+        assert!(error.to_string().contains("Available functions: area, build, scale"));
+    }
+
+    #[test]
+    fn test_mutate_anchor() {
+        let source = r#"
+fn add(a: i32, b: i32) -> i32 {
+    let doubled = a + a;
+    // mutate: add-boundary
+    doubled + b
+}
+"#;
+        let mutation = MutationConfig {
+            file: PathBuf::from("test.rs"),
+            function: "add".to_string(),
+            replacement: "doubled - b".to_string(),
+            id: "test".to_string(),
+            anchor: Some("add-boundary".to_string()),
+            ..Default::default()
+        };
+
+        // Without `original`, the expression on the marked line is used
+        let resolved = resolve_anchor(source, &mutation).unwrap();
+        assert_eq!(resolved.original, "doubled + b");
+        assert_eq!(resolved.line, Some(5));
+        let result = prepare_mutation(source, &mutation).unwrap();
+        assert!(result.mutated_source.contains("    doubled - b\n"));
+        assert_eq!(result.site.line, 5);
+
+        // With `original`, only matches on the marked line count
+        let mutation = MutationConfig {
+            original: "a + a".to_string(),
+            replacement: "a - a".to_string(),
+            ..mutation
+        };
+        assert!(matches!(
+            prepare_mutation(source, &mutation),
+            Err(MutationError::NoMatchOnLine { line: 5, .. })
+        ));
+
+        let mutation = MutationConfig {
+            anchor: Some("missing".to_string()),
+            ..mutation
+        };
+        assert!(matches!(
+            prepare_mutation(source, &mutation),
+            Err(MutationError::AnchorNotFound { .. })
+        ));
+
+        let twice = format!("{}\nfn other() -> i32 {{\n    // mutate: missing\n    1\n}}\n\
+fn third() -> i32 {{\n    // mutate: missing\n    2\n}}\n", source);
+        assert!(matches!(
+            prepare_mutation(&twice, &mutation),
+            Err(MutationError::AmbiguousAnchor { ref lines, .. }) if lines == &[10, 14]
+        ));
+    }
+
+    #[test]
+    fn test_mutate_if_let_scrutinee() {
+        let source = r#"
+fn first_even(values: &[i32]) -> i32 {
+    if let Some(x) = values.iter().find(|v| *v % 2 == 0) {
+        *x
+    } else {
+        0
+    }
+}
+"#;
+        let mutation = MutationConfig {
+            file: PathBuf::from("test.rs"),
+            function: "first_even".to_string(),
+            original: "values.iter().find(|v| *v % 2 == 0)".to_string(),
+            replacement: "values.iter().find(|v| *v % 2 != 0)".to_string(),
+            id: "test".to_string(),
+            ..Default::default()
+        };
+
+        let result = prepare_mutation(source, &mutation).unwrap();
+        assert!(result
+            .mutated_source
+            .contains("if let Some(x) = values.iter().find(|v| *v % 2 != 0) {"));
+        assert_eq!(result.site.line, 3);
+
+        // The whole `let` condition can be matched too
+        let mutation = MutationConfig {
+            original: "let Some(x) = values.iter().find(|v| *v % 2 == 0)".to_string(),
+            replacement: "let Some(x) = values.iter().next()".to_string(),
+            ..mutation
+        };
+        let result = prepare_mutation(source, &mutation).unwrap();
+        assert!(result.mutated_source.contains("if let Some(x) = values.iter().next() {"));
+    }
+
+    #[test]
+    fn test_mutate_range_bounds() {
+        let source = r#"
+fn sum_to(n: u32) -> u32 {
+    let mut total = 0;
+    for i in 0..n {
+        total += i;
+    }
+    total
+}
+"#;
+        let mutation = MutationConfig {
+            file: PathBuf::from("test.rs"),
+            function: "sum_to".to_string(),
+            original: "0..n".to_string(),
+            replacement: "0..=n".to_string(),
+            id: "test".to_string(),
+            ..Default::default()
+        };
+
+        // Changing the limits is the classic off-by-one mutation
+        let result = prepare_mutation(source, &mutation).unwrap();
+        assert!(result.mutated_source.contains("for i in 0..=n {"));
+        assert_eq!(result.site.line, 4);
+
+        let mutation = MutationConfig {
+            replacement: "1..n".to_string(),
+            ..mutation
+        };
+        let result = prepare_mutation(source, &mutation).unwrap();
+        assert!(result.mutated_source.contains("for i in 1..n {"));
+
+        // Mutating back from an inclusive range
+        let inclusive = source.replace("0..n", "0..=n");
+        let mutation = MutationConfig {
+            original: "0..=n".to_string(),
+            replacement: "0..n".to_string(),
+            ..mutation
+        };
+        let result = prepare_mutation(&inclusive, &mutation).unwrap();
+        assert!(result.mutated_source.contains("for i in 0..n {"));
+
+        // `..` doesn't match `..=`
+        assert!(matches!(
+            prepare_mutation(source, &mutation),
+            Err(MutationError::NoMatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_mutate_cast_type() {
+        let source = r#"
+fn checksum(x: u64, bytes: &[u8]) -> u32 {
+    let low = x as u32;
+    low + bytes.len() as u32
+}
+"#;
+        let mutation = MutationConfig {
+            file: PathBuf::from("test.rs"),
+            function: "checksum".to_string(),
+            original: "x as u32".to_string(),
+            replacement: "x as u8".to_string(),
+            id: "test".to_string(),
+            ..Default::default()
+        };
+
+        let result = prepare_mutation(source, &mutation).unwrap();
+        assert!(result.mutated_source.contains("let low = x as u8;"));
+        assert!(result.mutated_source.contains("bytes.len() as u32"));
+        assert_eq!(result.site.line, 3);
+
+        // Truncating but keeping the type, so the mutant still compiles
+        let mutation = MutationConfig {
+            replacement: "x as u8 as u32".to_string(),
+            ..mutation
+        };
+        let result = prepare_mutation(source, &mutation).unwrap();
+        assert!(result.mutated_source.contains("let low = x as u8 as u32;"));
+
+        // Only the width differs: `x as u16` isn't in the function
+        let mutation = MutationConfig {
+            original: "x as u16".to_string(),
+            replacement: "x as u8".to_string(),
+            ..mutation
+        };
+        assert!(matches!(
+            prepare_mutation(source, &mutation),
+            Err(MutationError::NoMatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_mutate_field_access() {
+        let source = r#"
+struct Point { x: i32, y: i32 }
+
+fn offset(point: &Point, pair: (i32, i32)) -> i32 {
+    point.x + pair.0
+}
+"#;
+        let mut mutation = MutationConfig {
+            file: PathBuf::from("test.rs"),
+            function: "offset".to_string(),
+            original: "point.x".to_string(),
+            replacement: "point.y".to_string(),
+            id: "test".to_string(),
+            ..Default::default()
+        };
+
+        let result = prepare_mutation(source, &mutation).unwrap();
+        assert!(result.mutated_source.contains("point.y + pair.0"));
+        assert_eq!(result.site.line, 5);
+
+        // Tuple indices round-trip as plain indices, not `pair.1i32` or similar
+        mutation.original = "pair.0".to_string();
+        mutation.replacement = "pair.1".to_string();
+        let result = prepare_mutation(source, &mutation).unwrap();
+        assert!(result.mutated_source.contains("point.x + pair.1\n"));
+        syn::parse_file(&result.mutated_source).unwrap();
+
+        // A replacement field that doesn't exist still applies; the mutant
+        // then fails to compile
+        mutation.replacement = "pair.2".to_string();
+        assert!(prepare_mutation(source, &mutation).is_ok());
+
+        // Only the named field matches
+        mutation.original = "point.y".to_string();
+        assert!(matches!(
+            prepare_mutation(source, &mutation),
+            Err(MutationError::NoMatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_mutate_break_value() {
+        let source = r#"
+fn first_over(values: &[i32], limit: i32) -> i32 {
+    let mut iter = values.iter();
+    loop {
+        let x = *iter.next().unwrap();
+        if x > limit {
+            break x + 1;
+        }
+    }
+}
+"#;
+        let mut mutation = MutationConfig {
+            file: PathBuf::from("test.rs"),
+            function: "first_over".to_string(),
+            original: "break x + 1".to_string(),
+            replacement: "break x".to_string(),
+            id: "test".to_string(),
+            ..Default::default()
+        };
+
+        let result = prepare_mutation(source, &mutation).unwrap();
+        assert!(result.mutated_source.contains("break x;"));
+        assert_eq!(result.site.line, 7);
+
+        // The broken value can also be mutated on its own
+        mutation.original = "x + 1".to_string();
+        mutation.replacement = "x - 1".to_string();
+        let result = prepare_mutation(source, &mutation).unwrap();
+        assert!(result.mutated_source.contains("break x - 1;"));
+
+        // A bare `break` doesn't match one with a value
+        mutation.original = "break".to_string();
+        mutation.replacement = "continue".to_string();
+        let result = prepare_mutation(source, &mutation);
+        assert!(matches!(result, Err(MutationError::NoMatch { .. })));
+    }
+
+    #[test]
+    fn test_mutate_assignments() {
+        let source = r#"
+fn tally(values: &[i32]) -> (i32, i32) {
+    let mut sum = 0;
+    let mut last = 0;
+    for v in values {
+        sum += v;
+        last = *v;
+    }
+    (sum, last)
+}
+"#;
+        let mut mutation = MutationConfig {
+            file: PathBuf::from("test.rs"),
+            function: "tally".to_string(),
+            original: "sum += v".to_string(),
+            replacement: "sum -= v".to_string(),
+            id: "test".to_string(),
+            ..Default::default()
+        };
+
+        let result = prepare_mutation(source, &mutation).unwrap();
+        assert!(result.mutated_source.contains("sum -= v;"));
+        assert!(!result.mutated_source.contains("sum += v"));
+        assert_eq!(result.site.line, 6);
+
+        mutation.original = "last = *v".to_string();
+        mutation.replacement = "last = 0".to_string();
+        let result = prepare_mutation(source, &mutation).unwrap();
+        assert!(result.mutated_source.contains("last = 0;"));
+        assert_eq!(result.site.line, 7);
+
+        // The assigned value can also be mutated on its own
+        mutation.original = "*v".to_string();
+        mutation.replacement = "-*v".to_string();
+        let result = prepare_mutation(source, &mutation).unwrap();
+        assert!(result.mutated_source.contains("last = -*v;"));
+    }
+
+    #[test]
+    fn test_mutate_closure() {
+        let source = r#"
+fn increment_all(values: &[i32]) -> Vec<i32> {
+    values.iter().map(|x| x + 1).collect()
+}
+"#;
+        let mutation = MutationConfig {
+            file: PathBuf::from("test.rs"),
+            function: "increment_all".to_string(),
+            original: "|x| x + 1".to_string(),
+            replacement: "|x| x - 1".to_string(),
+            id: "test".to_string(),
+            ..Default::default()
+        };
+
+        let result = prepare_mutation(source, &mutation).unwrap();
+        assert!(result.mutated_source.contains("map(|x| x - 1)"));
+    }
+
+    #[test]
+    fn test_blocks_of_equal_length_are_not_conflated() {
+        let source = r#"
+fn adjust(values: &[i32]) -> (Vec<i32>, Vec<i32>) {
+    let up = values.iter().map(|x| { let y = x * 2; y + 1 }).collect();
+    let down = values.iter().map(|x| { let y = x * 2; y - 1 }).collect();
+    (up, down)
+}
+"#;
+        let mutation = MutationConfig {
+            file: PathBuf::from("test.rs"),
+            function: "adjust".to_string(),
+            original: "|x| { let y = x * 2; y + 1 }".to_string(),
+            replacement: "|x| { let y = x * 2; y }".to_string(),
+            id: "test".to_string(),
+            ..Default::default()
+        };
+
+        // Only the closure with the same statements matches, not both
+        let result = prepare_mutation(source, &mutation).unwrap();
+        assert_eq!(result.site.line, 3);
+        assert!(result.mutated_source.contains("y - 1"));
+        assert!(!result.mutated_source.contains("y + 1"));
+    }
+
+    #[test]
+    fn test_atomic_negative_literal() {
+        let source = r#"
+fn floor(x: i32) -> i32 {
+    if x < -5 { 5 } else { x }
+}
+"#;
+        let mut mutation = MutationConfig {
+            file: PathBuf::from("test.rs"),
+            function: "floor".to_string(),
+            original: "5".to_string(),
+            replacement: "6".to_string(),
+            id: "test".to_string(),
+            ..Default::default()
+        };
+
+        // `5` is ambiguous with the operand of `-5` by default
+        let result = prepare_mutation(source, &mutation);
+        assert!(matches!(result, Err(MutationError::AmbiguousMatch { .. })));
+
+        mutation.atomic_negative_literals = true;
+        let result = prepare_mutation(source, &mutation).unwrap();
+        assert!(result.mutated_source.contains("-5"));
+        assert!(result.mutated_source.contains("6"));
+    }
+
+    #[test]
+    fn test_leaf_only_field_chains() {
+        let source = r#"
+fn spread(a: &Pair) -> u32 {
+    let low = a.b;
+    a.b.c - low + a.b
+}
+"#;
+        let mut mutation = MutationConfig {
+            file: PathBuf::from("test.rs"),
+            function: "spread".to_string(),
+            original: "a.b".to_string(),
+            replacement: "a.d".to_string(),
+            id: "test".to_string(),
+            ..Default::default()
+        };
+
+        // `a.b` is also the base of `a.b.c`
+        let result = prepare_mutation(source, &mutation);
+        assert!(matches!(
+            result,
+            Err(MutationError::AmbiguousMatch { match_count: 3, .. })
+        ));
+
+        // `line` picks the standalone one on its own line
+        mutation.line = Some(3);
+        let result = prepare_mutation(source, &mutation).unwrap();
+        assert!(result.mutated_source.contains("let low = a.d;"));
+
+        // On a shared line, only leaf-only matching tells them apart
+        mutation.line = Some(4);
+        let result = prepare_mutation(source, &mutation);
+        assert!(matches!(result, Err(MutationError::AmbiguousMatch { .. })));
+        mutation.leaf_only = true;
+        let result = prepare_mutation(source, &mutation).unwrap();
+        assert!(result.mutated_source.contains("a.b.c - low + a.d"));
+    }
+
+    #[test]
+    fn test_mutate_associated_const() {
+        let source = r#"
+struct Buffer;
+
+impl Buffer {
+    const LIMIT: u32 = 10;
+
+    fn limit() -> u32 {
+        10
+    }
+}
+"#;
+        let mutation = MutationConfig {
+            file: PathBuf::from("test.rs"),
+            function: "Buffer::LIMIT".to_string(),
+            original: "10".to_string(),
+            replacement: "11".to_string(),
+            id: "test".to_string(),
+            ..Default::default()
+        };
+
+        let result = prepare_mutation(source, &mutation).unwrap();
+        assert_eq!(result.site.line, 5);
+        assert!(result.mutated_source.contains("const LIMIT: u32 = 11;"));
+
+        // Consts need the qualified name
+        let mutation = MutationConfig {
+            function: "LIMIT".to_string(),
+            ..mutation
+        };
+        let result = prepare_mutation(source, &mutation);
+        assert!(matches!(result, Err(MutationError::FunctionNotFound { .. })));
+    }
+
+    #[test]
+    fn test_mutate_macro_argument() {
+        let source = r#"
+fn check(a: u32, b: u32) {
+    assert!(a * b > 0, "product of {} and {}", a, 2);
+}
+"#;
+        let mutation = MutationConfig {
+            file: PathBuf::from("test.rs"),
+            function: "check".to_string(),
+            original: "b".to_string(),
+            replacement: "b + 1".to_string(),
+            id: "test".to_string(),
+            ..Default::default()
+        };
+
+        // Off by default
+        let result = prepare_mutation(source, &mutation);
+        assert!(matches!(result, Err(MutationError::NoMatch { .. })));
+
+        // Nested replacements are parenthesized, the other arguments are kept
+        let mutation = MutationConfig {
+            in_macros: true,
+            ..mutation
+        };
+        let result = prepare_mutation(source, &mutation).unwrap();
+        assert_eq!(result.site.line, 3);
+        assert!(result.mutated_source.contains("a * (b + 1) > 0"));
+        assert!(result.mutated_source.contains("\"product of {} and {}\", a, 2"));
+    }
 }
@@ -1,31 +1,200 @@
 //! Configuration file parsing for mutation testing
 
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::time::Duration;
 
+use crate::codegen::resolve_anchor;
+use crate::coverage::Coverage;
 use crate::error::MutationError;
+use crate::files::{glob_files, is_glob};
+use crate::matcher::{target_cfgs, target_exists, target_exists_with, MatchOptions, TargetName};
+use crate::operators::MutationOp;
 
 /// Top-level configuration structure
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Config {
     pub version: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Settings::is_default")]
     pub settings: Settings,
+    /// Reusable mutation shapes, referenced by name from a mutation's
+    /// `template`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub templates: HashMap<String, MutationTemplate>,
+    #[serde(deserialize_with = "deserialize_mutations")]
     pub mutations: Vec<MutationConfig>,
 }
 
+/// A reusable `original`/`replacement` pair
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct MutationTemplate {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub original: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub replacement: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mutation_op: Option<MutationOp>,
+}
+
 /// Global settings for mutation testing
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Settings {
-    /// Timeout in seconds for each test run
+    /// Timeout in seconds for each test run, including building the mutant
     #[serde(default = "default_timeout")]
     pub timeout: u64,
+    /// Wall-clock budget in seconds for the whole run; once exceeded, no new
+    /// mutations are started
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_runtime: Option<u64>,
+    /// Test at most this many mutations per file; the first ones in config
+    /// order are kept
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_per_file: Option<usize>,
+    /// Test at most this many mutations per function; the first ones in
+    /// config order are kept
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_per_function: Option<usize>,
+    /// Run each test target as a separate, parallel `cargo test` invocation
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub split_targets: bool,
+    /// Crate directory, relative to the project directory; mutation file
+    /// paths are resolved against it and tests run inside it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_root: Option<PathBuf>,
+    /// When an `original` has no exact match, report near-matches to help
+    /// fix the config (they are never mutated)
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub loose: bool,
+    /// Only mutate functions that are part of the public API
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub pub_only: bool,
+    /// Also mutate test code (`#[test]` functions and `#[cfg(test)]`
+    /// modules), which is skipped by default
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub mutate_tests: bool,
+    /// Skip gitignored files when expanding `file` globs
+    #[serde(default = "default_respect_gitignore")]
+    pub respect_gitignore: bool,
+    /// Rustup toolchain to run the tests with (`cargo +<toolchain> test`),
+    /// so results don't depend on the machine's default toolchain
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub toolchain: Option<String>,
+    /// Also run `#[ignore]`d tests (`-- --include-ignored`), so slow tests
+    /// can kill mutants too, at the cost of a longer run per mutation
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub include_ignored: bool,
+    /// When a mutant fails to compile, build it again once without
+    /// incremental compilation and only report a compile error if it
+    /// reproduces
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub clean_build_retry: bool,
+    /// Times the test command is started again when starting it fails with
+    /// an I/O error (e.g. a transient resource shortage), before the mutation
+    /// is reported as a test execution error
+    #[serde(default)]
+    pub test_command_retries: u32,
+    /// Delay in milliseconds before the first such retry; it doubles with
+    /// every further retry
+    #[serde(default = "default_retry_backoff_ms")]
+    pub retry_backoff_ms: u64,
+    /// Lines of test or compiler output kept in a result's `details`; longer
+    /// output is truncated (0 keeps everything)
+    #[serde(default = "default_details_lines")]
+    pub details_lines: usize,
+    /// Kinds of tests run against each mutant; empty runs everything
+    /// `cargo test` runs
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub test_kinds: Vec<TestKind>,
+    /// Exit codes of a failed test run that count as killing the mutant;
+    /// others are reported as test execution errors. Empty counts any
+    /// non-zero exit. Compile errors and crashes are detected first either way
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub kill_on_exit_codes: Vec<i32>,
+    /// Prebuilt test binary, relative to the crate directory, run directly
+    /// instead of `cargo test`. Only mutants of files the binary wasn't
+    /// compiled from (e.g. loaded at runtime) can be tested this way
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub test_binary: Option<PathBuf>,
+    /// Directory the full output of each mutation is written to, as
+    /// `<id>.log` (only set from the command line)
+    #[serde(skip)]
+    pub output_dir: Option<PathBuf>,
+    /// Line coverage; mutations on lines it shows were never executed are
+    /// reported as not covered without running the tests (only set from the
+    /// command line)
+    #[serde(skip)]
+    pub coverage: Option<Coverage>,
+    /// Leave the mutant in place after testing instead of restoring the
+    /// original (debugging aid, only set from the command line)
+    #[serde(skip)]
+    pub no_restore: bool,
+    /// Whether the crate has a library target, if known; without one, `unit`
+    /// runs only `--bins` and `doc` is skipped (detected at the start of a
+    /// run)
+    #[serde(skip)]
+    pub lib_target: Option<bool>,
 }
 
 impl Default for Settings {
     fn default() -> Self {
         Self {
             timeout: default_timeout(),
+            max_runtime: None,
+            max_per_file: None,
+            max_per_function: None,
+            split_targets: false,
+            source_root: None,
+            loose: false,
+            pub_only: false,
+            mutate_tests: false,
+            respect_gitignore: default_respect_gitignore(),
+            toolchain: None,
+            include_ignored: false,
+            clean_build_retry: false,
+            test_command_retries: 0,
+            retry_backoff_ms: default_retry_backoff_ms(),
+            details_lines: default_details_lines(),
+            test_kinds: Vec::new(),
+            kill_on_exit_codes: Vec::new(),
+            test_binary: None,
+            output_dir: None,
+            coverage: None,
+            no_restore: false,
+            lib_target: None,
+        }
+    }
+}
+
+/// A kind of test `cargo test` can run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TestKind {
+    /// Unit tests in the library and binaries
+    Unit,
+    /// Documentation tests
+    Doc,
+    /// Integration tests in `tests/`
+    Integration,
+}
+
+impl Settings {
+    /// Whether every setting has its default value
+    pub fn is_default(&self) -> bool {
+        *self == Settings::default()
+    }
+
+    /// How long a mutation's test run may take: its own `timeout` if set,
+    /// otherwise `settings.timeout` (which `--timeout` overrides)
+    pub fn timeout_for(&self, mutation: &MutationConfig) -> Duration {
+        Duration::from_secs(mutation.timeout.unwrap_or(self.timeout))
+    }
+
+    /// The directory mutation files are relative to and tests run in
+    pub fn crate_dir(&self, project_dir: &Path) -> PathBuf {
+        match &self.source_root {
+            Some(root) => project_dir.join(root),
+            None => project_dir.to_path_buf(),
         }
     }
 }
@@ -34,20 +203,126 @@ fn default_timeout() -> u64 {
     30
 }
 
+fn default_respect_gitignore() -> bool {
+    true
+}
+
+fn default_details_lines() -> usize {
+    40
+}
+
+fn default_retry_backoff_ms() -> u64 {
+    100
+}
+
+/// Parse a test kind: `unit`, `doc` or `integration`
+pub fn parse_test_kind(s: &str) -> Result<TestKind, String> {
+    match s.trim() {
+        "unit" => Ok(TestKind::Unit),
+        "doc" => Ok(TestKind::Doc),
+        "integration" => Ok(TestKind::Integration),
+        other => Err(format!(
+            "invalid test kind '{}' (expected unit, doc or integration)",
+            other
+        )),
+    }
+}
+
+/// Parse a duration such as `90`, `90s`, `30m` or `2h` (bare numbers are seconds)
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    let (number, multiplier) = match s.char_indices().last() {
+        Some((i, 's')) => (&s[..i], 1),
+        Some((i, 'm')) => (&s[..i], 60),
+        Some((i, 'h')) => (&s[..i], 60 * 60),
+        _ => (s, 1),
+    };
+    let value: u64 = number
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid duration '{}' (expected e.g. 90s, 30m, 2h)", s))?;
+    let secs = value
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!("duration '{}' is too large", s))?;
+    if secs == 0 {
+        return Err(format!("duration '{}' must be greater than zero", s));
+    }
+    Ok(Duration::from_secs(secs))
+}
+
 /// A single mutation definition
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
 pub struct MutationConfig {
     /// Path to the Rust source file
     pub file: PathBuf,
     /// Name of the function containing the code
     pub function: String,
-    /// The code to find (parsed as AST)
+    /// The code to find (parsed as AST); may be omitted with `anchor`
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub original: String,
-    /// The code to replace it with
+    /// The code to replace it with (generated when `mutation_op` is set)
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub replacement: String,
-    /// Optional unique identifier (auto-generated if omitted)
-    #[serde(default = "generate_id")]
+    /// Optional built-in operator that generates the replacement
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mutation_op: Option<MutationOp>,
+    /// Optional unique identifier (auto-generated if omitted, and left out
+    /// when serializing the config if it was)
+    #[serde(default)]
     pub id: String,
+    /// Whether `id` was generated rather than written in the config
+    #[serde(skip)]
+    pub id_generated: bool,
+    /// Optional line number used to pick one of several matches
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    /// Treat negated literals like `-5` as atomic, so `original: 5` doesn't
+    /// match the operand of a negation
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub atomic_negative_literals: bool,
+    /// Only match a field or index access where it ends a chain, so
+    /// `original: a.b` skips the `a.b` inside `a.b.c` and `a.b[i]`
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub leaf_only: bool,
+    /// Only mutate the function if it is part of the public API (`pub` or
+    /// `pub(crate)`); set for every mutation by `settings.pub_only`
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub pub_only: bool,
+    /// Also match the function if it is test code; set for every mutation by
+    /// `settings.mutate_tests`
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub mutate_tests: bool,
+    /// Also match inside the arguments of macro calls, where they parse as
+    /// comma-separated expressions (`assert!(a > b)`, `vec![a + 1]`)
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub in_macros: bool,
+    /// Tests that must fail for the mutant to count as killed; when other
+    /// tests fail but none of these, the mutant is reported as surviving.
+    /// Names match the full test path or its trailing segments, e.g.
+    /// `test_add` or `tests::test_add`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub killed_by: Option<Vec<String>>,
+    /// Environment variables set for this mutation's test run, overriding
+    /// inherited ones (e.g. feature toggles read from the environment)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env: Option<HashMap<String, String>>,
+    /// Timeout in seconds for this mutation's test run, overriding
+    /// `settings.timeout` and `--timeout`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+    /// Mutations sharing a group are applied together and tested in a single
+    /// run, producing one result named after the group (a higher-order mutant)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    /// Name of a `// mutate: <name>` comment in the source; the mutation
+    /// targets the line after it, and without `original` the outermost
+    /// expression on that line
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub anchor: Option<String>,
+    /// Name of an entry in `templates` supplying the `original`,
+    /// `replacement` and `mutation_op` this mutation doesn't set itself
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
 }
 
 fn generate_id() -> String {
@@ -56,7 +331,36 @@ fn generate_id() -> String {
     format!("mutation_{}", COUNTER.fetch_add(1, Ordering::SeqCst))
 }
 
+/// Deserialize a config's mutations, generating an id for each one that
+/// doesn't set its own
+fn deserialize_mutations<'de, D>(deserializer: D) -> Result<Vec<MutationConfig>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let mut mutations = Vec::<MutationConfig>::deserialize(deserializer)?;
+    for mutation in mutations.iter_mut().filter(|m| m.id.is_empty()) {
+        mutation.id = generate_id();
+        mutation.id_generated = true;
+    }
+    Ok(mutations)
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
 impl MutationConfig {
+    /// Options used when matching this mutation's original expression
+    pub fn match_options(&self) -> MatchOptions {
+        MatchOptions {
+            atomic_negative_literals: self.atomic_negative_literals,
+            pub_only: self.pub_only,
+            skip_tests: !self.mutate_tests,
+            leaf_only: self.leaf_only,
+            in_macros: self.in_macros,
+        }
+    }
+
     /// Create a description for this mutation
     pub fn description(&self) -> String {
         format!(
@@ -66,21 +370,473 @@ impl MutationConfig {
     }
 }
 
+/// The config files (`*.yaml`, `*.yml`, `*.toml`) directly in `dir`, sorted
+/// by name
+pub fn config_files_in(dir: &Path) -> Result<Vec<PathBuf>, MutationError> {
+    let entries = std::fs::read_dir(dir).map_err(|e| MutationError::ConfigError {
+        message: format!("Failed to read config directory '{}': {}", dir.display(), e),
+    })?;
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .extension()
+                    .is_some_and(|ext| ext == "yaml" || ext == "yml" || ext == "toml")
+        })
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// Whether the config file at `path` has a `settings` section
+fn has_settings(path: &Path) -> Result<bool, MutationError> {
+    #[derive(Deserialize)]
+    struct Sections {
+        settings: Option<serde::de::IgnoredAny>,
+    }
+    let content = std::fs::read_to_string(path).map_err(|e| MutationError::ConfigError {
+        message: format!("Failed to read config file '{}': {}", path.display(), e),
+    })?;
+    let parsed: Result<Sections, String> = if path.extension().is_some_and(|ext| ext == "toml") {
+        toml::from_str(&content).map_err(|e| e.to_string())
+    } else {
+        serde_yaml::from_str(&content).map_err(|e| e.to_string())
+    };
+    let sections = parsed.map_err(|e| MutationError::ConfigError {
+        message: format!("Failed to parse config file '{}': {}", path.display(), e),
+    })?;
+    Ok(sections.settings.is_some())
+}
+
 impl Config {
-    /// Load configuration from a YAML file
+    /// Load configuration from a file
+    ///
+    /// Files ending in `.toml` are parsed as TOML, anything else as YAML.
     pub fn load(path: &Path) -> Result<Self, MutationError> {
         let content = std::fs::read_to_string(path).map_err(|e| MutationError::ConfigError {
             message: format!("Failed to read config file '{}': {}", path.display(), e),
         })?;
 
-        let config: Config =
-            serde_yaml::from_str(&content).map_err(|e| MutationError::ConfigError {
-                message: format!("Failed to parse config file '{}': {}", path.display(), e),
+        let parsed = if path.extension().is_some_and(|ext| ext == "toml") {
+            toml::from_str(&content).map_err(|e| e.to_string())
+        } else {
+            serde_yaml::from_str(&content).map_err(|e| e.to_string())
+        };
+        let mut config: Config = parsed.map_err(|e| MutationError::ConfigError {
+            message: format!("Failed to parse config file '{}': {}", path.display(), e),
+        })?;
+
+        config.resolve_templates()?;
+        config.resolve_operators()?;
+
+        Ok(config)
+    }
+
+    /// Load every config file in a directory as one configuration
+    ///
+    /// The files ([`config_files_in`]) are loaded in name order and their
+    /// mutations concatenated; templates only apply within their own file.
+    /// Settings come from the first file, so a `settings` section in any
+    /// other file is an error rather than silently ignored, as is a mutation
+    /// id used in more than one file.
+    pub fn load_dir(dir: &Path) -> Result<Self, MutationError> {
+        let files = config_files_in(dir)?;
+        let Some((first, rest)) = files.split_first() else {
+            return Err(MutationError::ConfigError {
+                message: format!(
+                    "No config files (*.yaml, *.yml, *.toml) found in '{}'",
+                    dir.display()
+                ),
+            });
+        };
+
+        let mut config = Config::load(first)?;
+        let mut sources: HashMap<String, PathBuf> = config
+            .mutations
+            .iter()
+            .map(|m| (m.id.clone(), first.clone()))
+            .collect();
+        for path in rest {
+            if has_settings(path)? {
+                return Err(MutationError::ConfigError {
+                    message: format!(
+                        "'{}' has settings, but only the first config file in '{}' ({}) may",
+                        path.display(),
+                        dir.display(),
+                        first.display()
+                    ),
+                });
+            }
+            let other = Config::load(path)?;
+            for mutation in &other.mutations {
+                if let Some(previous) = sources.insert(mutation.id.clone(), path.clone()) {
+                    return Err(MutationError::ConfigError {
+                        message: format!(
+                            "Mutation id '{}' is used in both '{}' and '{}'",
+                            mutation.id,
+                            previous.display(),
+                            path.display()
+                        ),
+                    });
+                }
+            }
+            config.templates.extend(other.templates);
+            config.mutations.extend(other.mutations);
+        }
+        Ok(config)
+    }
+
+    /// Parse a configuration from YAML text, e.g. when embedding or in tests
+    pub fn from_yaml_str(yaml: &str) -> Result<Self, MutationError> {
+        let mut config: Config =
+            serde_yaml::from_str(yaml).map_err(|e| MutationError::ConfigError {
+                message: format!("Failed to parse YAML config: {}", e),
             })?;
+        config.resolve_templates()?;
+        config.resolve_operators()?;
+        Ok(config)
+    }
 
+    /// Parse a configuration from TOML text
+    pub fn from_toml_str(toml: &str) -> Result<Self, MutationError> {
+        let mut config: Config = toml::from_str(toml).map_err(|e| MutationError::ConfigError {
+            message: format!("Failed to parse TOML config: {}", e),
+        })?;
+        config.resolve_templates()?;
+        config.resolve_operators()?;
         Ok(config)
     }
 
+    /// A version 1.0 config of `mutations`, with default settings and no
+    /// templates
+    pub fn from_mutations(mutations: Vec<MutationConfig>) -> Self {
+        Config {
+            version: "1.0".to_string(),
+            settings: Settings::default(),
+            templates: HashMap::new(),
+            mutations,
+        }
+    }
+
+    /// Serialize the configuration as YAML, in the format [`Config::load`]
+    /// reads
+    ///
+    /// Default settings, generated ids and the replacements of mutations
+    /// with a `mutation_op` are left out; loading the result fills them in
+    /// again.
+    pub fn to_yaml_string(&self) -> Result<String, MutationError> {
+        let error = |e: serde_yaml::Error| MutationError::ConfigError {
+            message: format!("Failed to serialize config as YAML: {}", e),
+        };
+        let mut value = serde_yaml::to_value(self).map_err(error)?;
+        let defaults = serde_yaml::to_value(Settings::default()).map_err(error)?;
+        if let (Some(settings), Some(defaults)) = (
+            value.get_mut("settings").and_then(|s| s.as_mapping_mut()),
+            defaults.as_mapping(),
+        ) {
+            settings.retain(|key, value| defaults.get(key) != Some(value));
+        }
+        if let Some(mutations) = value.get_mut("mutations").and_then(|m| m.as_sequence_mut()) {
+            for (mutation, config) in mutations.iter_mut().zip(&self.mutations) {
+                let Some(mutation) = mutation.as_mapping_mut() else {
+                    continue;
+                };
+                if config.id_generated {
+                    mutation.remove("id");
+                }
+                if mutation.contains_key("mutation_op") {
+                    mutation.remove("replacement");
+                }
+            }
+        }
+        serde_yaml::to_string(&value).map_err(error)
+    }
+
+    /// Serialize the configuration as TOML, leaving out the same fields as
+    /// [`Config::to_yaml_string`]
+    pub fn to_toml_string(&self) -> Result<String, MutationError> {
+        let mut value = toml::Value::try_from(self).map_err(|e| MutationError::ConfigError {
+            message: format!("Failed to serialize config as TOML: {}", e),
+        })?;
+        if let Some(mutations) = value.get_mut("mutations").and_then(|m| m.as_array_mut()) {
+            for (mutation, config) in mutations.iter_mut().zip(&self.mutations) {
+                let Some(mutation) = mutation.as_table_mut() else {
+                    continue;
+                };
+                if config.id_generated {
+                    mutation.remove("id");
+                }
+                if mutation.contains_key("mutation_op") {
+                    mutation.remove("replacement");
+                }
+            }
+        }
+        toml::to_string(&value).map_err(|e| MutationError::ConfigError {
+            message: format!("Failed to serialize config as TOML: {}", e),
+        })
+    }
+
+    /// Fill in every mutation that names a `template` from that template
+    ///
+    /// Fields the mutation sets itself take precedence over the template's.
+    pub fn resolve_templates(&mut self) -> Result<(), MutationError> {
+        for mutation in &mut self.mutations {
+            let Some(name) = &mutation.template else {
+                continue;
+            };
+            let Some(template) = self.templates.get(name) else {
+                let mut available: Vec<&str> = self.templates.keys().map(String::as_str).collect();
+                available.sort_unstable();
+                return Err(MutationError::ConfigError {
+                    message: format!(
+                        "Mutation '{}' uses unknown template '{}' (available: {})",
+                        mutation.id,
+                        name,
+                        if available.is_empty() {
+                            "none".to_string()
+                        } else {
+                            available.join(", ")
+                        }
+                    ),
+                });
+            };
+            if mutation.original.is_empty() {
+                mutation.original = template.original.clone();
+            }
+            if mutation.replacement.is_empty() && mutation.mutation_op.is_none() {
+                mutation.replacement = template.replacement.clone();
+                mutation.mutation_op = template.mutation_op;
+            }
+        }
+        Ok(())
+    }
+
+    /// Fill in the replacement of every mutation that names a `mutation_op`
+    ///
+    /// Each mutation must have exactly one of `replacement` or `mutation_op`.
+    pub fn resolve_operators(&mut self) -> Result<(), MutationError> {
+        for mutation in &mut self.mutations {
+            match mutation.mutation_op {
+                Some(op) => {
+                    if !mutation.replacement.is_empty() {
+                        return Err(MutationError::ConfigError {
+                            message: format!(
+                                "Mutation '{}' sets both 'replacement' and 'mutation_op'",
+                                mutation.id
+                            ),
+                        });
+                    }
+                    mutation.replacement = op.replacement_for(&mutation.original)?;
+                }
+                None if mutation.replacement.is_empty() => {
+                    return Err(MutationError::ConfigError {
+                        message: format!(
+                            "Mutation '{}' needs either 'replacement' or 'mutation_op'",
+                            mutation.id
+                        ),
+                    });
+                }
+                None => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Fill in the `line`, and the `original` if omitted, of every mutation
+    /// with an `anchor`, so reports show the code being mutated
+    ///
+    /// Anchors that can't be resolved are left alone; applying the mutation
+    /// reports why.
+    pub fn resolve_anchors(&mut self, project_dir: &Path) {
+        let crate_dir = self.settings.crate_dir(project_dir);
+        for mutation in self.mutations.iter_mut().filter(|m| m.anchor.is_some()) {
+            let resolved = std::fs::read_to_string(crate_dir.join(&mutation.file))
+                .ok()
+                .and_then(|source| resolve_anchor(&source, mutation).ok());
+            if let Some(resolved) = resolved {
+                *mutation = resolved;
+            }
+        }
+    }
+
+    /// Replace every mutation whose `file` is a glob with one mutation per
+    /// matching file that defines the target function outside test code
+    /// (unless the mutation may mutate tests)
+    ///
+    /// Expanded mutations get the id `<id>@<file>`. Files that don't parse are
+    /// kept so the parse error is reported. Running and validating expand
+    /// globs themselves, so this is only needed to look at the mutations
+    /// beforehand.
+    pub fn expand_file_globs(&mut self, project_dir: &Path) -> Result<(), MutationError> {
+        let mut expanded = Vec::with_capacity(self.mutations.len());
+        for mutation in &self.mutations {
+            expanded.extend(self.expand_file_glob(mutation, project_dir)?);
+        }
+        self.mutations = expanded;
+        Ok(())
+    }
+
+    /// The mutations `mutation` stands for: one per file matching its `file`
+    /// glob, as in [`Config::expand_file_globs`], or just itself
+    pub fn expand_file_glob(
+        &self,
+        mutation: &MutationConfig,
+        project_dir: &Path,
+    ) -> Result<Vec<MutationConfig>, MutationError> {
+        if !is_glob(&mutation.file) {
+            return Ok(vec![mutation.clone()]);
+        }
+
+        let crate_dir = self.settings.crate_dir(project_dir);
+        let pattern = mutation.file.to_string_lossy().into_owned();
+        let target = TargetName::parse(&mutation.function);
+        // A file only defining the function in its tests doesn't count
+        let options = MatchOptions {
+            skip_tests: !(mutation.mutate_tests || self.settings.mutate_tests),
+            ..Default::default()
+        };
+        let files: Vec<PathBuf> = glob_files(&crate_dir, &pattern, self.settings.respect_gitignore)?
+            .into_iter()
+            .filter(|file| {
+                std::fs::read_to_string(crate_dir.join(file))
+                    .ok()
+                    .and_then(|source| syn::parse_file(&source).ok())
+                    .is_none_or(|ast| target_exists_with(&ast, &target, &options))
+            })
+            .collect();
+
+        if files.is_empty() {
+            return Err(MutationError::ConfigError {
+                message: format!(
+                    "No files matching '{}' define '{}'",
+                    pattern, mutation.function
+                ),
+            });
+        }
+
+        Ok(files
+            .into_iter()
+            .map(|file| MutationConfig {
+                id: format!("{}@{}", mutation.id, file.display()),
+                file,
+                ..mutation.clone()
+            })
+            .collect())
+    }
+
+    /// Apply `settings.pub_only` to every mutation, then remove and return the
+    /// mutations whose function is not public
+    ///
+    /// Mutations whose file can't be read or parsed are kept so the error is
+    /// reported by validation.
+    pub fn retain_public(&mut self, project_dir: &Path) -> Vec<MutationConfig> {
+        if self.settings.pub_only {
+            for mutation in &mut self.mutations {
+                mutation.pub_only = true;
+            }
+        }
+        self.retain_matching(project_dir, |mutation| mutation.pub_only)
+    }
+
+    /// Apply `settings.mutate_tests` to every mutation, then remove and return
+    /// the mutations whose function is test code, unless they may mutate it
+    ///
+    /// Like [`Config::retain_public`], mutations whose file can't be read or
+    /// parsed are kept.
+    pub fn retain_non_test(&mut self, project_dir: &Path) -> Vec<MutationConfig> {
+        if self.settings.mutate_tests {
+            for mutation in &mut self.mutations {
+                mutation.mutate_tests = true;
+            }
+        }
+        self.retain_matching(project_dir, |mutation| !mutation.mutate_tests)
+    }
+
+    /// Remove and return the `restricted` mutations whose function exists but
+    /// isn't matched with the mutation's match options
+    fn retain_matching(
+        &mut self,
+        project_dir: &Path,
+        restricted: impl Fn(&MutationConfig) -> bool,
+    ) -> Vec<MutationConfig> {
+        let crate_dir = self.settings.crate_dir(project_dir);
+        let (kept, skipped) = self.mutations.drain(..).partition(|mutation| {
+            if !restricted(mutation) {
+                return true;
+            }
+            let Some(ast) = std::fs::read_to_string(crate_dir.join(&mutation.file))
+                .ok()
+                .and_then(|source| syn::parse_file(&source).ok())
+            else {
+                return true;
+            };
+            let target = TargetName::parse(&mutation.function);
+            // Missing functions are kept too, so they're reported as such
+            !target_exists(&ast, &target)
+                || target_exists_with(&ast, &target, &mutation.match_options())
+        });
+        self.mutations = kept;
+        skipped
+    }
+
+    /// Keep at most `settings.max_per_file` mutations per file and
+    /// `settings.max_per_function` per function, removing and returning the
+    /// rest
+    ///
+    /// The first mutations in config order are kept, so the subset is the
+    /// same on every run. Grouped mutations are always kept, so a group is
+    /// never split, and don't count towards the caps.
+    pub fn apply_caps(&mut self) -> Vec<MutationConfig> {
+        let per_file = self.settings.max_per_file;
+        let per_function = self.settings.max_per_function;
+        if per_file.is_none() && per_function.is_none() {
+            return Vec::new();
+        }
+
+        let mut files: HashMap<PathBuf, usize> = HashMap::new();
+        let mut functions: HashMap<(PathBuf, String), usize> = HashMap::new();
+        let (kept, skipped) = self.mutations.drain(..).partition(|mutation| {
+            if mutation.group.is_some() {
+                return true;
+            }
+            let in_file = files.entry(mutation.file.clone()).or_default();
+            let in_function = functions
+                .entry((mutation.file.clone(), mutation.function.clone()))
+                .or_default();
+            if per_file.is_some_and(|max| *in_file >= max)
+                || per_function.is_some_and(|max| *in_function >= max)
+            {
+                return false;
+            }
+            *in_file += 1;
+            *in_function += 1;
+            true
+        });
+        self.mutations = kept;
+        skipped
+    }
+
+    /// The `cfg` predicates gating each mutation's function, in the order of
+    /// `mutations`
+    ///
+    /// Gated code is only compiled, and so only exercised by the tests, if the
+    /// test run enables the predicate. Files that can't be read or parsed give
+    /// no predicates.
+    pub fn cfg_gates(&self, project_dir: &Path) -> Vec<Vec<String>> {
+        let crate_dir = self.settings.crate_dir(project_dir);
+        self.mutations
+            .iter()
+            .map(|mutation| {
+                std::fs::read_to_string(crate_dir.join(&mutation.file))
+                    .ok()
+                    .and_then(|source| syn::parse_file(&source).ok())
+                    .map(|ast| target_cfgs(&ast, &TargetName::parse(&mutation.function)))
+                    .unwrap_or_default()
+            })
+            .collect()
+    }
+
     /// Validate all mutations in the configuration
     pub fn validate(&self) -> Result<(), Vec<MutationError>> {
         let mut errors = Vec::new();
@@ -136,13 +892,387 @@ mutations:
     replacement: a - b
 "#;
 
-        let config: Config = serde_yaml::from_str(yaml).unwrap();
+        let config = Config::from_yaml_str(yaml).unwrap();
         assert_eq!(config.version, "1.0");
         assert_eq!(config.settings.timeout, 60);
         assert_eq!(config.mutations.len(), 1);
         assert_eq!(config.mutations[0].function, "add");
     }
 
+    #[test]
+    fn test_from_toml_str() {
+        let toml = r#"
+version = "1.0"
+
+[settings]
+timeout = 60
+
+[[mutations]]
+file = "src/math.rs"
+function = "add"
+original = "return a + b"
+mutation_op = "return_default"
+"#;
+
+        let config = Config::from_toml_str(toml).unwrap();
+        assert_eq!(config.settings.timeout, 60);
+        assert_eq!(config.mutations[0].replacement, "return Default::default()");
+
+        let result = Config::from_toml_str("version = ");
+        assert!(matches!(result, Err(MutationError::ConfigError { .. })));
+        let result = Config::from_yaml_str("mutations: []");
+        assert!(result.unwrap_err().to_string().contains("missing field `version`"));
+    }
+
+    #[test]
+    fn test_timeout_precedence() {
+        let yaml = r#"
+version: "1.0"
+settings:
+  timeout: 30
+mutations:
+  - file: src/math.rs
+    function: add
+    original: a + b
+    replacement: a - b
+    timeout: 120
+  - file: src/math.rs
+    function: sub
+    original: a - b
+    replacement: a + b
+"#;
+        let mut config = Config::from_yaml_str(yaml).unwrap();
+        let (slow, other) = (&config.mutations[0], &config.mutations[1]);
+        assert_eq!(config.settings.timeout_for(slow), Duration::from_secs(120));
+        assert_eq!(config.settings.timeout_for(other), Duration::from_secs(30));
+
+        // `--timeout` replaces `settings.timeout`, but not a mutation's own
+        config.settings.timeout = 60;
+        let (slow, other) = (&config.mutations[0], &config.mutations[1]);
+        assert_eq!(config.settings.timeout_for(slow), Duration::from_secs(120));
+        assert_eq!(config.settings.timeout_for(other), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn test_resolve_return_default() {
+        let yaml = r#"
+version: "1.0"
+mutations:
+  - file: src/math.rs
+    function: add
+    original: return a + b
+    mutation_op: return_default
+"#;
+        let mut config: Config = serde_yaml::from_str(yaml).unwrap();
+        config.resolve_operators().unwrap();
+        assert_eq!(config.mutations[0].mutation_op, Some(MutationOp::ReturnDefault));
+        assert_eq!(config.mutations[0].replacement, "return Default::default()");
+    }
+
+    #[test]
+    fn test_load_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("a-math.yaml"),
+            r#"
+version: "1.0"
+settings:
+  timeout: 90
+mutations:
+  - id: plus
+    file: src/math.rs
+    function: add
+    original: a + b
+    replacement: a - b
+"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("b-checks.toml"),
+            r#"
+version = "1.0"
+
+[[mutations]]
+id = "adult"
+file = "src/checks.rs"
+function = "is_adult"
+original = "age >= 18"
+replacement = "age > 18"
+"#,
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("notes.md"), "not a config").unwrap();
+
+        let config = Config::load_dir(dir.path()).unwrap();
+        let ids: Vec<&str> = config.mutations.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, ["plus", "adult"]);
+        assert_eq!(config.settings.timeout, 90);
+
+        // Settings outside the first file would be ignored, so they're refused
+        let settings = "version: \"1.0\"\nsettings:\n  timeout: 5\nmutations: []\n";
+        std::fs::write(dir.path().join("c-more.yml"), settings).unwrap();
+        let error = Config::load_dir(dir.path()).unwrap_err().to_string();
+        assert!(error.contains("c-more.yml' has settings"), "{}", error);
+
+        // As are ids used in more than one file
+        let duplicate = "version: \"1.0\"\nmutations:\n  - id: plus\n    file: src/x.rs\n    \
+                         function: f\n    original: a\n    replacement: b\n";
+        std::fs::write(dir.path().join("c-more.yml"), duplicate).unwrap();
+        let error = Config::load_dir(dir.path()).unwrap_err().to_string();
+        assert!(error.contains("'plus' is used in both"), "{}", error);
+
+        let empty = tempfile::tempdir().unwrap();
+        assert!(Config::load_dir(empty.path()).is_err());
+    }
+
+    #[test]
+    fn test_expand_file_globs() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/a.rs"), "fn add(a: i32) -> i32 { a + 1 }").unwrap();
+        std::fs::write(dir.path().join("src/b.rs"), "fn sub(a: i32) -> i32 { a - 1 }").unwrap();
+
+        let yaml = r#"
+version: "1.0"
+mutations:
+  - id: plus
+    file: src/*.rs
+    function: add
+    original: a + 1
+    replacement: a - 1
+"#;
+        let mut config: Config = serde_yaml::from_str(yaml).unwrap();
+        config.expand_file_globs(dir.path()).unwrap();
+
+        // Only the file defining `add` is kept
+        assert_eq!(config.mutations.len(), 1);
+        assert_eq!(config.mutations[0].file, PathBuf::from("src/a.rs"));
+        assert_eq!(config.mutations[0].id, "plus@src/a.rs");
+
+        config.mutations[0].file = PathBuf::from("src/*.rs");
+        config.mutations[0].function = "mul".to_string();
+        assert!(config.expand_file_globs(dir.path()).is_err());
+    }
+
+    #[test]
+    fn test_resolve_templates() {
+        let yaml = r#"
+version: "1.0"
+templates:
+  flip_ge:
+    original: age >= 18
+    replacement: age > 18
+  default_return:
+    original: return total
+    mutation_op: return_default
+mutations:
+  - file: src/a.rs
+    function: is_adult
+    template: flip_ge
+  - file: src/b.rs
+    function: can_vote
+    template: flip_ge
+    replacement: age >= 21
+  - file: src/c.rs
+    function: sum
+    template: default_return
+"#;
+        let config = Config::from_yaml_str(yaml).unwrap();
+        let pairs: Vec<(&str, &str)> = config
+            .mutations
+            .iter()
+            .map(|m| (m.original.as_str(), m.replacement.as_str()))
+            .collect();
+        assert_eq!(
+            pairs,
+            [
+                ("age >= 18", "age > 18"),
+                ("age >= 18", "age >= 21"),
+                ("return total", "return Default::default()"),
+            ]
+        );
+
+        let unknown = yaml.replace("template: default_return", "template: missing");
+        let error = Config::from_yaml_str(&unknown).unwrap_err().to_string();
+        assert!(error.contains("unknown template 'missing' (available: default_return, flip_ge)"));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let yaml = r#"
+version: "1.0"
+settings:
+  timeout: 60
+  test_kinds: [unit, doc]
+templates:
+  flip_ge:
+    original: age >= 18
+    replacement: age > 18
+mutations:
+  - id: adult
+    file: src/a.rs
+    function: is_adult
+    template: flip_ge
+  - id: sum_default
+    file: src/c.rs
+    function: sum
+    original: return total
+    mutation_op: return_default
+  - id: add
+    file: src/math.rs
+    function: add
+    original: a + b
+    replacement: a - b
+    line: 3
+    killed_by: [tests::test_add]
+    env:
+      MODE: strict
+"#;
+        let config = Config::from_yaml_str(yaml).unwrap();
+
+        let reloaded = Config::from_yaml_str(&config.to_yaml_string().unwrap()).unwrap();
+        assert_eq!(reloaded, config);
+        let reloaded = Config::from_toml_str(&config.to_toml_string().unwrap()).unwrap();
+        assert_eq!(reloaded, config);
+
+        // Generated ids and default settings are left out
+        let generated = Config::from_mutations(vec![MutationConfig {
+            file: PathBuf::from("src/math.rs"),
+            function: "add".to_string(),
+            original: "a + b".to_string(),
+            replacement: "a - b".to_string(),
+            id: generate_id(),
+            id_generated: true,
+            ..Default::default()
+        }]);
+        assert_eq!(
+            generated.to_yaml_string().unwrap(),
+            "version: '1.0'\nmutations:\n- file: src/math.rs\n  function: add\n  \
+             original: a + b\n  replacement: a - b\n"
+        );
+        let mut generated = generated;
+        generated.settings.source_root = Some(PathBuf::from("member"));
+        assert!(generated
+            .to_yaml_string()
+            .unwrap()
+            .starts_with("version: '1.0'\nsettings:\n  source_root: member\nmutations:\n"));
+
+        // Only ids the config left out count as generated, whatever they look like
+        let yaml = r#"
+version: "1.0"
+mutations:
+  - id: mutation_3
+    file: src/math.rs
+    function: add
+    original: a + b
+    replacement: a - b
+  - file: src/math.rs
+    function: add
+    original: a + b
+    replacement: a * b
+"#;
+        let config = Config::from_yaml_str(yaml).unwrap();
+        assert!(!config.mutations[0].id_generated);
+        assert!(config.mutations[1].id_generated);
+        assert!(config.mutations[1].id.starts_with("mutation_"));
+        let written = config.to_yaml_string().unwrap();
+        assert!(written.contains("id: mutation_3"));
+        assert_eq!(written.matches("id:").count(), 1);
+        let written = config.to_toml_string().unwrap();
+        assert_eq!(written.matches("id = ").count(), 1);
+    }
+
+    #[test]
+    fn test_apply_caps() {
+        let mutation = |file: &str, function: &str, id: &str| MutationConfig {
+            file: PathBuf::from(file),
+            function: function.to_string(),
+            original: "a + b".to_string(),
+            replacement: "a - b".to_string(),
+            id: id.to_string(),
+            ..Default::default()
+        };
+        let mut config = Config::from_yaml_str("version: \"1.0\"\nmutations: []").unwrap();
+        config.mutations = vec![
+            mutation("src/a.rs", "add", "a1"),
+            mutation("src/a.rs", "add", "a2"),
+            mutation("src/a.rs", "add", "a3"),
+            mutation("src/a.rs", "sub", "a4"),
+            mutation("src/a.rs", "mul", "a5"),
+            mutation("src/b.rs", "add", "b1"),
+            MutationConfig {
+                group: Some("pair".to_string()),
+                ..mutation("src/a.rs", "add", "g1")
+            },
+        ];
+        let ids = |mutations: &[MutationConfig]| {
+            mutations.iter().map(|m| m.id.clone()).collect::<Vec<_>>()
+        };
+
+        // No caps: nothing is skipped
+        assert!(config.apply_caps().is_empty());
+        assert_eq!(config.mutations.len(), 7);
+
+        config.settings.max_per_function = Some(2);
+        assert_eq!(ids(&config.apply_caps()), ["a3"]);
+
+        config.settings.max_per_file = Some(3);
+        assert_eq!(ids(&config.apply_caps()), ["a5"]);
+        assert_eq!(ids(&config.mutations), ["a1", "a2", "a4", "b1", "g1"]);
+
+        // Deterministic: applying the same caps again keeps the same subset
+        assert!(config.apply_caps().is_empty());
+    }
+
+    #[test]
+    fn test_resolve_requires_one_replacement() {
+        let yaml = r#"
+version: "1.0"
+mutations:
+  - file: src/math.rs
+    function: add
+    original: a + b
+"#;
+        let mut config: Config = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.resolve_operators().is_err());
+
+        config.mutations[0].replacement = "a - b".to_string();
+        config.mutations[0].mutation_op = Some(MutationOp::ReturnDefault);
+        assert!(config.resolve_operators().is_err());
+    }
+
+    #[test]
+    fn test_crate_dir() {
+        let mut settings = Settings::default();
+        assert_eq!(settings.crate_dir(Path::new("repo")), PathBuf::from("repo"));
+
+        settings.source_root = Some(PathBuf::from("crates/core"));
+        let crate_dir = settings.crate_dir(Path::new("repo"));
+        assert_eq!(crate_dir, PathBuf::from("repo/crates/core"));
+        assert_eq!(
+            crate_dir.join("src/lib.rs"),
+            PathBuf::from("repo/crates/core/src/lib.rs")
+        );
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("30m"), Ok(Duration::from_secs(30 * 60)));
+        assert_eq!(parse_duration("2h"), Ok(Duration::from_secs(2 * 60 * 60)));
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("m").is_err());
+        assert!(parse_duration("1.5h").is_err());
+        assert!(parse_duration("10d").is_err());
+        assert!(parse_duration("0").is_err());
+        assert!(parse_duration("0m").is_err());
+        assert_eq!(
+            parse_duration("18446744073709551615h"),
+            Err("duration '18446744073709551615h' is too large".to_string())
+        );
+    }
+
     #[test]
     fn test_default_timeout() {
         let yaml = r#"
@@ -0,0 +1,130 @@
+//! Line coverage from LCOV files
+//!
+//! Mutants on lines the tests never execute can't be killed, so running them
+//! only adds noise. With coverage data (e.g. from `cargo llvm-cov --lcov`),
+//! such mutations are reported as not covered without running the tests.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::error::{MutationError, Result};
+
+/// Hit counts per line of each source file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Coverage {
+    files: HashMap<PathBuf, HashMap<usize, u64>>,
+}
+
+impl Coverage {
+    /// Parse LCOV tracefile data
+    ///
+    /// Only `SF` (source file) and `DA` (line hits) records are read. Hits
+    /// for a file recorded several times, e.g. once per test binary, are
+    /// added up.
+    pub fn parse(lcov: &str) -> Self {
+        let mut coverage = Coverage::default();
+        let mut current: Option<PathBuf> = None;
+        for line in lcov.lines().map(str::trim) {
+            if let Some(file) = line.strip_prefix("SF:") {
+                let path = PathBuf::from(file);
+                current = Some(match path.canonicalize() {
+                    Ok(canonical) if path.is_absolute() => canonical,
+                    _ => path,
+                });
+            } else if let Some(data) = line.strip_prefix("DA:") {
+                let (Some(file), Some((line, hits))) = (&current, parse_line_hits(data)) else {
+                    continue;
+                };
+                let lines = coverage.files.entry(file.clone()).or_default();
+                *lines.entry(line).or_default() += hits;
+            } else if line == "end_of_record" {
+                current = None;
+            }
+        }
+        coverage
+    }
+
+    /// Load an LCOV file
+    pub fn load(path: &Path) -> Result<Self> {
+        let lcov = std::fs::read_to_string(path).map_err(|e| MutationError::FileReadError {
+            file: path.to_path_buf(),
+            error: e.to_string(),
+        })?;
+        Ok(Self::parse(&lcov))
+    }
+
+    /// How often `line` of `file` was executed, or `None` if the coverage
+    /// data says nothing about it
+    ///
+    /// Absolute paths in the data must name the same file; relative ones
+    /// match any `file` they are a suffix of, as long as no other relative
+    /// path in the data is one too. Data that could be for several files
+    /// says nothing about any of them.
+    pub fn hits(&self, file: &Path, line: usize) -> Option<u64> {
+        let file = file.canonicalize().unwrap_or_else(|_| file.to_path_buf());
+        let lines = match self.files.get(&file) {
+            Some(lines) => lines,
+            None => {
+                let mut suffixes = self
+                    .files
+                    .iter()
+                    .filter(|(path, _)| path.is_relative() && file.ends_with(path));
+                match (suffixes.next(), suffixes.next()) {
+                    (Some((_, lines)), None) => lines,
+                    _ => return None,
+                }
+            }
+        };
+        lines.get(&line).copied()
+    }
+
+    /// Whether the coverage data shows `line` of `file` was never executed
+    ///
+    /// Lines without data (files that weren't instrumented, or lines holding
+    /// no executable code) count as covered, so they are still tested.
+    pub fn is_uncovered(&self, file: &Path, line: usize) -> bool {
+        self.hits(file, line) == Some(0)
+    }
+}
+
+/// Parse the `<line>,<hits>[,<checksum>]` of a `DA` record
+fn parse_line_hits(data: &str) -> Option<(usize, u64)> {
+    let mut fields = data.split(',');
+    let line = fields.next()?.trim().parse().ok()?;
+    let hits = fields.next()?.trim().parse().ok()?;
+    Some((line, hits))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_lcov() {
+        let lcov = "TN:\nSF:src/lib.rs\nFN:1,add\nDA:1,3\nDA:2,0\nDA:4,1,abc\nend_of_record\n\
+                    SF:src/lib.rs\nDA:2,0\nDA:4,2\nend_of_record\n\
+                    SF:/elsewhere/src/other.rs\nDA:1,0\nend_of_record\n";
+        let coverage = Coverage::parse(lcov);
+        let lib = Path::new("/project/src/lib.rs");
+
+        assert_eq!(coverage.hits(lib, 1), Some(3));
+        assert_eq!(coverage.hits(lib, 4), Some(3));
+        assert!(coverage.is_uncovered(lib, 2));
+        // No data for the line or the file
+        assert!(!coverage.is_uncovered(lib, 3));
+        assert!(!coverage.is_uncovered(Path::new("/project/src/other.rs"), 1));
+        assert!(coverage.is_uncovered(Path::new("/elsewhere/src/other.rs"), 1));
+    }
+
+    #[test]
+    fn test_ambiguous_suffix() {
+        let lcov = "SF:src/lib.rs\nDA:1,0\nend_of_record\n\
+                    SF:core/src/lib.rs\nDA:1,5\nend_of_record\n";
+        let coverage = Coverage::parse(lcov);
+
+        // Both paths are suffixes of a file in `core`
+        assert_eq!(coverage.hits(Path::new("/ws/core/src/lib.rs"), 1), None);
+        assert!(!coverage.is_uncovered(Path::new("/ws/core/src/lib.rs"), 1));
+        assert_eq!(coverage.hits(Path::new("/ws/cli/src/lib.rs"), 1), Some(0));
+    }
+}
@@ -1,10 +1,16 @@
 //! Error types for mutation testing
 
 use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 /// Errors that can occur during mutation testing
-#[derive(Debug, Error)]
+///
+/// Serialized with a `kind` tag (e.g. `{"kind": "no_match", ...}`), so the
+/// JSON report says why a mutation couldn't be applied.
+#[derive(Debug, Clone, PartialEq, Error, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
 pub enum MutationError {
     /// Original code couldn't be parsed as valid Rust
     #[error("Invalid original expression: '{code}'\n  Parse error: {parse_error}")]
@@ -27,19 +33,41 @@ pub enum MutationError {
     ParseError { file: PathBuf, error: String },
 
     /// Target function not found in file
-    #[error("Function '{function}' not found in {}\n  Available functions: {}", file.display(), available_functions.join(", "))]
+    #[error("Function '{function}' not found in {}\n  Available functions: {}{}", file.display(), available_functions.join(", "), format_macro_hint(macro_sites))]
     FunctionNotFound {
         file: PathBuf,
         function: String,
         available_functions: Vec<String>,
+        /// Items in the file carrying macros that may generate the function
+        macro_sites: Vec<String>,
     },
 
     /// Original expression not found in function
-    #[error("Expression '{original}' not found in function '{function}'")]
+    #[error("Expression '{original}' not found in function '{function}'{}", format_suggestions(suggestions))]
     NoMatch {
         file: PathBuf,
         function: String,
         original: String,
+        /// Expressions in the function resembling `original`, to copy from
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        suggestions: Vec<NearMatch>,
+    },
+
+    /// Original expression not found exactly, but similar code exists (loose mode)
+    #[error("Expression '{original}' not found in function '{function}', but found near-matches:\n{}\n  Near-matches are never mutated; copy the exact code into `original`", format_near_matches(candidates))]
+    NearMatches {
+        function: String,
+        original: String,
+        candidates: Vec<NearMatch>,
+    },
+
+    /// Original expression found in function, but not on the requested line
+    #[error("Expression '{original}' not found on line {line} of function '{function}'\n  Found on lines: {}", format_lines(found_lines))]
+    NoMatchOnLine {
+        function: String,
+        original: String,
+        line: usize,
+        found_lines: Vec<usize>,
     },
 
     /// Multiple matches found (ambiguous)
@@ -51,6 +79,30 @@ pub enum MutationError {
         locations: Vec<MatchLocation>,
     },
 
+    /// No `// mutate: <anchor>` comment marks the mutation's code
+    #[error("Anchor '// mutate: {anchor}' not found in {}", file.display())]
+    AnchorNotFound { file: PathBuf, anchor: String },
+
+    /// Several `// mutate: <anchor>` comments use the same name
+    #[error("Anchor '// mutate: {anchor}' appears more than once in {}\n  Marked lines: {}", file.display(), format_lines(lines))]
+    AmbiguousAnchor {
+        file: PathBuf,
+        anchor: String,
+        lines: Vec<usize>,
+    },
+
+    /// Another run holds the crate's lock file
+    #[error("Another mutation testing run is in progress{} (lock file: {})\n  If that process isn't a mutation testing run, remove the lock with `clean --force`", format_pid(pid), lock.display())]
+    RunInProgress { lock: PathBuf, pid: Option<u32> },
+
+    /// Two mutations of a group change overlapping code in the same file
+    #[error("Mutations '{}' of the group change overlapping code in {}\n  Locations: {}\n  Applying one would move or replace the code the other targets; put them in separate groups", mutations.join("' and '"), file.display(), format_locations(locations))]
+    OverlappingMutations {
+        file: PathBuf,
+        mutations: Vec<String>,
+        locations: Vec<MatchLocation>,
+    },
+
     /// Failed to apply mutation
     #[error("Failed to apply mutation: {reason}")]
     FailedToApply { reason: String },
@@ -59,22 +111,88 @@ pub enum MutationError {
     #[error("Failed to write mutated file '{}': {error}", file.display())]
     WriteError { file: PathBuf, error: String },
 
+    /// A required program (e.g. `cargo`) couldn't be started
+    #[error("`{program}` not found on PATH")]
+    CommandNotFound { program: String },
+
+    /// The mutated file is compiled into `settings.test_binary`, so running
+    /// the prebuilt binary wouldn't test the mutant
+    #[error("{} is compiled into the test binary {}; testing a mutant of it needs a rebuild, which settings.test_binary skips\n  Remove settings.test_binary to test it with cargo test", file.display(), binary.display())]
+    RecompilationRequired { file: PathBuf, binary: PathBuf },
+
     /// Test execution failed
     #[error("Test execution failed: {error}")]
     TestExecutionError { error: String },
 
+    /// The tests failed with an exit code `settings.kill_on_exit_codes`
+    /// doesn't count as a kill, so it's unknown whether they caught the mutant
+    #[error("Tests failed with {}, which settings.kill_on_exit_codes doesn't count as a kill", format_exit_code(code))]
+    UnexpectedExitCode { code: Option<i32> },
+
     /// Configuration error
     #[error("Configuration error: {message}")]
     ConfigError { message: String },
 }
 
+impl MutationError {
+    /// Short name of the error's cause, as used for `kind` in the JSON report
+    pub fn kind(&self) -> &'static str {
+        match self {
+            MutationError::InvalidOriginal { .. } => "invalid_original",
+            MutationError::InvalidReplacement { .. } => "invalid_replacement",
+            MutationError::FileNotFound { .. } => "file_not_found",
+            MutationError::FileReadError { .. } => "file_read_error",
+            MutationError::ParseError { .. } => "parse_error",
+            MutationError::FunctionNotFound { .. } => "function_not_found",
+            MutationError::NoMatch { .. } => "no_match",
+            MutationError::NearMatches { .. } => "near_matches",
+            MutationError::NoMatchOnLine { .. } => "no_match_on_line",
+            MutationError::AmbiguousMatch { .. } => "ambiguous_match",
+            MutationError::AnchorNotFound { .. } => "anchor_not_found",
+            MutationError::AmbiguousAnchor { .. } => "ambiguous_anchor",
+            MutationError::RunInProgress { .. } => "run_in_progress",
+            MutationError::OverlappingMutations { .. } => "overlapping_mutations",
+            MutationError::FailedToApply { .. } => "failed_to_apply",
+            MutationError::WriteError { .. } => "write_error",
+            MutationError::CommandNotFound { .. } => "command_not_found",
+            MutationError::RecompilationRequired { .. } => "recompilation_required",
+            MutationError::TestExecutionError { .. } => "test_execution_error",
+            MutationError::UnexpectedExitCode { .. } => "unexpected_exit_code",
+            MutationError::ConfigError { .. } => "config_error",
+        }
+    }
+}
+
 /// A location where a match was found
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct MatchLocation {
     pub line: usize,
     pub column: usize,
 }
 
+/// Code that loosely matches an `original` with no exact match
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NearMatch {
+    pub line: usize,
+    pub column: usize,
+    pub code: String,
+}
+
+fn format_near_matches(candidates: &[NearMatch]) -> String {
+    candidates
+        .iter()
+        .map(|c| format!("    line {}, column {}: {}", c.line, c.column, c.code))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn format_suggestions(suggestions: &[NearMatch]) -> String {
+    if suggestions.is_empty() {
+        return String::new();
+    }
+    format!("\n  Expressions in the function:\n{}", format_near_matches(suggestions))
+}
+
 fn format_locations(locations: &[MatchLocation]) -> String {
     locations
         .iter()
@@ -83,5 +201,37 @@ fn format_locations(locations: &[MatchLocation]) -> String {
         .join("; ")
 }
 
+fn format_macro_hint(macro_sites: &[String]) -> String {
+    if macro_sites.is_empty() {
+        return String::new();
+    }
+    format!(
+        "\n  Hint: functions generated by macros can't be matched from source; this file uses: {}",
+        macro_sites.join(", ")
+    )
+}
+
+fn format_pid(pid: &Option<u32>) -> String {
+    match pid {
+        Some(pid) => format!(" in process {}", pid),
+        None => String::new(),
+    }
+}
+
+fn format_exit_code(code: &Option<i32>) -> String {
+    match code {
+        Some(code) => format!("exit code {}", code),
+        None => "a signal".to_string(),
+    }
+}
+
+fn format_lines(lines: &[usize]) -> String {
+    lines
+        .iter()
+        .map(|line| line.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 /// Result type for mutation operations
 pub type Result<T> = std::result::Result<T, MutationError>;
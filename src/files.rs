@@ -0,0 +1,191 @@
+//! File glob expansion
+//!
+//! A mutation's `file` may be a glob such as `src/**/*.rs`. This module finds
+//! the files it matches, skipping gitignored paths (generated code, vendored
+//! crates) unless asked not to.
+
+use std::path::{Path, PathBuf};
+
+use globset::GlobBuilder;
+use ignore::WalkBuilder;
+
+use crate::error::{MutationError, Result};
+
+/// Find the project root for a command run from `start`, like cargo does
+///
+/// This is the package containing `start` (see [`find_package_root`]), or, if
+/// the nearest workspace above it lists the package in its `members` and
+/// doesn't `exclude` it, that workspace's root. Returns `None` outside any
+/// package.
+pub fn find_project_root(start: &Path) -> Option<PathBuf> {
+    let package = find_package_root(start)?;
+    let workspace = package
+        .ancestors()
+        .find_map(|dir| workspace_table(&dir.join("Cargo.toml")).map(|table| (dir, table)));
+    match workspace {
+        Some((root, table)) if workspace_includes(root, &table, &package) => {
+            Some(root.to_path_buf())
+        }
+        _ => Some(package),
+    }
+}
+
+/// The nearest directory at or above `start` with a `Cargo.toml`
+pub fn find_package_root(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .find(|dir| dir.join("Cargo.toml").is_file())
+        .map(Path::to_path_buf)
+}
+
+/// The `[workspace]` table of the manifest at `path`, if it declares one
+fn workspace_table(path: &Path) -> Option<toml::Table> {
+    let manifest = std::fs::read_to_string(path).ok()?.parse::<toml::Table>().ok()?;
+    match manifest.get("workspace")? {
+        toml::Value::Table(table) => Some(table.clone()),
+        _ => None,
+    }
+}
+
+/// Whether the workspace rooted at `root` includes the package in `package`
+///
+/// The root package always belongs to its workspace; any other package must
+/// match a `members` glob and not be under an `exclude` path.
+fn workspace_includes(root: &Path, workspace: &toml::Table, package: &Path) -> bool {
+    let Ok(relative) = package.strip_prefix(root) else {
+        return false;
+    };
+    if relative.as_os_str().is_empty() {
+        return true;
+    }
+    let paths = |key: &str| -> Vec<String> {
+        workspace
+            .get(key)
+            .and_then(|value| value.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|value| value.as_str())
+            .map(|path| path.trim_start_matches("./").trim_end_matches('/').to_string())
+            .collect()
+    };
+    if paths("exclude")
+        .iter()
+        .any(|excluded| relative.starts_with(excluded))
+    {
+        return false;
+    }
+    paths("members").iter().any(|member| {
+        GlobBuilder::new(member)
+            .literal_separator(true)
+            .build()
+            .is_ok_and(|glob| glob.compile_matcher().is_match(relative))
+    })
+}
+
+/// Whether a configured `file` is a glob rather than a plain path
+pub fn is_glob(file: &Path) -> bool {
+    file.to_string_lossy().contains(['*', '?', '[', '{'])
+}
+
+/// List the files under `crate_dir` matching `pattern`, relative to `crate_dir`
+///
+/// `*` doesn't cross directory boundaries; use `**` for that. Hidden files and
+/// directories are never matched.
+pub fn glob_files(crate_dir: &Path, pattern: &str, respect_gitignore: bool) -> Result<Vec<PathBuf>> {
+    let matcher = GlobBuilder::new(pattern)
+        .literal_separator(true)
+        .build()
+        .map_err(|e| MutationError::ConfigError {
+            message: format!("Invalid file glob '{}': {}", pattern, e),
+        })?
+        .compile_matcher();
+
+    let walker = WalkBuilder::new(crate_dir)
+        .git_ignore(respect_gitignore)
+        .git_exclude(respect_gitignore)
+        .git_global(respect_gitignore)
+        .ignore(respect_gitignore)
+        .parents(respect_gitignore)
+        // Honor .gitignore even if the crate isn't a git checkout (yet)
+        .require_git(false)
+        .build();
+
+    let mut files = Vec::new();
+    for entry in walker.flatten() {
+        if !entry.file_type().is_some_and(|t| t.is_file()) {
+            continue;
+        }
+        let relative = entry.path().strip_prefix(crate_dir).unwrap_or(entry.path());
+        if matcher.is_match(relative) {
+            files.push(relative.to_path_buf());
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_skips_gitignored_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let crate_dir = dir.path();
+        std::fs::create_dir_all(crate_dir.join("src/math")).unwrap();
+        std::fs::write(crate_dir.join("src/lib.rs"), "").unwrap();
+        std::fs::write(crate_dir.join("src/math/add.rs"), "").unwrap();
+        std::fs::write(crate_dir.join("src/generated.rs"), "").unwrap();
+        std::fs::write(crate_dir.join(".gitignore"), "src/generated.rs\n").unwrap();
+
+        assert!(is_glob(Path::new("src/**/*.rs")));
+        assert!(!is_glob(Path::new("src/lib.rs")));
+
+        let files = glob_files(crate_dir, "src/**/*.rs", true).unwrap();
+        assert_eq!(
+            files,
+            vec![PathBuf::from("src/lib.rs"), PathBuf::from("src/math/add.rs")]
+        );
+
+        let files = glob_files(crate_dir, "src/*.rs", false).unwrap();
+        assert_eq!(
+            files,
+            vec![PathBuf::from("src/generated.rs"), PathBuf::from("src/lib.rs")]
+        );
+    }
+
+    #[test]
+    fn test_find_project_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let workspace = dir.path().join("workspace");
+        let member = workspace.join("crates/core");
+        std::fs::create_dir_all(member.join("src/nested")).unwrap();
+        std::fs::write(member.join("Cargo.toml"), "[package]\nname = \"core\"\n").unwrap();
+
+        // A lone package: its own directory, from anywhere inside it
+        assert_eq!(find_project_root(&member.join("src/nested")), Some(member.clone()));
+        assert_eq!(find_project_root(&member), Some(member.clone()));
+
+        // Inside a workspace: the workspace root
+        let manifest = "[workspace]\nmembers = [\"crates/*\"]\n";
+        std::fs::write(workspace.join("Cargo.toml"), manifest).unwrap();
+        assert_eq!(find_project_root(&member.join("src")), Some(workspace.clone()));
+        assert_eq!(find_package_root(&member.join("src")), Some(member.clone()));
+
+        // Not a member, or excluded: the package on its own
+        let manifest = "[workspace]\nmembers = [\"tools/*\"]\n";
+        std::fs::write(workspace.join("Cargo.toml"), manifest).unwrap();
+        assert_eq!(find_project_root(&member.join("src")), Some(member.clone()));
+        let manifest = "[workspace]\nmembers = [\"crates/*\"]\nexclude = [\"crates/core\"]\n";
+        std::fs::write(workspace.join("Cargo.toml"), manifest).unwrap();
+        assert_eq!(find_project_root(&member.join("src")), Some(member.clone()));
+
+        // The root package of a workspace
+        let manifest = "[package]\nname = \"app\"\n\n[workspace]\n";
+        std::fs::write(workspace.join("Cargo.toml"), manifest).unwrap();
+        assert_eq!(find_project_root(&workspace), Some(workspace.clone()));
+
+        assert_eq!(find_project_root(dir.path()), None);
+    }
+}
@@ -0,0 +1,152 @@
+//! HTML report
+//!
+//! A single self-contained page: the summary, a heatmap of how well each
+//! function's mutants were killed, and the full list of results. Styling is
+//! inline CSS so the file can be opened or attached anywhere.
+
+use std::fmt::Write;
+
+use crate::report::{format_duration, FunctionScore, MutationReport};
+use crate::runner::MutationStatus;
+
+const STYLE: &str = "body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; margin-bottom: 2em; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.8em; text-align: left; }
+td.num { text-align: right; }
+code { font-size: 0.9em; }";
+
+/// Render the report as an HTML page
+pub fn render(report: &MutationReport) -> String {
+    let mut html = String::new();
+    html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    html.push_str("<title>Mutation Testing Report</title>\n");
+    let _ = writeln!(html, "<style>\n{}\n</style>\n</head>\n<body>", STYLE);
+    html.push_str("<h1>Mutation Testing Report</h1>\n");
+
+    let _ = writeln!(
+        html,
+        "<p>Mutation score: <strong>{:.1}%</strong> ({} killed, {} survived, {} total) in {}</p>",
+        report.score(),
+        report.killed(),
+        report.survived(),
+        report.total(),
+        format_duration(report.total_duration)
+    );
+    let _ = writeln!(html, "<p><small>{}</small></p>", escape(&report.explain_score()));
+
+    html.push_str("<h2>Functions</h2>\n");
+    html.push_str(&heatmap(&report.score_by_function()));
+
+    html.push_str("<h2>Mutations</h2>\n<table>\n");
+    html.push_str("<tr><th>Status</th><th>Id</th><th>Mutation</th><th>Location</th></tr>\n");
+    for result in &report.results {
+        let _ = writeln!(
+            html,
+            "<tr><td>{}{}</td><td>{}</td><td><code>{}</code> &rarr; <code>{}</code></td><td>{} in <code>{}</code></td></tr>",
+            status_label(&result.status),
+            result
+                .killed_by_test
+                .as_ref()
+                .map(|test| format!(" by <code>{}</code>", escape(test)))
+                .unwrap_or_default(),
+            escape(&result.mutation_id),
+            escape(&result.original),
+            escape(&result.replacement),
+            escape(&result.location()),
+            escape(&result.function)
+        );
+    }
+    html.push_str("</table>\n</body>\n</html>\n");
+    html
+}
+
+/// A table with one row per function, colored from red (no mutants killed)
+/// to green (all killed)
+///
+/// Functions whose mutants all errored or didn't run have no score and are
+/// left gray.
+fn heatmap(scores: &[FunctionScore]) -> String {
+    let mut html = String::from("<table>\n");
+    html.push_str(
+        "<tr><th>Function</th><th>File</th><th>Mutants</th><th>Killed</th><th>Score</th></tr>\n",
+    );
+    for score in scores {
+        let (color, label) = match score.score() {
+            Some(percent) => (heat_color(percent), format!("{:.1}%", percent)),
+            None => ("#eeeeee".to_string(), "-".to_string()),
+        };
+        let _ = writeln!(
+            html,
+            "<tr style=\"background-color: {}\"><td><code>{}</code></td><td>{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td><td class=\"num\">{}</td></tr>",
+            color,
+            escape(&score.function),
+            escape(&score.file.display().to_string()),
+            score.mutants,
+            score.killed,
+            label
+        );
+    }
+    html.push_str("</table>\n");
+    html
+}
+
+/// Background color for a score: hue 0 (red) at 0% through 120 (green) at 100%
+fn heat_color(percent: f64) -> String {
+    format!("hsl({:.0}, 70%, 80%)", percent.clamp(0.0, 100.0) * 1.2)
+}
+
+fn status_label(status: &MutationStatus) -> &'static str {
+    match status {
+        MutationStatus::Killed => "KILLED",
+        MutationStatus::Crashed => "CRASHED",
+        MutationStatus::Survived => "SURVIVED",
+        MutationStatus::Timeout => "TIMEOUT",
+        MutationStatus::CompileError => "COMPILE ERROR",
+        MutationStatus::ConfigError(_) => "CONFIG ERROR",
+        MutationStatus::NotRun => "NOT RUN",
+        MutationStatus::NotCovered => "NOT COVERED",
+    }
+}
+
+/// Escape text for use in HTML content and attribute values
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_heatmap() {
+        let score = |function: &str, killed, survived| FunctionScore {
+            file: PathBuf::from("src/lib.rs"),
+            function: function.to_string(),
+            mutants: killed + survived,
+            killed,
+            survived,
+        };
+        let html = heatmap(&[score("weak", 0, 2), score("strong", 3, 0), score("Vec<u8>", 0, 0)]);
+
+        assert!(html.contains("hsl(0, 70%, 80%)\"><td><code>weak</code>"));
+        assert!(html.contains("hsl(120, 70%, 80%)\"><td><code>strong</code>"));
+        assert!(html.contains("#eeeeee\"><td><code>Vec&lt;u8&gt;</code>"));
+        assert!(html.contains("<td class=\"num\">3</td><td class=\"num\">3</td><td class=\"num\">100.0%</td>"));
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("a < b && c > \"d\""), "a &lt; b &amp;&amp; c &gt; &quot;d&quot;");
+    }
+}
@@ -25,21 +25,68 @@
 //! use std::path::Path;
 //!
 //! let config = Config::load(Path::new("mutations.yaml")).unwrap();
-//! let results = run_mutation_tests(&config, Path::new("."), false);
+//! let results = run_mutation_tests(&config, Path::new("."), false).unwrap();
 //! let report = MutationReport::new(results);
 //! report.print();
 //! ```
+//!
+//! Configs can also be built from in-memory text with [`Config::from_yaml_str`]
+//! or [`Config::from_toml_str`].
+//!
+//! # Bring Your Own Runner
+//!
+//! To run the tests with something other than `cargo test`, use only the
+//! matching and mutating: [`prepare_mutation`] turns a file's source into the
+//! mutant's, and the files are yours to write and restore.
+//!
+//! ```no_run
+//! use mutation_testing_rust::{prepare_mutation, Config};
+//! use std::path::Path;
+//! use std::process::Command;
+//!
+//! let project = Path::new(".");
+//! let mut config = Config::load(Path::new("mutations.yaml")).unwrap();
+//! config.expand_file_globs(project).unwrap();
+//! let crate_dir = config.settings.crate_dir(project);
+//!
+//! for mutation in &config.mutations {
+//!     let path = crate_dir.join(&mutation.file);
+//!     let original = std::fs::read_to_string(&path).unwrap();
+//!     let prepared = prepare_mutation(&original, mutation).unwrap();
+//!
+//!     std::fs::write(&path, &prepared.mutated_source).unwrap();
+//!     let passed = Command::new("./run-tests.sh").status().unwrap().success();
+//!     std::fs::write(&path, &original).unwrap();
+//!
+//!     let status = if passed { "survived" } else { "killed" };
+//!     println!("{} (line {}): {}", mutation.id, prepared.site.line, status);
+//! }
+//! ```
 
+pub mod backup;
+pub mod baseline;
 pub mod codegen;
 pub mod config;
+pub mod coverage;
 pub mod error;
+pub mod files;
+pub mod html;
+pub mod manifest;
 pub mod matcher;
 pub mod mutator;
+pub mod operators;
 pub mod report;
 pub mod runner;
+pub mod targets;
+pub mod triage;
 
 // Re-export main types at crate root
+pub use codegen::{prepare_mutation, PreparedMutation};
 pub use config::{Config, MutationConfig, Settings};
 pub use error::{MutationError, Result};
+pub use matcher::{list_functions, FunctionKind, FunctionRef, MatchedSite};
 pub use report::MutationReport;
-pub use runner::{run_mutation_tests, validate_mutations, MutationResult, MutationStatus};
+pub use runner::{
+    check_cargo, prioritize_survivors, run_mutation_tests, validate_mutations, MutationResult,
+    MutationStatus,
+};
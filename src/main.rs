@@ -1,13 +1,34 @@
 //! CLI for mutation testing framework
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::ExitCode;
+use std::time::{Duration, Instant};
 
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use colored::Colorize;
+use quote::ToTokens;
 
+use mutation_testing_rust::backup::{
+    git_checkout, git_head, git_version, modified_in_git, remove_stale_lock, restore_backups,
+    BACKUP_DIR, LOCK_FILE,
+};
+use mutation_testing_rust::baseline::Baseline;
+use mutation_testing_rust::codegen::{is_formatting_only, normalize_code, prepare_mutation};
+use mutation_testing_rust::config::{config_files_in, parse_duration, parse_test_kind, TestKind};
+use mutation_testing_rust::coverage::Coverage;
+use mutation_testing_rust::files::{find_package_root, find_project_root};
+use mutation_testing_rust::html;
+use mutation_testing_rust::manifest::Manifest;
+use mutation_testing_rust::matcher::{
+    collect_function_names, expressions_in_function, macro_sites, target_exists, TargetName,
+};
+use mutation_testing_rust::operators::OperatorRegistry;
+use mutation_testing_rust::report::{exit_code, EXIT_CONFIG_ERRORS, EXIT_OK, EXIT_SURVIVORS};
+use mutation_testing_rust::runner::{cargo_version, Validation};
+use mutation_testing_rust::triage::{parse_survivor_order, SurvivorOrder};
 use mutation_testing_rust::{
-    run_mutation_tests, validate_mutations, Config, MutationReport,
+    check_cargo, prioritize_survivors, run_mutation_tests, validate_mutations, Config,
+    MutationConfig, MutationError, MutationReport,
 };
 
 #[derive(Parser)]
@@ -16,65 +37,457 @@ use mutation_testing_rust::{
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// When to color output
+    #[arg(long, value_enum, global = true, default_value = "auto")]
+    color: ColorChoice,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ColorChoice {
+    /// Color when writing to a terminal
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum OutputFormat {
+    /// Colored, human-readable lines
+    Text,
+    /// A JSON array with one object per mutation
+    Json,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum TestFormat {
+    /// The colored text report
+    Text,
+    /// The full JSON report, with one object per mutation
+    Json,
+    /// Only the JSON report's summary: counts, score, durations and the
+    /// tested commit, for tracking the score over time
+    SummaryJson,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ReportFormat {
+    /// The text report, as printed by `test` (without colors in a file)
+    Text,
+    Json,
+    /// Only the JSON report's summary, without the results
+    SummaryJson,
+    /// A single page with a per-function heatmap
+    Html,
 }
 
 #[derive(Subcommand)]
 enum Commands {
     /// Run mutation tests
-    Test {
+    Test(Box<TestArgs>),
+
+    /// Validate mutation configuration without running tests
+    Validate {
         /// Path to the mutations config file
         #[arg(short, long, default_value = "mutations.yaml")]
         config: PathBuf,
 
-        /// Project directory (defaults to current directory)
+        /// Project directory (defaults to the enclosing package or workspace root)
         #[arg(short, long)]
         project: Option<PathBuf>,
 
-        /// Enable verbose output
-        #[arg(short, long)]
-        verbose: bool,
+        /// Report near-matches for expressions with no exact match
+        #[arg(long)]
+        loose: bool,
+
+        /// Include gitignored files when expanding `file` globs
+        #[arg(long)]
+        no_gitignore: bool,
+
+        /// Only mutate public (`pub` or `pub(crate)`) functions
+        #[arg(long)]
+        pub_only: bool,
+
+        /// Also mutate test code: `#[test]` functions and `#[cfg(test)]` modules
+        #[arg(long)]
+        mutate_tests: bool,
+
+        /// Output format
+        #[arg(long, value_enum, default_value = "text")]
+        format: OutputFormat,
+
+        /// For each valid mutation, show how its original normalizes and the
+        /// source code it matched
+        #[arg(long)]
+        show_normalized: bool,
     },
 
-    /// Validate mutation configuration without running tests
-    Validate {
+    /// Restore files left mutated by an interrupted run
+    Clean {
         /// Path to the mutations config file
         #[arg(short, long, default_value = "mutations.yaml")]
         config: PathBuf,
 
-        /// Project directory (defaults to current directory)
+        /// Project directory (defaults to the enclosing package or workspace root)
         #[arg(short, long)]
         project: Option<PathBuf>,
+
+        /// Also `git checkout` configured files that still differ from HEAD
+        #[arg(long)]
+        checkout: bool,
+
+        /// Remove the run lock even if the process holding it is still alive
+        #[arg(long)]
+        force: bool,
     },
 
     /// Show example configuration
     Example,
+
+    /// Check that cargo, the project, the config and git are usable
+    Doctor {
+        /// Path to the mutations config file
+        #[arg(short, long, default_value = "mutations.yaml")]
+        config: PathBuf,
+
+        /// Project directory (defaults to the enclosing package or workspace root)
+        #[arg(short, long)]
+        project: Option<PathBuf>,
+    },
+
+    /// Re-run the mutations recorded in a manifest and flag changed statuses
+    Replay {
+        /// Manifest written by `test --manifest`
+        #[arg(long, value_name = "FILE")]
+        manifest: PathBuf,
+
+        /// Project directory (defaults to the enclosing package or workspace root)
+        #[arg(short, long)]
+        project: Option<PathBuf>,
+
+        /// Enable verbose output
+        #[arg(short, long)]
+        verbose: bool,
+    },
+
+    /// Generate mutations for a source file and print them as YAML
+    Generate {
+        /// Path to the Rust source file, relative to the crate directory
+        file: PathBuf,
+
+        /// Crate directory (defaults to the enclosing package)
+        #[arg(short, long)]
+        project: Option<PathBuf>,
+
+        /// Mutation operators to run, comma-separated: cond (branch conditions
+        /// to true/false), arith, cmp, logic (swap binary operators), overflow
+        /// (wrapping and checked arithmetic methods), boundary (shift compared
+        /// integer literals by one), ref (`&mut x` to `&x`)
+        #[arg(long, value_delimiter = ',', required = true)]
+        ops: Vec<String>,
+
+        /// Macros whose arguments aren't mutated, comma-separated (default:
+        /// debug_assert*, print*, eprint*, dbg and the log macros)
+        #[arg(long, value_delimiter = ',', conflicts_with = "include_macros")]
+        skip_macros: Option<Vec<String>>,
+
+        /// Mutate inside the arguments of every macro, skipping none
+        #[arg(long)]
+        include_macros: bool,
+    },
+
+    /// Render a stored JSON report in another format, without re-running
+    Render {
+        /// JSON report written by `test --json` or `test --format json`
+        #[arg(short, long)]
+        input: PathBuf,
+
+        /// Format to render
+        #[arg(long, value_enum, default_value = "text")]
+        format: ReportFormat,
+
+        /// Write to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// List survivors in the text report by location, category or
+        /// complexity
+        #[arg(long, value_name = "ORDER", value_parser = parse_survivor_order)]
+        sort_survivors: Option<SurvivorOrder>,
+
+        /// List one aligned line per mutation in the text report
+        #[arg(long)]
+        compact: bool,
+    },
+
+    /// Print how each expression in a function was parsed (for writing `original`)
+    #[command(hide = true)]
+    DumpAst {
+        /// Path to the Rust source file
+        #[arg(long)]
+        file: PathBuf,
+
+        /// Name of the function to dump
+        #[arg(long)]
+        function: String,
+    },
+}
+
+#[derive(Args)]
+struct TestArgs {
+    /// Path to the mutations config file
+    #[arg(short, long, default_value = "mutations.yaml")]
+    config: PathBuf,
+
+    /// Run every config file (*.yaml, *.yml, *.toml) in this directory as one
+    /// config, instead of --config; only the first file may have settings
+    #[arg(long, value_name = "DIR", conflicts_with = "config")]
+    config_dir: Option<PathBuf>,
+
+    /// Project directory (defaults to the enclosing package or workspace root)
+    #[arg(short, long)]
+    project: Option<PathBuf>,
+
+    /// Enable verbose output
+    #[arg(short, long)]
+    verbose: bool,
+
+    /// Stop starting new mutations after this much time (e.g. 90s, 30m, 2h)
+    #[arg(long, value_parser = parse_duration)]
+    max_runtime: Option<Duration>,
+
+    /// Test at most N mutations per file, the first ones in config order
+    /// (overrides settings.max_per_file)
+    #[arg(long, value_name = "N")]
+    max_per_file: Option<usize>,
+
+    /// Test at most N mutations per function, the first ones in config order
+    /// (overrides settings.max_per_function)
+    #[arg(long, value_name = "N")]
+    max_per_function: Option<usize>,
+
+    /// Timeout for each test run, overriding settings.timeout (e.g. 120, 5m)
+    #[arg(long, value_parser = parse_duration)]
+    timeout: Option<Duration>,
+
+    /// Only print surviving mutations and the summary line
+    #[arg(long)]
+    list_survivors_only: bool,
+
+    /// List one aligned line per mutation (status, id, location, function,
+    /// change) and the summary, without the detailed sections
+    #[arg(long, conflicts_with = "list_survivors_only")]
+    compact: bool,
+
+    /// List survivors by location, category (likely real gaps, such as
+    /// swapped operators, first) or complexity (simplest expressions first),
+    /// instead of in config order
+    #[arg(long, value_name = "ORDER", value_parser = parse_survivor_order)]
+    sort_survivors: Option<SurvivorOrder>,
+
+    /// Also write the report as JSON to this file
+    #[arg(long, value_name = "FILE")]
+    json: Option<PathBuf>,
+
+    /// Write a manifest of the run's exact inputs and results, for `replay`
+    #[arg(long, value_name = "FILE")]
+    manifest: Option<PathBuf>,
+
+    /// Also write the text report, without colors, to this file
+    #[arg(long, value_name = "FILE")]
+    output: Option<PathBuf>,
+
+    /// Also write an HTML report, with a per-function heatmap, to this file
+    #[arg(long, value_name = "FILE")]
+    html: Option<PathBuf>,
+
+    /// Append the run's score to this CSV file, to track it over time
+    #[arg(long, value_name = "FILE")]
+    history: Option<PathBuf>,
+
+    /// Run mutations that survived in this previous JSON report first
+    #[arg(long, value_name = "REPORT")]
+    prioritize: Option<PathBuf>,
+
+    /// Accept the survivors listed in this baseline file: without a
+    /// threshold, only survivors it doesn't list fail the run
+    #[arg(long, value_name = "FILE")]
+    baseline: Option<PathBuf>,
+
+    /// Overwrite the --baseline file with this run's survivors, accepting
+    /// them all, including new ones
+    #[arg(long, requires = "baseline")]
+    baseline_update: bool,
+
+    /// Exit successfully when the mutation score is at least this percentage,
+    /// even if some mutants survived
+    #[arg(long, value_name = "SCORE")]
+    threshold: Option<f64>,
+
+    /// Report format written to stdout; progress always goes to stderr
+    #[arg(long, value_enum, default_value = "text")]
+    format: TestFormat,
+
+    /// Print how the score was computed, with the counts it excludes
+    #[arg(long)]
+    explain_score: bool,
+
+    /// Exit with code 3 if any mutant failed to compile, to catch replacements
+    /// that no longer type-check
+    #[arg(long)]
+    fail_on_compile_error: bool,
+
+    /// Report near-matches for expressions with no exact match
+    #[arg(long)]
+    loose: bool,
+
+    /// Include gitignored files when expanding `file` globs
+    #[arg(long)]
+    no_gitignore: bool,
+
+    /// Only mutate public (`pub` or `pub(crate)`) functions
+    #[arg(long)]
+    pub_only: bool,
+
+    /// Also mutate test code: `#[test]` functions and `#[cfg(test)]` modules
+    /// (sets settings.mutate_tests)
+    #[arg(long)]
+    mutate_tests: bool,
+
+    /// Leave the mutant in place after testing (debugging a single mutation)
+    #[arg(long)]
+    no_restore: bool,
+
+    /// Write each mutation's full test output to DIR/<id>.log; the report
+    /// only keeps the first `settings.details_lines` lines
+    #[arg(long, value_name = "DIR")]
+    output_dir: Option<PathBuf>,
+
+    /// LCOV coverage file (e.g. from `cargo llvm-cov --lcov`); mutations on
+    /// lines it shows were never executed are reported as not covered
+    /// without running the tests
+    #[arg(long, value_name = "LCOV")]
+    coverage: Option<PathBuf>,
+
+    /// Run the tests with this rustup toolchain (`cargo +<TOOLCHAIN> test`)
+    #[arg(long, value_name = "TOOLCHAIN")]
+    toolchain: Option<String>,
+
+    /// Also run `#[ignore]`d tests (`cargo test -- --include-ignored`)
+    #[arg(long)]
+    include_ignored: bool,
+
+    /// Only run these kinds of tests against each mutant: unit, doc and/or
+    /// integration, comma-separated (overrides settings.test_kinds)
+    #[arg(long, value_name = "KINDS", value_delimiter = ',', value_parser = parse_test_kind)]
+    test_kinds: Vec<TestKind>,
 }
 
 fn main() -> ExitCode {
     let cli = Cli::parse();
 
+    match cli.color {
+        ColorChoice::Auto => {}
+        ColorChoice::Always => colored::control::set_override(true),
+        ColorChoice::Never => colored::control::set_override(false),
+    }
+
     match cli.command {
-        Commands::Test {
+        Commands::Test(args) => run_tests(*args),
+
+        Commands::Validate {
             config,
             project,
-            verbose,
-        } => run_tests(&config, project, verbose),
+            loose,
+            no_gitignore,
+            pub_only,
+            mutate_tests,
+            format,
+            show_normalized,
+        } => validate_config(
+            &config,
+            project,
+            ValidateOptions {
+                loose,
+                no_gitignore,
+                pub_only,
+                mutate_tests,
+                format,
+                show_normalized,
+            },
+        ),
 
-        Commands::Validate { config, project } => validate_config(&config, project),
+        Commands::Clean {
+            config,
+            project,
+            checkout,
+            force,
+        } => clean(&config, project, checkout, force),
 
         Commands::Example => {
             print_example();
             ExitCode::SUCCESS
         }
+
+        Commands::Doctor { config, project } => doctor(&config, project),
+
+        Commands::Replay {
+            manifest,
+            project,
+            verbose,
+        } => replay(&manifest, project, verbose),
+
+        Commands::Generate {
+            file,
+            project,
+            ops,
+            skip_macros,
+            include_macros,
+        } => {
+            let skip_macros = if include_macros { Some(Vec::new()) } else { skip_macros };
+            generate(&file, project, &ops, skip_macros)
+        }
+
+        Commands::Render {
+            input,
+            format,
+            output,
+            sort_survivors,
+            compact,
+        } => render(&input, format, output.as_deref(), sort_survivors, compact),
+
+        Commands::DumpAst { file, function } => dump_ast(&file, &function),
+    }
+}
+
+/// The `--project` directory, or the project root found from the current
+/// directory like cargo does
+fn resolve_project_dir(project: Option<PathBuf>) -> PathBuf {
+    if let Some(project) = project {
+        return project;
+    }
+    let detected = std::env::current_dir()
+        .ok()
+        .and_then(|cwd| find_project_root(&cwd));
+    match detected {
+        Some(root) => {
+            eprintln!("{}", format!("Project root: {}", root.display()).dimmed());
+            root
+        }
+        None => PathBuf::from("."),
     }
 }
 
-fn run_tests(config_path: &PathBuf, project: Option<PathBuf>, verbose: bool) -> ExitCode {
-    let project_dir = project.unwrap_or_else(|| PathBuf::from("."));
+fn run_tests(args: TestArgs) -> ExitCode {
+    let project_dir = resolve_project_dir(args.project);
 
-    // Load configuration
-    println!("{}", "Loading configuration...".dimmed());
-    let config = match Config::load(config_path) {
+    // Progress goes to stderr; stdout only gets the report
+    eprintln!("{}", "Loading configuration...".dimmed());
+    let loaded = match &args.config_dir {
+        Some(dir) => Config::load_dir(dir),
+        None => Config::load(&args.config),
+    };
+    let mut config = match loaded {
         Ok(c) => c,
         Err(e) => {
             eprintln!("{}: {}", "Error".red().bold(), e);
@@ -82,13 +495,93 @@ fn run_tests(config_path: &PathBuf, project: Option<PathBuf>, verbose: bool) ->
         }
     };
 
-    println!(
+    // CLI flags override config settings
+    if let Some(max_runtime) = args.max_runtime {
+        config.settings.max_runtime = Some(max_runtime.as_secs());
+    }
+    if let Some(max) = args.max_per_file {
+        config.settings.max_per_file = Some(max);
+    }
+    if let Some(max) = args.max_per_function {
+        config.settings.max_per_function = Some(max);
+    }
+    if let Some(timeout) = args.timeout {
+        config.settings.timeout = timeout.as_secs();
+    }
+    if args.loose {
+        config.settings.loose = true;
+    }
+    if args.no_gitignore {
+        config.settings.respect_gitignore = false;
+    }
+    if args.pub_only {
+        config.settings.pub_only = true;
+    }
+    if args.mutate_tests {
+        config.settings.mutate_tests = true;
+    }
+    if let Some(toolchain) = args.toolchain {
+        config.settings.toolchain = Some(toolchain);
+    }
+    if args.include_ignored {
+        config.settings.include_ignored = true;
+    }
+    if !args.test_kinds.is_empty() {
+        config.settings.test_kinds = args.test_kinds;
+    }
+    if let Some(dir) = args.output_dir {
+        config.settings.output_dir = Some(dir);
+    }
+    if let Some(path) = &args.coverage {
+        match Coverage::load(path) {
+            Ok(coverage) => config.settings.coverage = Some(coverage),
+            Err(e) => {
+                eprintln!("{}: {}", "Error".red().bold(), e);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    if let Err(e) = config.expand_file_globs(&project_dir) {
+        eprintln!("{}: {}", "Error".red().bold(), e);
+        return ExitCode::FAILURE;
+    }
+    config.resolve_anchors(&project_dir);
+    report_skipped_tests(&config.retain_non_test(&project_dir));
+    report_skipped_private(&config.retain_public(&project_dir));
+    report_capped(&config.apply_caps());
+
+    // Read the baseline up front, so a bad path fails before the run; one
+    // that doesn't exist yet is about to be written
+    let baseline = match &args.baseline {
+        Some(path) if args.baseline_update && !path.exists() => Some(Baseline::default()),
+        Some(path) => match Baseline::load(path) {
+            Ok(baseline) => Some(baseline),
+            Err(e) => {
+                eprintln!("{}: {}", "Error".red().bold(), e);
+                return ExitCode::FAILURE;
+            }
+        },
+        None => None,
+    };
+
+    // Schedule previous survivors first
+    if let Some(previous) = &args.prioritize {
+        match MutationReport::load_json(previous) {
+            Ok(previous) => prioritize_survivors(&mut config.mutations, &previous),
+            Err(e) => {
+                eprintln!("{}: {}", "Error".red().bold(), e);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    eprintln!(
         "Found {} mutation(s) in config",
         config.mutations.len()
     );
 
     // Validate configuration first
-    println!("{}", "Validating mutations...".dimmed());
+    eprintln!("{}", "Validating mutations...".dimmed());
     let validation_results = validate_mutations(&config, &project_dir);
     let errors: Vec<_> = validation_results
         .into_iter()
@@ -100,52 +593,470 @@ fn run_tests(config_path: &PathBuf, project: Option<PathBuf>, verbose: bool) ->
         for error in &errors {
             eprintln!("  • {}", error);
         }
+        return ExitCode::from(EXIT_CONFIG_ERRORS);
+    }
+
+    if let Err(e) = check_cargo(config.settings.toolchain.as_deref()) {
+        eprintln!("{}: {}", "Error".red().bold(), e);
         return ExitCode::FAILURE;
     }
 
-    println!("{}", "All mutations valid. Running tests...".green());
-    println!();
+    if args.no_restore {
+        // Mutants would stack on top of each other, so only allow one
+        if config.mutations.len() != 1 {
+            eprintln!(
+                "{}: --no-restore needs a config with exactly one mutation, found {}",
+                "Error".red().bold(),
+                config.mutations.len()
+            );
+            return ExitCode::FAILURE;
+        }
+        config.settings.no_restore = true;
+        eprintln!(
+            "{}",
+            "WARNING: --no-restore leaves the mutated file in place and the working tree dirty; \
+             run `clean` afterwards to restore it"
+                .red()
+                .bold()
+        );
+    }
+
+    report_cfg_gated(&config, &project_dir);
+    eprintln!("{}", "All mutations valid. Running tests...".green());
+    eprintln!();
 
     // Run mutation tests
-    let results = run_mutation_tests(&config, &project_dir, verbose);
+    let started = Instant::now();
+    let results = match run_mutation_tests(&config, &project_dir, args.verbose) {
+        Ok(results) => results,
+        Err(e) => {
+            eprintln!("{}: {}", "Error".red().bold(), e);
+            return ExitCode::FAILURE;
+        }
+    };
 
     // Generate and print report
-    let report = MutationReport::new(results);
-    report.print();
-
-    // Return appropriate exit code
-    if report.survived() > 0 {
-        ExitCode::from(1) // Some mutations survived
-    } else if report.config_errors() > 0 {
-        ExitCode::from(2) // Configuration errors
+    let mut report = MutationReport::new(results).with_wall_clock(started.elapsed());
+    if let Some(order) = args.sort_survivors {
+        report = report.with_survivor_order(order);
+    }
+    if args.compact {
+        report = report.with_compact();
+    }
+    match args.format {
+        TestFormat::Json => {
+            println!("{}", report.to_json());
+            if args.explain_score {
+                eprintln!("{}", report.explain_score().dimmed());
+            }
+        }
+        TestFormat::SummaryJson => {
+            println!("{}", report.to_summary_json(git_head(&project_dir).as_deref()));
+            if args.explain_score {
+                eprintln!("{}", report.explain_score().dimmed());
+            }
+        }
+        TestFormat::Text => {
+            if args.list_survivors_only {
+                report.print_survivors_only();
+            } else {
+                report.print();
+            }
+            if args.explain_score {
+                println!("{}", report.explain_score().dimmed());
+            }
+        }
+    }
+
+    if let Some(path) = &args.output {
+        if let Err(e) = report.write_text_file(path, args.list_survivors_only) {
+            eprintln!("{}: {}", "Error".red().bold(), e);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    if let Some(path) = &args.json {
+        if let Err(e) = report.write_json(path) {
+            eprintln!("{}: {}", "Error".red().bold(), e);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    if let Some(path) = &args.html {
+        if let Err(e) = report.write_html(path) {
+            eprintln!("{}: {}", "Error".red().bold(), e);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    if let Some(path) = &args.history {
+        let sha = git_head(&project_dir);
+        if let Err(e) = report.append_history(path, sha.as_deref()) {
+            eprintln!("{}: {}", "Error".red().bold(), e);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    if let Some(path) = &args.manifest {
+        let config_source = match &args.config_dir {
+            // The files in load order, so the hash changes with any of them
+            Some(dir) => config_files_in(dir)
+                .unwrap_or_default()
+                .iter()
+                .flat_map(|path| std::fs::read(path).unwrap_or_default())
+                .collect(),
+            None => std::fs::read(&args.config).unwrap_or_default(),
+        };
+        let manifest = Manifest::new(&config, &config_source, &project_dir, &report.results);
+        if let Err(e) = manifest.write(path) {
+            eprintln!("{}: {}", "Error".red().bold(), e);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    let mut code = exit_code(&report, args.threshold, args.fail_on_compile_error);
+    if let (Some(path), Some(baseline)) = (&args.baseline, &baseline) {
+        match check_baseline(&report, baseline, path, args.baseline_update) {
+            Ok(true) if code == EXIT_SURVIVORS && args.threshold.is_none() => code = EXIT_OK,
+            Ok(_) => {}
+            Err(e) => {
+                eprintln!("{}: {}", "Error".red().bold(), e);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    ExitCode::from(code)
+}
+
+/// Compare the run's survivors with the baseline, or with `update`, replace
+/// the baseline with them
+///
+/// Survivors the baseline doesn't list are printed either way, so accepting
+/// them with `update` is never silent. Returns whether every survivor is now
+/// accepted.
+fn check_baseline(
+    report: &MutationReport,
+    baseline: &Baseline,
+    path: &Path,
+    update: bool,
+) -> mutation_testing_rust::Result<bool> {
+    let new = baseline.new_survivors(report);
+    if !new.is_empty() {
+        let heading = if update {
+            format!("{} new survivor(s), now accepted:", new.len())
+        } else {
+            format!("{} survivor(s) not in the baseline:", new.len())
+        };
+        eprintln!("{}", heading.yellow().bold());
+        for result in &new {
+            let change = format!(
+                "{} -> {} in {}::{}",
+                result.original,
+                result.replacement,
+                result.file.display(),
+                result.function
+            );
+            eprintln!("  {}: {}", result.mutation_id, change.dimmed());
+        }
+    }
+    if !update {
+        return Ok(new.is_empty());
+    }
+
+    // Mutations that weren't run can't be recorded; dropping them from the
+    // baseline would report them as new next time
+    if report.not_run() > 0 {
+        return Err(MutationError::ConfigError {
+            message: format!(
+                "{} mutation(s) weren't run, so the baseline wasn't updated; \
+                 rerun without --max-runtime",
+                report.not_run()
+            ),
+        });
+    }
+    let updated = Baseline::from_report(report);
+    updated.write(path)?;
+    eprintln!(
+        "Recorded {} survivor(s) in {}",
+        updated.survivors.len(),
+        path.display()
+    );
+    Ok(true)
+}
+
+/// Check the environment a run needs and print a checklist
+///
+/// Fails if cargo, the project or the config is unusable; git and leftovers
+/// of an interrupted run only produce warnings.
+fn doctor(config_path: &Path, project: Option<PathBuf>) -> ExitCode {
+    let mut healthy = true;
+    let mut check = |ok: bool, critical: bool, message: String| {
+        let mark = match (ok, critical) {
+            (true, _) => "✓".green(),
+            (false, true) => "✗".red(),
+            (false, false) => "!".yellow(),
+        };
+        println!("{} {}", mark, message);
+        healthy &= ok || !critical;
+    };
+
+    let config = Config::load(config_path);
+    let toolchain = config
+        .as_ref()
+        .ok()
+        .and_then(|config| config.settings.toolchain.clone());
+    match check_cargo(toolchain.as_deref()) {
+        Ok(()) => {
+            let version = cargo_version(toolchain.as_deref()).unwrap_or_default();
+            check(true, true, format!("cargo: {}", version));
+        }
+        Err(e) => check(false, true, format!("cargo: {}", e)),
+    }
+
+    let project_dir = match project {
+        Some(project) => Some(project),
+        None => std::env::current_dir()
+            .ok()
+            .and_then(|cwd| find_project_root(&cwd)),
+    };
+    let crate_dir = project_dir.as_ref().map(|dir| match &config {
+        Ok(config) => config.settings.crate_dir(dir),
+        Err(_) => dir.clone(),
+    });
+    match &crate_dir {
+        Some(dir) if dir.join("Cargo.toml").is_file() => {
+            check(true, true, format!("Cargo project: {}", dir.display()));
+        }
+        Some(dir) => check(
+            false,
+            true,
+            format!("Cargo project: no Cargo.toml in {}", dir.display()),
+        ),
+        None => check(
+            false,
+            true,
+            "Cargo project: no Cargo.toml in the current directory or above (use --project)"
+                .to_string(),
+        ),
+    }
+
+    match &config {
+        Ok(config) => check(
+            true,
+            true,
+            format!(
+                "Config: {} ({} mutation(s))",
+                config_path.display(),
+                config.mutations.len()
+            ),
+        ),
+        Err(e) => check(false, true, format!("Config: {}", e)),
+    }
+
+    match git_version() {
+        Some(version) => check(true, false, format!("git: {}", version)),
+        None => check(
+            false,
+            false,
+            "git: not found (needed for --history and clean --checkout)".to_string(),
+        ),
+    }
+
+    if let Some(dir) = &crate_dir {
+        if dir.join(LOCK_FILE).exists() {
+            check(
+                false,
+                false,
+                format!("{} exists: a run is in progress, or `clean` removes it", LOCK_FILE),
+            );
+        }
+        if dir.join(BACKUP_DIR).exists() {
+            check(
+                false,
+                false,
+                format!("{} exists: run `clean` to restore the backed-up files", BACKUP_DIR),
+            );
+        }
+    }
+
+    if healthy {
+        ExitCode::SUCCESS
     } else {
+        ExitCode::FAILURE
+    }
+}
+
+fn replay(manifest_path: &Path, project: Option<PathBuf>, verbose: bool) -> ExitCode {
+    let project_dir = resolve_project_dir(project);
+
+    let manifest = match Manifest::load(manifest_path) {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            eprintln!("{}: {}", "Error".red().bold(), e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if let Err(e) = check_cargo(manifest.toolchain.as_deref()) {
+        eprintln!("{}: {}", "Error".red().bold(), e);
+        return ExitCode::FAILURE;
+    }
+    let current = cargo_version(manifest.toolchain.as_deref());
+    if manifest.cargo_version.is_some() && current != manifest.cargo_version {
+        eprintln!(
+            "{} recorded with {}, replaying with {}",
+            "Warning:".yellow().bold(),
+            manifest.cargo_version.as_deref().unwrap_or("unknown cargo"),
+            current.as_deref().unwrap_or("unknown cargo")
+        );
+    }
+
+    eprintln!(
+        "{}",
+        format!("Replaying {} mutation(s)...", manifest.entries.len()).dimmed()
+    );
+    let outcomes = match manifest.replay(&project_dir, verbose) {
+        Ok(outcomes) => outcomes,
+        Err(e) => {
+            eprintln!("{}: {}", "Error".red().bold(), e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    for outcome in &outcomes {
+        if outcome.changed() {
+            println!(
+                "{} {} - {}: {} -> {}",
+                "[CHANGED]".red().bold(),
+                outcome.result.mutation_id,
+                outcome.result.location(),
+                outcome.recorded.label(),
+                outcome.result.status.label()
+            );
+        } else {
+            println!(
+                "{} {} - {}",
+                "[SAME]".green(),
+                outcome.result.mutation_id.dimmed(),
+                outcome.result.status.label()
+            );
+        }
+    }
+
+    let skipped = manifest.entries.len() - outcomes.len();
+    if skipped > 0 {
+        println!(
+            "{}",
+            format!("Skipped {} mutation(s) that weren't tested in the recorded run", skipped)
+                .dimmed()
+        );
+    }
+
+    let changed = outcomes.iter().filter(|o| o.changed()).count();
+    println!();
+    if changed == 0 {
+        println!(
+            "{} All {} replayed mutation(s) match the recorded run",
+            "✓".green().bold(),
+            outcomes.len()
+        );
         ExitCode::SUCCESS
+    } else {
+        println!(
+            "{} {} of {} replayed mutation(s) changed status (flaky tests or environment drift)",
+            "✗".red().bold(),
+            changed,
+            outcomes.len()
+        );
+        ExitCode::FAILURE
     }
 }
 
-fn validate_config(config_path: &PathBuf, project: Option<PathBuf>) -> ExitCode {
-    let project_dir = project.unwrap_or_else(|| PathBuf::from("."));
+/// Flags of the `validate` command
+struct ValidateOptions {
+    loose: bool,
+    no_gitignore: bool,
+    pub_only: bool,
+    mutate_tests: bool,
+    format: OutputFormat,
+    show_normalized: bool,
+}
+
+fn validate_config(
+    config_path: &Path,
+    project: Option<PathBuf>,
+    options: ValidateOptions,
+) -> ExitCode {
+    let ValidateOptions {
+        loose,
+        no_gitignore,
+        pub_only,
+        mutate_tests,
+        format,
+        show_normalized,
+    } = options;
+    let project_dir = resolve_project_dir(project);
 
-    // Load configuration
-    println!("{}", "Loading configuration...".dimmed());
-    let config = match Config::load(config_path) {
+    // Progress goes to stderr; stdout only gets the results
+    eprintln!("{}", "Loading configuration...".dimmed());
+    let mut config = match Config::load(config_path) {
         Ok(c) => c,
         Err(e) => {
             eprintln!("{}: {}", "Error".red().bold(), e);
             return ExitCode::FAILURE;
         }
     };
+    if loose {
+        config.settings.loose = true;
+    }
+    if no_gitignore {
+        config.settings.respect_gitignore = false;
+    }
+    if pub_only {
+        config.settings.pub_only = true;
+    }
+    if mutate_tests {
+        config.settings.mutate_tests = true;
+    }
+    if let Err(e) = config.expand_file_globs(&project_dir) {
+        eprintln!("{}: {}", "Error".red().bold(), e);
+        return ExitCode::FAILURE;
+    }
+    config.resolve_anchors(&project_dir);
+    report_skipped_tests(&config.retain_non_test(&project_dir));
+    report_skipped_private(&config.retain_public(&project_dir));
 
-    println!(
+    if let OutputFormat::Json = format {
+        let validation_results = validate_mutations(&config, &project_dir);
+        let validations: Vec<Validation> = config
+            .mutations
+            .iter()
+            .zip(&validation_results)
+            .map(|(mutation, result)| Validation::new(mutation, result))
+            .collect();
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&validations).expect("validations serialize to JSON")
+        );
+        return if validations.iter().all(|v| v.valid) {
+            ExitCode::SUCCESS
+        } else {
+            ExitCode::FAILURE
+        };
+    }
+
+    eprintln!(
         "Found {} mutation(s) in config",
         config.mutations.len()
     );
 
     // Validate all mutations
-    println!("{}", "Validating mutations...".dimmed());
-    println!();
+    eprintln!("{}", "Validating mutations...".dimmed());
+    eprintln!();
 
     let validation_results = validate_mutations(&config, &project_dir);
+    let cfg_gates = config.cfg_gates(&project_dir);
+    let crate_dir = config.settings.crate_dir(&project_dir);
     let mut all_valid = true;
 
     for (i, result) in validation_results.iter().enumerate() {
@@ -160,6 +1071,9 @@ fn validate_config(config_path: &PathBuf, project: Option<PathBuf>) -> ExitCode
                     mutation.file.display(),
                     mutation.function
                 );
+                if show_normalized {
+                    print_normalized(mutation, &crate_dir);
+                }
             }
             Err(e) => {
                 all_valid = false;
@@ -174,6 +1088,16 @@ fn validate_config(config_path: &PathBuf, project: Option<PathBuf>) -> ExitCode
                 println!("  {}: {}", "Error".red(), e);
             }
         }
+        if !cfg_gates[i].is_empty() {
+            println!("  {}: {}", "Note".yellow(), cfg_note(mutation, &cfg_gates[i]));
+        }
+        if is_formatting_only(mutation) {
+            println!(
+                "  {}: the replacement only changes formatting or parentheses, so the mutant is \
+                 the original code and will always survive",
+                "Warning".yellow()
+            );
+        }
     }
 
     println!();
@@ -196,6 +1120,346 @@ fn validate_config(config_path: &PathBuf, project: Option<PathBuf>) -> ExitCode
     }
 }
 
+fn render(
+    input: &Path,
+    format: ReportFormat,
+    output: Option<&Path>,
+    sort_survivors: Option<SurvivorOrder>,
+    compact: bool,
+) -> ExitCode {
+    let report = match MutationReport::load_json(input) {
+        Ok(report) => MutationReport {
+            survivor_order: sort_survivors,
+            compact,
+            ..report
+        },
+        Err(e) => {
+            eprintln!("{}: {}", "Error".red().bold(), e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let written = match (format, output) {
+        (ReportFormat::Text, Some(path)) => report.write_text_file(path, false),
+        (ReportFormat::Json, Some(path)) => report.write_json(path),
+        (ReportFormat::SummaryJson, Some(path)) => report.write_summary_json(path, None),
+        (ReportFormat::Html, Some(path)) => report.write_html(path),
+        (ReportFormat::Text, None) => {
+            report.print();
+            Ok(())
+        }
+        (ReportFormat::Json, None) => {
+            println!("{}", report.to_json());
+            Ok(())
+        }
+        (ReportFormat::SummaryJson, None) => {
+            println!("{}", report.to_summary_json(None));
+            Ok(())
+        }
+        (ReportFormat::Html, None) => {
+            print!("{}", html::render(&report));
+            Ok(())
+        }
+    };
+    if let Err(e) = written {
+        eprintln!("{}: {}", "Error".red().bold(), e);
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+fn clean(config_path: &Path, project: Option<PathBuf>, checkout: bool, force: bool) -> ExitCode {
+    let project_dir = resolve_project_dir(project);
+
+    let mut config = match Config::load(config_path) {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("{}: {}", "Error".red().bold(), e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let crate_dir = config.settings.crate_dir(&project_dir);
+
+    // Restoring files underneath a live run would undo its mutants mid-test
+    match remove_stale_lock(&crate_dir, force) {
+        Ok(true) => println!("{} Removed stale run lock {}", "✓".green(), LOCK_FILE),
+        Ok(false) => {}
+        Err(e) => {
+            eprintln!("{}: {}", "Error".red().bold(), e);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    // Restore anything the runner backed up but never put back
+    match restore_backups(&crate_dir) {
+        Ok(restored) if restored.is_empty() => {
+            println!("{}", "No leftover mutation backups found".dimmed());
+        }
+        Ok(restored) => {
+            for file in restored {
+                println!("{} Restored {}", "✓".green(), file.display());
+            }
+        }
+        Err(e) => {
+            eprintln!("{}: {}", "Error".red().bold(), e);
+            return ExitCode::FAILURE;
+        }
+    }
+
+    // Without backups, git is the only record of the original content
+    if let Err(e) = config.expand_file_globs(&project_dir) {
+        eprintln!("{}: {}", "Error".red().bold(), e);
+        return ExitCode::FAILURE;
+    }
+    let mut files: Vec<PathBuf> = config.mutations.iter().map(|m| m.file.clone()).collect();
+    files.sort();
+    files.dedup();
+    let Some(modified) = modified_in_git(&crate_dir, &files) else {
+        return ExitCode::SUCCESS;
+    };
+    if modified.is_empty() {
+        return ExitCode::SUCCESS;
+    }
+
+    if checkout {
+        if let Err(e) = git_checkout(&crate_dir, &modified) {
+            eprintln!("{}: {}", "Error".red().bold(), e);
+            return ExitCode::FAILURE;
+        }
+        for file in &modified {
+            println!("{} Checked out {}", "✓".green(), file.display());
+        }
+    } else {
+        println!();
+        println!("{}", "Configured files differ from HEAD:".yellow().bold());
+        for file in &modified {
+            println!("  • {}", file.display());
+        }
+        println!(
+            "{}",
+            "If these are leftover mutations, re-run with --checkout to reset them.".dimmed()
+        );
+    }
+
+    ExitCode::SUCCESS
+}
+
+/// Explain that a mutation is in `cfg`-gated code
+fn cfg_note(mutation: &MutationConfig, cfgs: &[String]) -> String {
+    let gates: Vec<String> = cfgs.iter().map(|cfg| format!("#[cfg({})]", cfg)).collect();
+    format!(
+        "'{}' is behind {}; the mutation is only exercised if the test run enables it",
+        mutation.function,
+        gates.join(", ")
+    )
+}
+
+/// Show how a mutation's original normalizes next to the code it matched
+fn print_normalized(mutation: &MutationConfig, crate_dir: &Path) {
+    if let Some(normalized) = normalize_code(&mutation.original) {
+        println!("  {} {}", "normalized:".dimmed(), normalized.replace('\n', "\n    "));
+    }
+    let site = std::fs::read_to_string(crate_dir.join(&mutation.file))
+        .ok()
+        .and_then(|source| prepare_mutation(&source, mutation).ok())
+        .map(|prepared| prepared.site);
+    if let Some(site) = site {
+        println!(
+            "  {} {} {}",
+            "matched:   ".dimmed(),
+            site.source.as_deref().unwrap_or("?").replace('\n', "\n    "),
+            format!("(line {}, column {})", site.line, site.column).dimmed()
+        );
+    }
+}
+
+/// Warn about mutations in `cfg`-gated code before they are run
+fn report_cfg_gated(config: &Config, project_dir: &Path) {
+    for (mutation, cfgs) in config.mutations.iter().zip(config.cfg_gates(project_dir)) {
+        if !cfgs.is_empty() {
+            eprintln!(
+                "{} {}: {}",
+                "Warning:".yellow().bold(),
+                mutation.id,
+                cfg_note(mutation, &cfgs)
+            );
+        }
+    }
+}
+
+/// Note mutations dropped because their function is test code
+fn report_skipped_tests(skipped: &[MutationConfig]) {
+    for mutation in skipped {
+        eprintln!(
+            "{}",
+            format!(
+                "Skipping {}: '{}' is test code (use --mutate-tests to mutate it)",
+                mutation.id, mutation.function
+            )
+            .dimmed()
+        );
+    }
+}
+
+/// Note mutations dropped because their function isn't public (`pub_only`)
+fn report_skipped_private(skipped: &[MutationConfig]) {
+    for mutation in skipped {
+        eprintln!(
+            "{}",
+            format!(
+                "Skipping {}: '{}' is not public",
+                mutation.id, mutation.function
+            )
+            .dimmed()
+        );
+    }
+}
+
+/// Say how many mutations the per-file and per-function caps skipped, by file
+fn report_capped(skipped: &[MutationConfig]) {
+    if skipped.is_empty() {
+        return;
+    }
+    eprintln!(
+        "{}",
+        format!(
+            "Skipping {} mutation(s) over max_per_file/max_per_function:",
+            skipped.len()
+        )
+        .yellow()
+    );
+    let mut files: Vec<(&Path, usize)> = Vec::new();
+    for mutation in skipped {
+        match files.iter_mut().find(|(file, _)| *file == mutation.file) {
+            Some((_, count)) => *count += 1,
+            None => files.push((&mutation.file, 1)),
+        }
+    }
+    for (file, count) in files {
+        eprintln!("{}", format!("  {}: {} skipped", file.display(), count).dimmed());
+    }
+}
+
+fn generate(
+    file: &Path,
+    project: Option<PathBuf>,
+    ops: &[String],
+    skip_macros: Option<Vec<String>>,
+) -> ExitCode {
+    // `file` is relative to the crate, not to an enclosing workspace's root;
+    // the other commands run from the workspace root, so the config points
+    // them at the crate with `settings.source_root`
+    let cwd = std::env::current_dir().ok();
+    let package = cwd.as_deref().and_then(find_package_root);
+    let source_root = match (&project, &package) {
+        (None, Some(package)) => cwd
+            .as_deref()
+            .and_then(find_project_root)
+            .and_then(|root| package.strip_prefix(root).ok().map(Path::to_path_buf))
+            .filter(|relative| !relative.as_os_str().is_empty()),
+        _ => None,
+    };
+    let crate_dir = project.or(package).unwrap_or_else(|| PathBuf::from("."));
+    let path = crate_dir.join(file);
+    let source = match std::fs::read_to_string(&path) {
+        Ok(source) => source,
+        Err(e) => {
+            let error = MutationError::FileReadError {
+                file: path,
+                error: e.to_string(),
+            };
+            eprintln!("{}: {}", "Error".red().bold(), error);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let ast = match syn::parse_file(&source) {
+        Ok(ast) => ast,
+        Err(e) => {
+            let error = MutationError::ParseError {
+                file: path,
+                error: e.to_string(),
+            };
+            eprintln!("{}: {}", "Error".red().bold(), error);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut registry = OperatorRegistry::with_builtins();
+    if let Some(skip_macros) = skip_macros {
+        registry.skip_macros(skip_macros);
+    }
+    let mutations = match registry.generate(ops, &ast, file) {
+        Ok(mutations) => mutations,
+        Err(e) => {
+            eprintln!("{}: {}", "Error".red().bold(), e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut config = Config::from_mutations(mutations);
+    config.settings.source_root = source_root;
+    match config.to_yaml_string() {
+        Ok(yaml) => {
+            print!("{}", yaml);
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("{}: {}", "Error".red().bold(), e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn dump_ast(file: &Path, function: &str) -> ExitCode {
+    let source = match std::fs::read_to_string(file) {
+        Ok(source) => source,
+        Err(e) => {
+            let error = MutationError::FileReadError {
+                file: file.to_path_buf(),
+                error: e.to_string(),
+            };
+            eprintln!("{}: {}", "Error".red().bold(), error);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let ast = match syn::parse_file(&source) {
+        Ok(ast) => ast,
+        Err(e) => {
+            let error = MutationError::ParseError {
+                file: file.to_path_buf(),
+                error: e.to_string(),
+            };
+            eprintln!("{}: {}", "Error".red().bold(), error);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if !target_exists(&ast, &TargetName::parse(function)) {
+        let error = MutationError::FunctionNotFound {
+            file: file.to_path_buf(),
+            function: function.to_string(),
+            available_functions: collect_function_names(&ast),
+            macro_sites: macro_sites(&ast),
+        };
+        eprintln!("{}: {}", "Error".red().bold(), error);
+        return ExitCode::FAILURE;
+    }
+
+    for site in expressions_in_function(&ast, function) {
+        println!(
+            "{} {}",
+            format!("{}:{}", site.line, site.column).dimmed(),
+            site.expr.to_token_stream().to_string().bold()
+        );
+        println!("{:#?}", site.expr);
+        println!();
+    }
+
+    ExitCode::SUCCESS
+}
+
 fn print_example() {
     let example = r#"# Example mutations.yaml configuration file
 version: "1.0"
@@ -0,0 +1,533 @@
+//! Replayable run manifests
+//!
+//! A manifest records the exact inputs of a run: a hash of the config, the
+//! toolchain and cargo arguments, and for each mutation the change it made to
+//! its file along with the resulting status. Replaying it re-applies the same
+//! changes, without matching the config again, and re-runs the tests, so
+//! statuses that differ point at flaky tests or a drifted environment.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::backup::RunLock;
+use crate::codegen::{prepare_mutation, prepare_mutations};
+use crate::config::{Config, MutationConfig, Settings, TestKind};
+use crate::error::{MutationError, Result};
+use crate::runner::{
+    cargo_test_args, cargo_version, group_config, run_file_mutations, FileMutation, Mutant,
+    MutationResult, MutationStatus,
+};
+use crate::targets::{discover_test_targets, select_targets, TargetKind};
+
+/// The inputs and results of a run
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Manifest {
+    /// FNV-1a hash of the config file, as hex
+    pub config_hash: String,
+    /// The pinned toolchain (`settings.toolchain`), if any
+    pub toolchain: Option<String>,
+    /// `cargo --version` at the time of the run
+    pub cargo_version: Option<String>,
+    /// Arguments the tests were run with
+    pub cargo_args: Vec<String>,
+    /// `settings.source_root`: the crate directory within the project
+    pub source_root: Option<PathBuf>,
+    /// `settings.timeout`
+    pub timeout: u64,
+    /// `settings.split_targets`
+    pub split_targets: bool,
+    /// `settings.include_ignored`
+    #[serde(default)]
+    pub include_ignored: bool,
+    /// `settings.clean_build_retry`
+    #[serde(default)]
+    pub clean_build_retry: bool,
+    /// `settings.test_kinds`
+    #[serde(default)]
+    pub test_kinds: Vec<TestKind>,
+    /// `settings.kill_on_exit_codes`
+    #[serde(default)]
+    pub kill_on_exit_codes: Vec<i32>,
+    /// `settings.test_binary`
+    #[serde(default)]
+    pub test_binary: Option<PathBuf>,
+    pub entries: Vec<ManifestEntry>,
+}
+
+/// One mutation of a run
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub mutation_id: String,
+    /// The file that was mutated, after glob expansion, relative to the crate
+    pub file: PathBuf,
+    pub function: String,
+    pub original: String,
+    pub replacement: String,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    /// The change applied to `file`; `None` if the mutation couldn't be
+    /// applied, or is a group
+    pub diff: Option<LineDiff>,
+    /// For a group, the change applied to each of its members' files, in
+    /// order of first appearance
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub group_diffs: Vec<FileDiff>,
+    /// The mutation's `killed_by` allowlist
+    #[serde(default)]
+    pub killed_by: Option<Vec<String>>,
+    /// The mutation's `env`
+    #[serde(default)]
+    pub env: Option<HashMap<String, String>>,
+    /// The mutation's `timeout`
+    #[serde(default)]
+    pub timeout: Option<u64>,
+    pub status: MutationStatus,
+}
+
+/// The change a group made to one of its files
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileDiff {
+    /// The file, relative to the crate
+    pub file: PathBuf,
+    pub diff: LineDiff,
+}
+
+/// A change to a file: the `removed` lines starting at `line` are replaced by
+/// `added`
+///
+/// Lines are split on `\n` without their terminator, so applying a diff
+/// reproduces the mutated file byte for byte.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LineDiff {
+    /// First changed line (1-indexed)
+    pub line: usize,
+    pub removed: Vec<String>,
+    pub added: Vec<String>,
+}
+
+impl LineDiff {
+    /// The diff turning `original` into `mutated`
+    pub fn between(original: &str, mutated: &str) -> Self {
+        let old: Vec<&str> = original.split('\n').collect();
+        let new: Vec<&str> = mutated.split('\n').collect();
+
+        let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+        let max_suffix = old.len().min(new.len()) - prefix;
+        let suffix = old
+            .iter()
+            .rev()
+            .zip(new.iter().rev())
+            .take(max_suffix)
+            .take_while(|(a, b)| a == b)
+            .count();
+
+        LineDiff {
+            line: prefix + 1,
+            removed: old[prefix..old.len() - suffix].iter().map(|l| l.to_string()).collect(),
+            added: new[prefix..new.len() - suffix].iter().map(|l| l.to_string()).collect(),
+        }
+    }
+
+    /// Apply the diff to `source`
+    ///
+    /// Returns `None` if `source` doesn't contain the removed lines where the
+    /// diff expects them, i.e. the file changed since the diff was taken.
+    pub fn apply(&self, source: &str) -> Option<String> {
+        let mut lines: Vec<&str> = source.split('\n').collect();
+        let start = self.line.checked_sub(1)?;
+        let end = start + self.removed.len();
+        if end > lines.len() || lines[start..end] != self.removed[..] {
+            return None;
+        }
+        lines.splice(start..end, self.added.iter().map(String::as_str));
+        Some(lines.join("\n"))
+    }
+}
+
+/// A replayed mutation, with the status it had in the recorded run
+pub struct ReplayOutcome {
+    pub recorded: MutationStatus,
+    pub result: MutationResult,
+}
+
+impl ReplayOutcome {
+    /// Whether the replay ended differently from the recorded run
+    pub fn changed(&self) -> bool {
+        self.recorded != self.result.status
+    }
+}
+
+impl Manifest {
+    /// Record a finished run
+    ///
+    /// Each mutation is prepared again against the restored files to capture
+    /// its diff, which is deterministic for unchanged sources. A group's
+    /// members are prepared together, one diff per file.
+    pub fn new(
+        config: &Config,
+        config_source: &[u8],
+        project_dir: &Path,
+        results: &[MutationResult],
+    ) -> Self {
+        let settings = &config.settings;
+        let crate_dir = settings.crate_dir(project_dir);
+        let entries = results
+            .iter()
+            .map(|result| {
+                let applied = !matches!(
+                    result.status,
+                    MutationStatus::ConfigError(_) | MutationStatus::NotRun
+                );
+                let single = config.mutations.iter().find(|m| m.id == result.mutation_id);
+                let members: Vec<&MutationConfig> = match single {
+                    Some(_) => Vec::new(),
+                    None => config
+                        .mutations
+                        .iter()
+                        .filter(|m| m.group.as_deref() == Some(result.mutation_id.as_str()))
+                        .collect(),
+                };
+                let group =
+                    (!members.is_empty()).then(|| group_config(&result.mutation_id, &members));
+                let mutation = single.or(group.as_ref());
+
+                let diff = single.filter(|_| applied).and_then(|mutation| {
+                    let source = std::fs::read_to_string(crate_dir.join(&mutation.file)).ok()?;
+                    let prepared = prepare_mutation(&source, mutation).ok()?;
+                    Some(LineDiff::between(&source, &prepared.mutated_source))
+                });
+                let group_diffs = if applied {
+                    group_file_diffs(&crate_dir, &members).unwrap_or_default()
+                } else {
+                    Vec::new()
+                };
+                ManifestEntry {
+                    mutation_id: result.mutation_id.clone(),
+                    file: result.file.clone(),
+                    function: result.function.clone(),
+                    original: result.original.clone(),
+                    replacement: result.replacement.clone(),
+                    line: result.line,
+                    column: result.column,
+                    diff,
+                    group_diffs,
+                    killed_by: mutation.and_then(|m| m.killed_by.clone()),
+                    env: mutation.and_then(|m| m.env.clone()),
+                    timeout: mutation.and_then(|m| m.timeout),
+                    status: result.status.clone(),
+                }
+            })
+            .collect();
+
+        Manifest {
+            config_hash: fnv1a_hex(config_source),
+            toolchain: settings.toolchain.clone(),
+            cargo_version: cargo_version(settings.toolchain.as_deref()),
+            cargo_args: cargo_test_args(settings, &[], false),
+            source_root: settings.source_root.clone(),
+            timeout: settings.timeout,
+            split_targets: settings.split_targets,
+            include_ignored: settings.include_ignored,
+            clean_build_retry: settings.clean_build_retry,
+            test_kinds: settings.test_kinds.clone(),
+            kill_on_exit_codes: settings.kill_on_exit_codes.clone(),
+            test_binary: settings.test_binary.clone(),
+            entries,
+        }
+    }
+
+    /// Write the manifest as JSON
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self).expect("manifest serializes to JSON");
+        std::fs::write(path, json).map_err(|e| MutationError::WriteError {
+            file: path.to_path_buf(),
+            error: e.to_string(),
+        })
+    }
+
+    /// Load a manifest written by [`Manifest::write`]
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path).map_err(|e| MutationError::FileReadError {
+            file: path.to_path_buf(),
+            error: e.to_string(),
+        })?;
+        serde_json::from_str(&json).map_err(|e| MutationError::ConfigError {
+            message: format!("Invalid manifest '{}': {}", path.display(), e),
+        })
+    }
+
+    /// The settings the recorded run used
+    pub fn settings(&self) -> Settings {
+        Settings {
+            timeout: self.timeout,
+            split_targets: self.split_targets,
+            source_root: self.source_root.clone(),
+            toolchain: self.toolchain.clone(),
+            include_ignored: self.include_ignored,
+            clean_build_retry: self.clean_build_retry,
+            test_kinds: self.test_kinds.clone(),
+            kill_on_exit_codes: self.kill_on_exit_codes.clone(),
+            test_binary: self.test_binary.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// Re-apply each recorded diff and re-run the tests
+    ///
+    /// Mutations that couldn't be applied in the recorded run, or weren't
+    /// tested because no test covered them or the run stopped before them,
+    /// are skipped. Fails with
+    /// [`MutationError::RunInProgress`] if another run holds the crate's lock
+    /// file.
+    pub fn replay(&self, project_dir: &Path, verbose: bool) -> Result<Vec<ReplayOutcome>> {
+        let mut settings = self.settings();
+        let crate_dir = settings.crate_dir(project_dir);
+        let _lock = RunLock::acquire(&crate_dir)?;
+        let selects_kinds = settings.test_binary.is_none() && !settings.test_kinds.is_empty();
+        let discovered = if settings.split_targets || selects_kinds {
+            discover_test_targets(&crate_dir).ok()
+        } else {
+            None
+        };
+        if let (true, Some(discovered)) = (selects_kinds, &discovered) {
+            settings.lib_target = Some(discovered.iter().any(|t| t.kind == TargetKind::Lib));
+        }
+        let targets = match discovered {
+            Some(targets) if settings.split_targets => {
+                select_targets(targets, &settings.test_kinds)
+            }
+            _ => Vec::new(),
+        };
+
+        let outcomes = self
+            .entries
+            .iter()
+            .filter_map(|entry| {
+                if matches!(entry.status, MutationStatus::NotCovered | MutationStatus::NotRun) {
+                    return None;
+                }
+                let files: Vec<(&Path, &LineDiff)> = match &entry.diff {
+                    Some(diff) => vec![(entry.file.as_path(), diff)],
+                    None => entry.group_diffs.iter().map(|d| (d.file.as_path(), &d.diff)).collect(),
+                };
+                if files.is_empty() {
+                    return None;
+                }
+                let mutation = MutationConfig {
+                    file: entry.file.clone(),
+                    function: entry.function.clone(),
+                    original: entry.original.clone(),
+                    replacement: entry.replacement.clone(),
+                    id: entry.mutation_id.clone(),
+                    line: entry.line,
+                    killed_by: entry.killed_by.clone(),
+                    env: entry.env.clone(),
+                    timeout: entry.timeout,
+                    ..Default::default()
+                };
+                // The recorded location is that of the first file's change
+                let mutates: Vec<_> = files
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &(file, diff))| {
+                        move |source: &str| {
+                            let mutated =
+                                diff.apply(source).ok_or_else(|| MutationError::ConfigError {
+                                    message: format!(
+                                        "{} changed since the recorded run; \
+                                         the diff no longer applies",
+                                        file.display()
+                                    ),
+                                })?;
+                            let first = i == 0;
+                            Ok(Mutant {
+                                source: mutated,
+                                line: entry.line.filter(|_| first).unwrap_or(diff.line),
+                                column: entry.column.filter(|_| first).unwrap_or(0),
+                                start_byte: None,
+                                end_byte: None,
+                            })
+                        }
+                    })
+                    .collect();
+                let file_mutations: Vec<FileMutation> = files
+                    .iter()
+                    .zip(&mutates)
+                    .map(|(&(file, _), mutate)| FileMutation { file, mutate })
+                    .collect();
+                let result = run_file_mutations(
+                    &mutation,
+                    &file_mutations,
+                    &crate_dir,
+                    &settings,
+                    &targets,
+                    verbose,
+                );
+                Some(ReplayOutcome {
+                    recorded: entry.status.clone(),
+                    result,
+                })
+            })
+            .collect();
+        Ok(outcomes)
+    }
+}
+
+/// The change a group's members make to each of their files, or `None` if a
+/// file can't be read or its members can't be applied
+fn group_file_diffs(crate_dir: &Path, members: &[&MutationConfig]) -> Option<Vec<FileDiff>> {
+    let mut files: Vec<&Path> = Vec::new();
+    for member in members {
+        if !files.contains(&member.file.as_path()) {
+            files.push(&member.file);
+        }
+    }
+    files
+        .into_iter()
+        .map(|file| {
+            let file_members: Vec<&MutationConfig> =
+                members.iter().copied().filter(|m| m.file == file).collect();
+            let source = std::fs::read_to_string(crate_dir.join(file)).ok()?;
+            let (mutated, _) = prepare_mutations(&source, &file_members).ok()?;
+            Some(FileDiff {
+                file: file.to_path_buf(),
+                diff: LineDiff::between(&source, &mutated),
+            })
+        })
+        .collect()
+}
+
+/// 64-bit FNV-1a hash, which unlike `DefaultHasher` is stable across Rust
+/// versions
+pub fn fnv1a_hex(bytes: &[u8]) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    format!("{:016x}", hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_diff_round_trip() {
+        let original = "fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n\nfn other() {}\n";
+        let mutated = "fn add(a: i32, b: i32) -> i32 {\n    a - b\n}\n\nfn other() {}\n";
+
+        let diff = LineDiff::between(original, mutated);
+        assert_eq!(
+            diff,
+            LineDiff {
+                line: 2,
+                removed: vec!["    a + b".to_string()],
+                added: vec!["    a - b".to_string()],
+            }
+        );
+        assert_eq!(diff.apply(original).unwrap(), mutated);
+
+        // The file changed since the diff was taken
+        assert!(diff.apply("fn add() {}\n").is_none());
+
+        // Identical files give an empty diff that applies as a no-op
+        let empty = LineDiff::between(original, original);
+        assert!(empty.removed.is_empty() && empty.added.is_empty());
+        assert_eq!(empty.apply(original).unwrap(), original);
+    }
+
+    #[test]
+    fn test_fnv1a_hex() {
+        assert_eq!(fnv1a_hex(b""), "cbf29ce484222325");
+        assert_eq!(fnv1a_hex(b"a"), "af63dc4c8601ec8c");
+    }
+
+    #[test]
+    fn test_not_run_is_not_replayed() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = "fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n";
+        std::fs::write(dir.path().join("lib.rs"), source).unwrap();
+        let config = Config::from_yaml_str(
+            r#"
+version: "1.0"
+mutations:
+  - id: add
+    file: lib.rs
+    function: add
+    original: a + b
+    replacement: a - b
+"#,
+        )
+        .unwrap();
+        let results = vec![crate::runner::not_run_result(&config.mutations[0])];
+
+        let manifest = Manifest::new(&config, b"", dir.path(), &results);
+        assert!(manifest.entries[0].diff.is_none());
+        assert!(manifest.replay(dir.path(), false).unwrap().is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_group_across_files_is_replayed() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        let lib = "mod b;\n\npub fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n";
+        let b = "pub fn double(x: i32) -> i32 {\n    x * 2\n}\n";
+        std::fs::write(dir.path().join("src/lib.rs"), lib).unwrap();
+        std::fs::write(dir.path().join("src/b.rs"), b).unwrap();
+        // Fails only when both files are mutated
+        let binary = dir.path().join("tests-bin");
+        let script = "#!/bin/sh\n! { grep -q 'a - b' src/lib.rs && grep -q 'x + 2' src/b.rs; }\n";
+        std::fs::write(&binary, script).unwrap();
+        std::fs::set_permissions(&binary, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let config = Config::from_yaml_str(
+            r#"
+version: "1.0"
+settings:
+  test_binary: tests-bin
+mutations:
+  - id: add
+    file: src/lib.rs
+    function: add
+    original: a + b
+    replacement: a - b
+    group: pair
+  - id: double
+    file: src/b.rs
+    function: double
+    original: x * 2
+    replacement: x + 2
+    group: pair
+"#,
+        )
+        .unwrap();
+        let members: Vec<&MutationConfig> = config.mutations.iter().collect();
+        let result = crate::runner::run_mutation_group(
+            "pair",
+            &members,
+            dir.path(),
+            &config.settings,
+            &[],
+            false,
+        );
+        assert_eq!(result.status, MutationStatus::Killed, "{:?}", result.details);
+
+        let manifest = Manifest::new(&config, b"", dir.path(), &[result]);
+        let entry = &manifest.entries[0];
+        assert!(entry.diff.is_none());
+        let files: Vec<&Path> = entry.group_diffs.iter().map(|d| d.file.as_path()).collect();
+        assert_eq!(files, [Path::new("src/lib.rs"), Path::new("src/b.rs")]);
+
+        let outcomes = manifest.replay(dir.path(), false).unwrap();
+        assert_eq!(outcomes.len(), 1);
+        assert!(!outcomes[0].changed(), "{:?}", outcomes[0].result.details);
+        assert_eq!(outcomes[0].result.line, Some(4));
+        assert_eq!(std::fs::read_to_string(dir.path().join("src/lib.rs")).unwrap(), lib);
+        assert_eq!(std::fs::read_to_string(dir.path().join("src/b.rs")).unwrap(), b);
+    }
+}
@@ -3,8 +3,10 @@
 //! This module finds expressions in Rust source code by comparing AST structures,
 //! ignoring whitespace and formatting differences.
 
+use syn::punctuated::Punctuated;
 use syn::visit::Visit;
-use syn::{BinOp, Expr, Lit, UnOp};
+use syn::visit_mut::VisitMut;
+use syn::{BinOp, Expr, Lit, Token, UnOp};
 
 use crate::error::MatchLocation;
 
@@ -15,8 +17,78 @@ pub struct MatchedSite {
     pub line: usize,
     /// Column number (1-indexed)
     pub column: usize,
+    /// Line just past the end of the match (1-indexed)
+    pub end_line: usize,
+    /// Column just past the end of the match (1-indexed)
+    pub end_column: usize,
+    /// Byte offset of the start of the match in the source, once located
+    /// (see [`MatchedSite::locate`])
+    pub start_byte: Option<usize>,
+    /// Byte offset just past the end of the match in the source, once located
+    pub end_byte: Option<usize>,
     /// The index of this match (for disambiguation when applying mutations)
     pub match_index: usize,
+    /// The matched code exactly as written in the source, if available
+    pub source: Option<String>,
+}
+
+/// Options that tweak how expressions are matched
+#[derive(Debug, Clone, Default)]
+pub struct MatchOptions {
+    /// Treat `-<literal>` as a single atomic literal, so the operand of a
+    /// negation is never matched on its own (`5` won't match inside `-5`)
+    pub atomic_negative_literals: bool,
+    /// Only match inside functions and consts that are part of the public
+    /// API (`pub` or `pub(crate)`)
+    pub pub_only: bool,
+    /// Don't match test code: `#[test]` functions and items marked or nested
+    /// in `#[cfg(test)]`
+    pub skip_tests: bool,
+    /// Only match a field or index access (`a.b`, `a[i]`) where it ends a
+    /// chain, not as the base of a longer one: `a.b` then matches neither the
+    /// `a.b` inside `a.b.c` nor the one inside `a.b[i]`
+    pub leaf_only: bool,
+    /// Also match inside the arguments of macro calls (see [`macro_args`])
+    pub in_macros: bool,
+}
+
+impl MatchOptions {
+    /// Whether the children of `expr` should be skipped during traversal
+    pub(crate) fn is_atomic(&self, expr: &Expr) -> bool {
+        self.atomic_negative_literals && is_negative_literal(expr)
+    }
+
+    /// Whether `expr` is a field or index access whose base is an access
+    /// too, which leaf-only matching doesn't match
+    ///
+    /// The base's own children are still matched, e.g. `i` in `a[i].b`.
+    pub(crate) fn skips_base(&self, expr: &Expr) -> bool {
+        let base = match expr {
+            Expr::Field(field) => &field.base,
+            Expr::Index(index) => &index.expr,
+            _ => return false,
+        };
+        self.leaf_only && matches!(**base, Expr::Field(_) | Expr::Index(_))
+    }
+}
+
+/// The arguments of a macro call, if they parse as comma-separated
+/// expressions, as in `assert!(a > b, "{}", c)` or `vec![a, b]`
+///
+/// The expressions keep the spans of the macro's tokens, so they point into
+/// the file. Macros with other syntax, like `vec![0; n]`, give `None`.
+pub fn macro_args(mac: &syn::Macro) -> Option<Punctuated<Expr, Token![,]>> {
+    mac.parse_body_with(Punctuated::parse_terminated).ok()
+}
+
+/// Check whether an expression is a negated literal such as `-5` or `-1.5`
+fn is_negative_literal(expr: &Expr) -> bool {
+    match expr {
+        Expr::Unary(unary) => {
+            matches!(unary.op, UnOp::Neg(_)) && matches!(*unary.expr, Expr::Lit(_))
+        }
+        _ => false,
+    }
 }
 
 impl MatchedSite {
@@ -26,6 +98,503 @@ impl MatchedSite {
             column: self.column,
         }
     }
+
+    /// Whether the code of this match and `other` overlaps
+    pub fn overlaps(&self, other: &MatchedSite) -> bool {
+        let start = |site: &MatchedSite| (site.line, site.column);
+        let end = |site: &MatchedSite| (site.end_line, site.end_column);
+        start(self) < end(other) && start(other) < end(self)
+    }
+
+    /// Fill in the byte offsets of the match in `source`, the text it was
+    /// parsed from
+    ///
+    /// The offsets are left unset if the span doesn't fit in `source`.
+    pub fn locate(&mut self, source: &str) {
+        let start = byte_offset(source, self.line, self.column);
+        let end = byte_offset(source, self.end_line, self.end_column);
+        if let (Some(start), Some(end)) = (start, end) {
+            self.start_byte = Some(start);
+            self.end_byte = Some(end);
+        }
+    }
+}
+
+/// Byte offset in `source` of a 1-indexed line and column (in chars)
+pub(crate) fn byte_offset(source: &str, line: usize, column: usize) -> Option<usize> {
+    let line_start = if line == 1 {
+        0
+    } else {
+        source.match_indices('\n').nth(line.checked_sub(2)?)?.0 + 1
+    };
+    let rest = &source[line_start..];
+    let offset = rest
+        .char_indices()
+        .nth(column.checked_sub(1)?)
+        .map_or(rest.len(), |(i, _)| i);
+    Some(line_start + offset)
+}
+
+/// The item a mutation's `function` field refers to
+///
+/// Either a bare name (`add`), which matches every function with that name,
+/// or a name qualified by its impl type or trait (`Counter::new`,
+/// `Counter::LIMIT`). Associated consts can only be targeted this way.
+///
+/// The owner may include generic arguments as written in the impl header
+/// (`Foo<T>::get`, `Foo<u8>::get`, `From<u16>::from`) to pick one of several
+/// impls of the same type or trait; without them it matches all of them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TargetName {
+    /// The impl type or trait name, if qualified
+    pub owner: Option<String>,
+    /// The function or const name
+    pub name: String,
+}
+
+impl TargetName {
+    /// Parse a `function` field such as `add` or `Counter::new`
+    pub fn parse(function: &str) -> Self {
+        match function.rsplit_once("::") {
+            Some((owner, name)) => TargetName {
+                owner: Some(compact(owner)),
+                name: name.trim().to_string(),
+            },
+            None => TargetName {
+                owner: None,
+                name: function.trim().to_string(),
+            },
+        }
+    }
+
+    /// Whether an item named `ident`, defined in a block with the given owner
+    /// names, is this target
+    fn matches(&self, ident: &syn::Ident, owners: &[String]) -> bool {
+        ident == &self.name
+            && self
+                .owner
+                .as_ref()
+                .is_none_or(|owner| owners.iter().any(|o| o == owner))
+    }
+}
+
+/// Names that qualify items of an impl block: the type and, for trait impls,
+/// the trait, each both bare and with its generic arguments
+fn impl_owners(item: &syn::ItemImpl) -> Vec<String> {
+    let mut owners = vec![type_name(&item.self_ty), generic_type_name(&item.self_ty)];
+    if let Some((_, path, _)) = &item.trait_ {
+        if let Some(segment) = path.segments.last() {
+            owners.push(segment.ident.to_string());
+            owners.push(segment_name(segment));
+        }
+    }
+    owners.dedup();
+    owners
+}
+
+/// The name of a type with its generic arguments but without module path,
+/// e.g. `Foo<u8>` for `crate::foo::Foo<u8>`
+fn generic_type_name(ty: &syn::Type) -> String {
+    use quote::ToTokens;
+    match ty {
+        syn::Type::Path(path) => match path.path.segments.last() {
+            Some(segment) => segment_name(segment),
+            None => compact(&ty.to_token_stream().to_string()),
+        },
+        _ => compact(&ty.to_token_stream().to_string()),
+    }
+}
+
+fn segment_name(segment: &syn::PathSegment) -> String {
+    use quote::ToTokens;
+    compact(&segment.to_token_stream().to_string())
+}
+
+/// Remove whitespace from a type, except between two words (`&'a str`,
+/// `Box<dyn Error>`), so `Foo<T, U>` and `Foo < T , U >` compare equal
+fn compact(text: &str) -> String {
+    let is_word = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+    let mut compacted = String::with_capacity(text.len());
+    let mut chars = text.trim().chars().peekable();
+    while let Some(c) = chars.next() {
+        if !c.is_whitespace() {
+            compacted.push(c);
+        } else if is_word(compacted.chars().last()) && is_word(chars.peek().copied()) {
+            compacted.push(' ');
+        }
+    }
+    compacted
+}
+
+/// The bare name of a type, without module path or generics
+fn type_name(ty: &syn::Type) -> String {
+    use quote::ToTokens;
+    match ty {
+        syn::Type::Path(path) => match path.path.segments.last() {
+            Some(segment) => segment.ident.to_string(),
+            None => ty.to_token_stream().to_string(),
+        },
+        _ => ty.to_token_stream().to_string(),
+    }
+}
+
+/// Whether a visibility counts as public API for `pub_only` matching
+fn is_public(vis: &syn::Visibility) -> bool {
+    match vis {
+        syn::Visibility::Public(_) => true,
+        syn::Visibility::Restricted(restricted) => restricted.path.is_ident("crate"),
+        syn::Visibility::Inherited => false,
+    }
+}
+
+/// Whether `attrs` mark test code: a `#[test]` attribute (including
+/// `#[tokio::test]` and the like) or `#[cfg(test)]`
+fn is_test_code(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|attr| {
+        let is_test = attr.path().segments.last().is_some_and(|s| s.ident == "test");
+        let is_cfg_test = attr.path().is_ident("cfg")
+            && matches!(&attr.meta, syn::Meta::List(list) if list.tokens.to_string() == "test");
+        is_test || is_cfg_test
+    })
+}
+
+/// The predicates of the `#[cfg(...)]` attributes in `attrs`
+///
+/// `cfg(test)` is left out, since the test run always enables it.
+fn cfg_predicates(attrs: &[syn::Attribute]) -> Vec<String> {
+    attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("cfg"))
+        .filter_map(|attr| match &attr.meta {
+            syn::Meta::List(list) => Some(list.tokens.to_string()),
+            _ => None,
+        })
+        .filter(|predicate| predicate != "test")
+        .collect()
+}
+
+/// Run `visitor` over every item matching `target` and return how many there were
+///
+/// Only the matching items are handed to the visitor, so it doesn't need to
+/// track which function it is in.
+pub(crate) fn visit_targets<'ast, V: Visit<'ast>>(
+    ast: &'ast syn::File,
+    target: &TargetName,
+    options: &MatchOptions,
+    visitor: &mut V,
+) -> usize {
+    let mut locator = TargetLocator::new(target, options, visitor);
+    locator.visit_file(ast);
+    locator.found
+}
+
+/// Mutable counterpart of [`visit_targets`]
+pub(crate) fn visit_targets_mut<V: VisitMut>(
+    ast: &mut syn::File,
+    target: &TargetName,
+    options: &MatchOptions,
+    visitor: &mut V,
+) -> usize {
+    let mut locator = TargetLocator::new(target, options, visitor);
+    locator.visit_file_mut(ast);
+    locator.found
+}
+
+/// Check whether any item in the file matches `target`
+pub fn target_exists(ast: &syn::File, target: &TargetName) -> bool {
+    target_exists_with(ast, target, &MatchOptions::default())
+}
+
+/// Check whether any item in the file matches `target`, using the given match
+/// options
+pub fn target_exists_with(ast: &syn::File, target: &TargetName, options: &MatchOptions) -> bool {
+    visit_targets(ast, target, options, &mut SkipBodies) > 0
+}
+
+/// The `cfg` predicates gating the items matching `target`
+///
+/// Includes the attributes of the items themselves and of enclosing modules,
+/// impls and traits, e.g. `feature = "x"` for a function in a
+/// `#[cfg(feature = "x")] mod`. The code is parsed regardless of `cfg`, but
+/// the test run only compiles it if the predicate holds.
+pub fn target_cfgs(ast: &syn::File, target: &TargetName) -> Vec<String> {
+    let mut visitor = SkipBodies;
+    let mut locator = TargetLocator::new(target, &MatchOptions::default(), &mut visitor);
+    locator.visit_file(ast);
+    let mut cfgs = locator.found_cfgs;
+    cfgs.sort();
+    cfgs.dedup();
+    cfgs
+}
+
+/// Visitor for [`visit_targets`] that doesn't look inside the matched items
+struct SkipBodies;
+
+impl<'ast> Visit<'ast> for SkipBodies {
+    fn visit_item_fn(&mut self, _: &'ast syn::ItemFn) {}
+    fn visit_impl_item_fn(&mut self, _: &'ast syn::ImplItemFn) {}
+    fn visit_trait_item_fn(&mut self, _: &'ast syn::TraitItemFn) {}
+    fn visit_impl_item_const(&mut self, _: &'ast syn::ImplItemConst) {}
+}
+
+struct TargetLocator<'a, V> {
+    target: &'a TargetName,
+    /// Only match items that are part of the public API
+    pub_only: bool,
+    /// Don't match test code
+    skip_tests: bool,
+    /// Whether the current block is test code
+    in_test: bool,
+    /// Names qualifying the impl or trait block currently being visited
+    owners: Vec<String>,
+    /// Whether items of the current block are public without a `pub` of their
+    /// own (methods of trait impls and of public traits)
+    inherits_pub: bool,
+    /// `cfg` predicates of the enclosing items (only tracked when visiting
+    /// immutably)
+    cfgs: Vec<String>,
+    /// `cfg` predicates gating the items found so far
+    found_cfgs: Vec<String>,
+    visitor: &'a mut V,
+    found: usize,
+}
+
+impl<'a, V> TargetLocator<'a, V> {
+    fn new(target: &'a TargetName, options: &MatchOptions, visitor: &'a mut V) -> Self {
+        TargetLocator {
+            target,
+            pub_only: options.pub_only,
+            skip_tests: options.skip_tests,
+            in_test: false,
+            owners: Vec::new(),
+            inherits_pub: false,
+            cfgs: Vec::new(),
+            found_cfgs: Vec::new(),
+            visitor,
+            found: 0,
+        }
+    }
+
+    /// Push the `cfg` predicates of an item being entered; returns the outer
+    /// length to truncate back to
+    fn push_cfgs(&mut self, attrs: &[syn::Attribute]) -> usize {
+        let outer = self.cfgs.len();
+        self.cfgs.extend(cfg_predicates(attrs));
+        outer
+    }
+
+    /// Record a found item along with the `cfg` predicates gating it
+    fn record(&mut self, attrs: &[syn::Attribute]) {
+        self.found += 1;
+        self.found_cfgs.extend(self.cfgs.iter().cloned());
+        self.found_cfgs.extend(cfg_predicates(attrs));
+    }
+
+    /// Whether an item with this name, visibility and attributes, in the
+    /// current block, is the target
+    fn is_target(
+        &self,
+        ident: &syn::Ident,
+        vis: Option<&syn::Visibility>,
+        attrs: &[syn::Attribute],
+    ) -> bool {
+        let visible = match vis {
+            Some(vis) => is_public(vis) || self.inherits_pub,
+            None => self.inherits_pub,
+        };
+        let test = self.in_test || is_test_code(attrs);
+        self.target.matches(ident, &self.owners)
+            && (visible || !self.pub_only)
+            && !(test && self.skip_tests)
+    }
+
+    /// Enter an item with `attrs`; returns whether the outer block was test
+    /// code, for [`Self::leave_test`]
+    fn enter_test(&mut self, attrs: &[syn::Attribute]) -> bool {
+        let outer = self.in_test;
+        self.in_test |= is_test_code(attrs);
+        outer
+    }
+
+    fn leave_test(&mut self, outer: bool) {
+        self.in_test = outer;
+    }
+
+    /// Enter an impl or trait block; returns the outer state for [`Self::leave`]
+    fn enter(&mut self, owners: Vec<String>, inherits_pub: bool) -> (Vec<String>, bool) {
+        let outer_owners = std::mem::replace(&mut self.owners, owners);
+        let outer_pub = std::mem::replace(&mut self.inherits_pub, inherits_pub);
+        (outer_owners, outer_pub)
+    }
+
+    fn leave(&mut self, (owners, inherits_pub): (Vec<String>, bool)) {
+        self.owners = owners;
+        self.inherits_pub = inherits_pub;
+    }
+}
+
+impl<'ast, V: Visit<'ast>> Visit<'ast> for TargetLocator<'_, V> {
+    fn visit_item_mod(&mut self, item: &'ast syn::ItemMod) {
+        let outer_cfgs = self.push_cfgs(&item.attrs);
+        let outer_test = self.enter_test(&item.attrs);
+        syn::visit::visit_item_mod(self, item);
+        self.leave_test(outer_test);
+        self.cfgs.truncate(outer_cfgs);
+    }
+
+    fn visit_item_impl(&mut self, item: &'ast syn::ItemImpl) {
+        let outer_cfgs = self.push_cfgs(&item.attrs);
+        let outer_test = self.enter_test(&item.attrs);
+        let outer = self.enter(impl_owners(item), item.trait_.is_some());
+        syn::visit::visit_item_impl(self, item);
+        self.leave(outer);
+        self.leave_test(outer_test);
+        self.cfgs.truncate(outer_cfgs);
+    }
+
+    fn visit_item_trait(&mut self, item: &'ast syn::ItemTrait) {
+        let outer_cfgs = self.push_cfgs(&item.attrs);
+        let outer_test = self.enter_test(&item.attrs);
+        let outer = self.enter(vec![item.ident.to_string()], is_public(&item.vis));
+        syn::visit::visit_item_trait(self, item);
+        self.leave(outer);
+        self.leave_test(outer_test);
+        self.cfgs.truncate(outer_cfgs);
+    }
+
+    fn visit_item_fn(&mut self, func: &'ast syn::ItemFn) {
+        if self.target.owner.is_none()
+            && self.is_target(&func.sig.ident, Some(&func.vis), &func.attrs)
+        {
+            self.record(&func.attrs);
+            self.visitor.visit_item_fn(func);
+        } else {
+            // Items nested in a function body are not part of the enclosing block
+            let outer_cfgs = self.push_cfgs(&func.attrs);
+            let outer_test = self.enter_test(&func.attrs);
+            let outer = self.enter(Vec::new(), false);
+            syn::visit::visit_item_fn(self, func);
+            self.leave(outer);
+            self.leave_test(outer_test);
+            self.cfgs.truncate(outer_cfgs);
+        }
+    }
+
+    fn visit_impl_item_fn(&mut self, func: &'ast syn::ImplItemFn) {
+        if self.is_target(&func.sig.ident, Some(&func.vis), &func.attrs) {
+            self.record(&func.attrs);
+            self.visitor.visit_impl_item_fn(func);
+        } else {
+            let outer_cfgs = self.push_cfgs(&func.attrs);
+            let outer_test = self.enter_test(&func.attrs);
+            let outer = self.enter(Vec::new(), false);
+            syn::visit::visit_impl_item_fn(self, func);
+            self.leave(outer);
+            self.leave_test(outer_test);
+            self.cfgs.truncate(outer_cfgs);
+        }
+    }
+
+    fn visit_trait_item_fn(&mut self, func: &'ast syn::TraitItemFn) {
+        // Only default methods have a body that can be mutated
+        if func.default.is_some() && self.is_target(&func.sig.ident, None, &func.attrs) {
+            self.record(&func.attrs);
+            self.visitor.visit_trait_item_fn(func);
+        } else {
+            let outer_cfgs = self.push_cfgs(&func.attrs);
+            let outer_test = self.enter_test(&func.attrs);
+            let outer = self.enter(Vec::new(), false);
+            syn::visit::visit_trait_item_fn(self, func);
+            self.leave(outer);
+            self.leave_test(outer_test);
+            self.cfgs.truncate(outer_cfgs);
+        }
+    }
+
+    fn visit_impl_item_const(&mut self, item: &'ast syn::ImplItemConst) {
+        // Consts are only matched by qualified name, so `LIMIT` alone never
+        // picks up a const that happens to share a function's name
+        if self.target.owner.is_some()
+            && self.is_target(&item.ident, Some(&item.vis), &item.attrs)
+        {
+            self.record(&item.attrs);
+            self.visitor.visit_impl_item_const(item);
+        }
+    }
+}
+
+impl<V: VisitMut> VisitMut for TargetLocator<'_, V> {
+    fn visit_item_mod_mut(&mut self, item: &mut syn::ItemMod) {
+        let outer_test = self.enter_test(&item.attrs);
+        syn::visit_mut::visit_item_mod_mut(self, item);
+        self.leave_test(outer_test);
+    }
+
+    fn visit_item_impl_mut(&mut self, item: &mut syn::ItemImpl) {
+        let outer_test = self.enter_test(&item.attrs);
+        let outer = self.enter(impl_owners(item), item.trait_.is_some());
+        syn::visit_mut::visit_item_impl_mut(self, item);
+        self.leave(outer);
+        self.leave_test(outer_test);
+    }
+
+    fn visit_item_trait_mut(&mut self, item: &mut syn::ItemTrait) {
+        let outer_test = self.enter_test(&item.attrs);
+        let outer = self.enter(vec![item.ident.to_string()], is_public(&item.vis));
+        syn::visit_mut::visit_item_trait_mut(self, item);
+        self.leave(outer);
+        self.leave_test(outer_test);
+    }
+
+    fn visit_item_fn_mut(&mut self, func: &mut syn::ItemFn) {
+        if self.target.owner.is_none()
+            && self.is_target(&func.sig.ident, Some(&func.vis), &func.attrs)
+        {
+            self.found += 1;
+            self.visitor.visit_item_fn_mut(func);
+        } else {
+            let outer_test = self.enter_test(&func.attrs);
+            let outer = self.enter(Vec::new(), false);
+            syn::visit_mut::visit_item_fn_mut(self, func);
+            self.leave(outer);
+            self.leave_test(outer_test);
+        }
+    }
+
+    fn visit_impl_item_fn_mut(&mut self, func: &mut syn::ImplItemFn) {
+        if self.is_target(&func.sig.ident, Some(&func.vis), &func.attrs) {
+            self.found += 1;
+            self.visitor.visit_impl_item_fn_mut(func);
+        } else {
+            let outer_test = self.enter_test(&func.attrs);
+            let outer = self.enter(Vec::new(), false);
+            syn::visit_mut::visit_impl_item_fn_mut(self, func);
+            self.leave(outer);
+            self.leave_test(outer_test);
+        }
+    }
+
+    fn visit_trait_item_fn_mut(&mut self, func: &mut syn::TraitItemFn) {
+        if func.default.is_some() && self.is_target(&func.sig.ident, None, &func.attrs) {
+            self.found += 1;
+            self.visitor.visit_trait_item_fn_mut(func);
+        } else {
+            let outer_test = self.enter_test(&func.attrs);
+            let outer = self.enter(Vec::new(), false);
+            syn::visit_mut::visit_trait_item_fn_mut(self, func);
+            self.leave(outer);
+            self.leave_test(outer_test);
+        }
+    }
+
+    fn visit_impl_item_const_mut(&mut self, item: &mut syn::ImplItemConst) {
+        if self.target.owner.is_some()
+            && self.is_target(&item.ident, Some(&item.vis), &item.attrs)
+        {
+            self.found += 1;
+            self.visitor.visit_impl_item_const_mut(item);
+        }
+    }
 }
 
 /// Find all occurrences of an expression within a specific function
@@ -33,23 +602,37 @@ pub fn find_expression_in_function(
     ast: &syn::File,
     function_name: &str,
     target_expr: &syn::Expr,
+) -> Vec<MatchedSite> {
+    find_expression_in_function_with(ast, function_name, target_expr, &MatchOptions::default())
+}
+
+/// Find all occurrences of an expression within a specific function, using the
+/// given match options
+pub fn find_expression_in_function_with(
+    ast: &syn::File,
+    function_name: &str,
+    target_expr: &syn::Expr,
+    options: &MatchOptions,
 ) -> Vec<MatchedSite> {
     let mut matcher = ExpressionMatcher {
         target: target_expr.clone(),
-        function_name: function_name.to_string(),
+        options: options.clone(),
         matches: Vec::new(),
-        in_target_function: false,
         current_match_index: 0,
     };
 
-    matcher.visit_file(ast);
+    visit_targets(ast, &TargetName::parse(function_name), options, &mut matcher);
     matcher.matches
 }
 
-/// Collect all function names in a file
+/// Collect all function names in a file, sorted and without duplicates
+///
+/// Methods of different impls often share a name; it is listed once.
 pub fn collect_function_names(ast: &syn::File) -> Vec<String> {
     let mut collector = FunctionCollector { functions: Vec::new() };
     collector.visit_file(ast);
+    collector.functions.sort();
+    collector.functions.dedup();
     collector.functions
 }
 
@@ -57,6 +640,185 @@ struct FunctionCollector {
     functions: Vec<String>,
 }
 
+/// Attributes the compiler handles itself, which never generate functions
+const BUILTIN_ATTRIBUTES: &[&str] = &[
+    "allow", "cfg", "cfg_attr", "cold", "deny", "deprecated", "doc", "expect", "forbid",
+    "inline", "must_use", "no_mangle", "non_exhaustive", "path", "repr", "test", "track_caller",
+    "warn",
+];
+
+/// Traits whose derives only implement the trait's own methods
+const STD_DERIVES: &[&str] =
+    &["Clone", "Copy", "Debug", "Default", "Eq", "Hash", "Ord", "PartialEq", "PartialOrd"];
+
+/// Describe the macros in a file that may generate functions
+///
+/// These are attribute macros, derives of non-std traits, and macro invocations
+/// in item position, e.g. `"#[tokio::main] on main (line 3)"`. The parsed
+/// source only has the unexpanded items, so a function they generate can't be
+/// found by name.
+pub fn macro_sites(ast: &syn::File) -> Vec<String> {
+    let mut collector = MacroSiteCollector { sites: Vec::new() };
+    collector.visit_file(ast);
+    collector.sites
+}
+
+struct MacroSiteCollector {
+    sites: Vec<String>,
+}
+
+impl MacroSiteCollector {
+    fn record(&mut self, attrs: &[syn::Attribute], name: &str) {
+        use quote::ToTokens;
+        for attr in attrs {
+            let path = attr.path().to_token_stream().to_string().replace(' ', "");
+            let generates = if path == "derive" {
+                let mut custom = false;
+                let _ = attr.parse_nested_meta(|meta| {
+                    let derived = meta.path.segments.last().map(|s| s.ident.to_string());
+                    custom |= !derived.is_some_and(|d| STD_DERIVES.contains(&d.as_str()));
+                    Ok(())
+                });
+                custom
+            } else {
+                !BUILTIN_ATTRIBUTES.contains(&path.as_str())
+            };
+            if generates {
+                let code = attr.to_token_stream().to_string().replace(' ', "");
+                let line = get_span(attr).start().line;
+                self.sites.push(format!("{} on {} (line {})", code, name, line));
+            }
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for MacroSiteCollector {
+    fn visit_item(&mut self, item: &'ast syn::Item) {
+        use quote::ToTokens;
+        match item {
+            syn::Item::Fn(i) => self.record(&i.attrs, &i.sig.ident.to_string()),
+            syn::Item::Struct(i) => self.record(&i.attrs, &i.ident.to_string()),
+            syn::Item::Enum(i) => self.record(&i.attrs, &i.ident.to_string()),
+            syn::Item::Union(i) => self.record(&i.attrs, &i.ident.to_string()),
+            syn::Item::Trait(i) => self.record(&i.attrs, &i.ident.to_string()),
+            syn::Item::Mod(i) => self.record(&i.attrs, &i.ident.to_string()),
+            syn::Item::Impl(i) => {
+                let name = i.self_ty.to_token_stream().to_string();
+                self.record(&i.attrs, &format!("impl {}", name))
+            }
+            syn::Item::Macro(i) if i.mac.path.is_ident("macro_rules") => {}
+            syn::Item::Macro(i) => {
+                let path = i.mac.path.to_token_stream().to_string().replace(' ', "");
+                let line = get_span(&i.mac.path).start().line;
+                self.sites.push(format!("{}! invocation (line {})", path, line));
+            }
+            _ => {}
+        }
+        syn::visit::visit_item(self, item);
+    }
+
+    fn visit_impl_item_fn(&mut self, func: &'ast syn::ImplItemFn) {
+        self.record(&func.attrs, &func.sig.ident.to_string());
+    }
+
+    fn visit_trait_item_fn(&mut self, func: &'ast syn::TraitItemFn) {
+        self.record(&func.attrs, &func.sig.ident.to_string());
+    }
+
+    // Items nested in function bodies can't be targeted
+    fn visit_block(&mut self, _block: &'ast syn::Block) {}
+}
+
+/// Where a function is defined
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FunctionKind {
+    /// A free function (`fn foo()`)
+    Free,
+    /// A method in an `impl` block, with the implementing type and its generic
+    /// arguments (`Foo<T>`)
+    Impl(String),
+    /// A default method in a `trait` block, with the trait name
+    Trait(String),
+}
+
+/// A function found in a source file, with enough context to disambiguate it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionRef {
+    /// The function's name
+    pub name: String,
+    /// The block the function is defined in
+    pub kind: FunctionKind,
+    /// Line of the function's name (1-indexed)
+    pub line: usize,
+}
+
+/// List all mutatable functions in a file along with their impl/trait context
+pub fn list_functions(ast: &syn::File) -> Vec<FunctionRef> {
+    let mut lister = FunctionLister {
+        functions: Vec::new(),
+        context: None,
+    };
+    lister.visit_file(ast);
+    lister.functions
+}
+
+struct FunctionLister {
+    functions: Vec<FunctionRef>,
+    /// The impl or trait block currently being visited
+    context: Option<FunctionKind>,
+}
+
+impl FunctionLister {
+    fn push(&mut self, ident: &syn::Ident, kind: FunctionKind) {
+        self.functions.push(FunctionRef {
+            name: ident.to_string(),
+            kind,
+            line: ident.span().start().line,
+        });
+    }
+}
+
+impl<'ast> Visit<'ast> for FunctionLister {
+    fn visit_item_impl(&mut self, item: &'ast syn::ItemImpl) {
+        let ty = generic_type_name(&item.self_ty);
+        let outer = self.context.replace(FunctionKind::Impl(ty));
+        syn::visit::visit_item_impl(self, item);
+        self.context = outer;
+    }
+
+    fn visit_item_trait(&mut self, item: &'ast syn::ItemTrait) {
+        let outer = self.context.replace(FunctionKind::Trait(item.ident.to_string()));
+        syn::visit::visit_item_trait(self, item);
+        self.context = outer;
+    }
+
+    fn visit_item_fn(&mut self, func: &'ast syn::ItemFn) {
+        self.push(&func.sig.ident, FunctionKind::Free);
+        // Items nested in a function body are not part of the enclosing block
+        let outer = self.context.take();
+        syn::visit::visit_item_fn(self, func);
+        self.context = outer;
+    }
+
+    fn visit_impl_item_fn(&mut self, func: &'ast syn::ImplItemFn) {
+        let kind = self.context.clone().unwrap_or(FunctionKind::Free);
+        self.push(&func.sig.ident, kind);
+        let outer = self.context.take();
+        syn::visit::visit_impl_item_fn(self, func);
+        self.context = outer;
+    }
+
+    fn visit_trait_item_fn(&mut self, func: &'ast syn::TraitItemFn) {
+        if func.default.is_some() {
+            let kind = self.context.clone().unwrap_or(FunctionKind::Free);
+            self.push(&func.sig.ident, kind);
+        }
+        let outer = self.context.take();
+        syn::visit::visit_trait_item_fn(self, func);
+        self.context = outer;
+    }
+}
+
 impl<'ast> Visit<'ast> for FunctionCollector {
     fn visit_item_fn(&mut self, func: &'ast syn::ItemFn) {
         self.functions.push(func.sig.ident.to_string());
@@ -67,68 +829,260 @@ impl<'ast> Visit<'ast> for FunctionCollector {
         self.functions.push(func.sig.ident.to_string());
         syn::visit::visit_impl_item_fn(self, func);
     }
+
+    fn visit_trait_item_fn(&mut self, func: &'ast syn::TraitItemFn) {
+        // Only default methods have a body that can be mutated
+        if func.default.is_some() {
+            self.functions.push(func.sig.ident.to_string());
+        }
+        syn::visit::visit_trait_item_fn(self, func);
+    }
 }
 
 struct ExpressionMatcher {
     target: syn::Expr,
-    function_name: String,
+    options: MatchOptions,
     matches: Vec<MatchedSite>,
-    in_target_function: bool,
     current_match_index: usize,
 }
 
-impl<'ast> Visit<'ast> for ExpressionMatcher {
-    fn visit_item_fn(&mut self, func: &'ast syn::ItemFn) {
-        if func.sig.ident == self.function_name {
-            self.in_target_function = true;
-            syn::visit::visit_item_fn(self, func);
-            self.in_target_function = false;
+impl ExpressionMatcher {
+    /// Visit `expr`, matching it unless it is the base of an access that
+    /// leaf-only matching skips
+    fn visit(&mut self, expr: &syn::Expr, matchable: bool) {
+        if matchable && ast_equals(expr, &self.target) {
+            let span = get_span(expr);
+            self.matches.push(MatchedSite {
+                line: span.start().line,
+                column: span.start().column + 1, // 1-indexed
+                end_line: span.end().line,
+                end_column: span.end().column + 1,
+                start_byte: None,
+                end_byte: None,
+                match_index: self.current_match_index,
+                source: span.source_text(),
+            });
+            self.current_match_index += 1;
+        }
+        if self.options.is_atomic(expr) {
+            return;
+        }
+        // Continue searching in child expressions
+        match expr {
+            syn::Expr::Field(field) if self.options.skips_base(expr) => {
+                self.visit(&field.base, false)
+            }
+            syn::Expr::Index(index) if self.options.skips_base(expr) => {
+                self.visit(&index.expr, false);
+                self.visit(&index.index, true);
+            }
+            _ => syn::visit::visit_expr(self, expr),
         }
     }
+}
 
-    fn visit_impl_item_fn(&mut self, func: &'ast syn::ImplItemFn) {
-        if func.sig.ident == self.function_name {
-            self.in_target_function = true;
-            syn::visit::visit_impl_item_fn(self, func);
-            self.in_target_function = false;
+impl<'ast> Visit<'ast> for ExpressionMatcher {
+    fn visit_expr(&mut self, expr: &'ast syn::Expr) {
+        self.visit(expr, true);
+    }
+
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        if !self.options.in_macros {
+            return;
+        }
+        for arg in macro_args(mac).iter().flatten() {
+            self.visit(arg, true);
         }
     }
+}
+
+/// An expression inside a function, as parsed by `syn`
+#[derive(Debug, Clone)]
+pub struct ExprSite {
+    /// Line number (1-indexed)
+    pub line: usize,
+    /// Column number (1-indexed)
+    pub column: usize,
+    /// The parsed expression
+    pub expr: syn::Expr,
+}
+
+/// List every expression in a function, outermost first
+///
+/// Useful for seeing how `syn` parsed the code when an `original` won't match.
+pub fn expressions_in_function(ast: &syn::File, function_name: &str) -> Vec<ExprSite> {
+    let mut lister = ExpressionLister {
+        expressions: Vec::new(),
+    };
+
+    visit_targets(ast, &TargetName::parse(function_name), &MatchOptions::default(), &mut lister);
+    lister.expressions
+}
+
+struct ExpressionLister {
+    expressions: Vec<ExprSite>,
+}
 
+impl<'ast> Visit<'ast> for ExpressionLister {
     fn visit_expr(&mut self, expr: &'ast syn::Expr) {
-        if self.in_target_function && ast_equals(expr, &self.target) {
+        let span = get_span(expr);
+        self.expressions.push(ExprSite {
+            line: span.start().line,
+            column: span.start().column + 1, // 1-indexed
+            expr: expr.clone(),
+        });
+        syn::visit::visit_expr(self, expr);
+    }
+}
+
+/// Lines marked with a `// mutate: <anchor>` comment: each the line after such
+/// a comment (1-indexed)
+///
+/// `syn` drops comments, so markers are found in the source text. Only
+/// comments on a line of their own count.
+pub fn anchor_lines(source: &str, anchor: &str) -> Vec<usize> {
+    source
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| {
+            line.trim_start()
+                .strip_prefix("//")
+                .and_then(|comment| comment.trim_start().strip_prefix("mutate:"))
+                .is_some_and(|name| name.trim() == anchor)
+        })
+        .map(|(index, _)| index + 2)
+        .collect()
+}
+
+/// The outermost expression of a function that starts and ends on `line`
+pub fn expression_on_line(ast: &syn::File, function_name: &str, line: usize) -> Option<ExprSite> {
+    expressions_in_function(ast, function_name)
+        .into_iter()
+        .find(|site| site.line == line && get_span(&site.expr).end().line == line)
+}
+
+/// Find expressions in a function whose code contains the target's tokens
+///
+/// This is a fallback for when [`find_expression_in_function`] finds nothing:
+/// `a + b` loosely matches `a + b as i64`. Only the innermost containing
+/// expression is reported, not every expression around it.
+pub fn find_near_matches(
+    ast: &syn::File,
+    function_name: &str,
+    target_expr: &syn::Expr,
+) -> Vec<ExprSite> {
+    use quote::ToTokens;
+    let mut matcher = NearMatcher {
+        needle: target_expr.to_token_stream().to_string(),
+        sites: Vec::new(),
+    };
+
+    visit_targets(ast, &TargetName::parse(function_name), &MatchOptions::default(), &mut matcher);
+    matcher.sites.sort_by_key(|site| (site.line, site.column));
+    matcher.sites
+}
+
+struct NearMatcher {
+    /// Token string of the target, spaced the same way as the candidates'
+    needle: String,
+    sites: Vec<ExprSite>,
+}
+
+impl<'ast> Visit<'ast> for NearMatcher {
+    fn visit_expr(&mut self, expr: &'ast syn::Expr) {
+        use quote::ToTokens;
+        let found = self.sites.len();
+        // Children first, so an enclosing expression is skipped when a
+        // smaller one already contains the target
+        syn::visit::visit_expr(self, expr);
+        if self.sites.len() == found && expr.to_token_stream().to_string().contains(&self.needle) {
             let span = get_span(expr);
+            self.sites.push(ExprSite {
+                line: span.start().line,
+                column: span.start().column + 1, // 1-indexed
+                expr: expr.clone(),
+            });
+        }
+    }
+}
+
+/// Find all occurrences of a block (`{ ... }`) within a specific function
+///
+/// Unlike expression matching, blocks are compared statement by statement,
+/// and blocks that are not expressions (loop and `if` bodies) are included.
+pub fn find_block_in_function(
+    ast: &syn::File,
+    function_name: &str,
+    target_block: &syn::Block,
+) -> Vec<MatchedSite> {
+    find_block_in_function_with(ast, function_name, target_block, &MatchOptions::default())
+}
+
+/// Find all occurrences of a block within a specific function, using the
+/// given match options
+pub fn find_block_in_function_with(
+    ast: &syn::File,
+    function_name: &str,
+    target_block: &syn::Block,
+    options: &MatchOptions,
+) -> Vec<MatchedSite> {
+    let mut matcher = BlockMatcher {
+        target: target_block.clone(),
+        matches: Vec::new(),
+        current_match_index: 0,
+    };
+
+    visit_targets(ast, &TargetName::parse(function_name), options, &mut matcher);
+    matcher.matches
+}
+
+struct BlockMatcher {
+    target: syn::Block,
+    matches: Vec<MatchedSite>,
+    current_match_index: usize,
+}
+
+impl<'ast> Visit<'ast> for BlockMatcher {
+    fn visit_block(&mut self, block: &'ast syn::Block) {
+        if block_equals(block, &self.target) {
+            let span = get_span(block);
             self.matches.push(MatchedSite {
                 line: span.start().line,
                 column: span.start().column + 1, // 1-indexed
+                end_line: span.end().line,
+                end_column: span.end().column + 1,
+                start_byte: None,
+                end_byte: None,
                 match_index: self.current_match_index,
+                source: span.source_text(),
             });
             self.current_match_index += 1;
         }
-        // Continue searching in child expressions
-        syn::visit::visit_expr(self, expr);
+        syn::visit::visit_block(self, block);
     }
 }
 
-/// Get the span of an expression
-fn get_span(expr: &syn::Expr) -> proc_macro2::Span {
-    use quote::ToTokens;
-    expr.to_token_stream()
-        .into_iter()
-        .next()
-        .map(|t| t.span())
-        .unwrap_or_else(proc_macro2::Span::call_site)
+/// Get the full span of a node, from its first token to its last
+fn get_span<T: syn::spanned::Spanned>(node: &T) -> proc_macro2::Span {
+    node.span()
 }
 
 /// Compare two AST expressions for structural equality (ignoring spans/whitespace)
 pub fn ast_equals(a: &Expr, b: &Expr) -> bool {
     match (a, b) {
-        // Binary expressions (a + b, a * b, etc.)
+        // Binary expressions (a + b, a * b, etc.), including compound
+        // assignments (x += 1), which `syn` parses as binary expressions
         (Expr::Binary(a), Expr::Binary(b)) => {
             ast_equals(&a.left, &b.left)
                 && binop_equals(&a.op, &b.op)
                 && ast_equals(&a.right, &b.right)
         }
 
+        // Assignments (x = y)
+        (Expr::Assign(a), Expr::Assign(b)) => {
+            ast_equals(&a.left, &b.left) && ast_equals(&a.right, &b.right)
+        }
+
         // Unary expressions (!a, -a, etc.)
         (Expr::Unary(a), Expr::Unary(b)) => unop_equals(&a.op, &b.op) && ast_equals(&a.expr, &b.expr),
 
@@ -155,6 +1109,7 @@ pub fn ast_equals(a: &Expr, b: &Expr) -> bool {
         (Expr::MethodCall(a), Expr::MethodCall(b)) => {
             ast_equals(&a.receiver, &b.receiver)
                 && a.method == b.method
+                && tokens_equal(&a.turbofish, &b.turbofish)
                 && a.args.len() == b.args.len()
                 && a.args.iter().zip(b.args.iter()).all(|(a, b)| ast_equals(a, b))
         }
@@ -197,10 +1152,14 @@ pub fn ast_equals(a: &Expr, b: &Expr) -> bool {
             // We don't compare the blocks for simpler matching
         }
 
-        // Block expressions
+        // `let` conditions (if let Some(x) = compute())
+        (Expr::Let(a), Expr::Let(b)) => pat_equals(&a.pat, &b.pat) && ast_equals(&a.expr, &b.expr),
+
+        // Block expressions, compared statement by statement so blocks that
+        // merely have the same length aren't conflated
         (Expr::Block(a), Expr::Block(b)) => {
-            a.block.stmts.len() == b.block.stmts.len()
-            // Simplified: don't do deep comparison of blocks
+            label_equals(a.label.as_ref().map(|l| &l.name), b.label.as_ref().map(|l| &l.name))
+                && block_equals(&a.block, &b.block)
         }
 
         // Return expressions
@@ -210,6 +1169,28 @@ pub fn ast_equals(a: &Expr, b: &Expr) -> bool {
             _ => false,
         },
 
+        // Loop control (break 'outer value, continue 'outer)
+        (Expr::Break(a), Expr::Break(b)) => {
+            label_equals(a.label.as_ref(), b.label.as_ref())
+                && match (&a.expr, &b.expr) {
+                    (Some(a), Some(b)) => ast_equals(a, b),
+                    (None, None) => true,
+                    _ => false,
+                }
+        }
+        (Expr::Continue(a), Expr::Continue(b)) => label_equals(a.label.as_ref(), b.label.as_ref()),
+
+        // Closures (|x| x + 1)
+        (Expr::Closure(a), Expr::Closure(b)) => {
+            a.movability.is_some() == b.movability.is_some()
+                && a.asyncness.is_some() == b.asyncness.is_some()
+                && a.capture.is_some() == b.capture.is_some()
+                && a.inputs.len() == b.inputs.len()
+                && a.inputs.iter().zip(b.inputs.iter()).all(|(a, b)| pat_equals(a, b))
+                && return_type_equals(&a.output, &b.output)
+                && ast_equals(&a.body, &b.body)
+        }
+
         // Range expressions (a..b, a..=b, etc.)
         (Expr::Range(a), Expr::Range(b)) => {
             match (&a.start, &b.start) {
@@ -239,6 +1220,35 @@ pub fn ast_equals(a: &Expr, b: &Expr) -> bool {
     }
 }
 
+/// Compare optional loop labels by name
+fn label_equals(a: Option<&syn::Lifetime>, b: Option<&syn::Lifetime>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => a.ident == b.ident,
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+/// Compare two blocks statement by statement (ignoring spans/whitespace)
+pub fn block_equals(a: &syn::Block, b: &syn::Block) -> bool {
+    a.stmts.len() == b.stmts.len()
+        && a.stmts.iter().zip(b.stmts.iter()).all(|(a, b)| stmt_equals(a, b))
+}
+
+fn stmt_equals(a: &syn::Stmt, b: &syn::Stmt) -> bool {
+    match (a, b) {
+        (syn::Stmt::Expr(a, a_semi), syn::Stmt::Expr(b, b_semi)) => {
+            a_semi.is_some() == b_semi.is_some() && (ast_equals(a, b) || tokens_equal(a, b))
+        }
+        // Locals, items and macros - just check the string representation
+        _ => tokens_equal(a, b),
+    }
+}
+
+fn tokens_equal<T: quote::ToTokens>(a: &T, b: &T) -> bool {
+    a.to_token_stream().to_string() == b.to_token_stream().to_string()
+}
+
 fn binop_equals(a: &BinOp, b: &BinOp) -> bool {
     std::mem::discriminant(a) == std::mem::discriminant(b)
 }
@@ -279,6 +1289,20 @@ fn member_equals(a: &syn::Member, b: &syn::Member) -> bool {
     }
 }
 
+fn pat_equals(a: &syn::Pat, b: &syn::Pat) -> bool {
+    // Simplified pattern comparison - just check the string representation
+    use quote::ToTokens;
+    a.to_token_stream().to_string() == b.to_token_stream().to_string()
+}
+
+fn return_type_equals(a: &syn::ReturnType, b: &syn::ReturnType) -> bool {
+    match (a, b) {
+        (syn::ReturnType::Default, syn::ReturnType::Default) => true,
+        (syn::ReturnType::Type(_, a), syn::ReturnType::Type(_, b)) => type_equals(a, b),
+        _ => false,
+    }
+}
+
 fn type_equals(a: &syn::Type, b: &syn::Type) -> bool {
     // Simplified type comparison - just check the string representation
     use quote::ToTokens;
@@ -301,6 +1325,35 @@ mod tests {
         assert!(!ast_equals(&parse_expr("a + b"), &parse_expr("x + y")));
     }
 
+    #[test]
+    fn test_assignment_equals() {
+        assert!(ast_equals(&parse_expr("x += 1"), &parse_expr("x+=1")));
+        assert!(!ast_equals(&parse_expr("x += 1"), &parse_expr("x -= 1")));
+        assert!(!ast_equals(&parse_expr("x += 1"), &parse_expr("x + 1")));
+        assert!(ast_equals(&parse_expr("self.total = 0"), &parse_expr("self.total=0")));
+        assert!(!ast_equals(&parse_expr("x = y"), &parse_expr("x = z")));
+        assert!(!ast_equals(&parse_expr("x = y"), &parse_expr("y = x")));
+    }
+
+    #[test]
+    fn test_reference_equals() {
+        assert!(ast_equals(&parse_expr("&mut x"), &parse_expr("& mut x")));
+        // Mutability is part of the reference
+        assert!(!ast_equals(&parse_expr("&mut x"), &parse_expr("&x")));
+        assert!(!ast_equals(&parse_expr("&x"), &parse_expr("&mut x")));
+        assert!(!ast_equals(&parse_expr("&x"), &parse_expr("&y")));
+    }
+
+    #[test]
+    fn test_block_expr_equals() {
+        assert!(ast_equals(&parse_expr("{ log(); a + b }"), &parse_expr("{log();a+b}")));
+        // Same number of statements, different code
+        assert!(!ast_equals(&parse_expr("{ log(); a + b }"), &parse_expr("{ log(); a - b }")));
+        assert!(!ast_equals(&parse_expr("{ a; }"), &parse_expr("{ a }")));
+        assert!(!ast_equals(&parse_expr("'outer: { a }"), &parse_expr("'inner: { a }")));
+        assert!(!ast_equals(&parse_expr("'outer: { a }"), &parse_expr("{ a }")));
+    }
+
     #[test]
     fn test_literal_equals() {
         assert!(ast_equals(&parse_expr("42"), &parse_expr("42")));
@@ -315,6 +1368,231 @@ mod tests {
         assert!(!ast_equals(&parse_expr("a >= b"), &parse_expr("a > b")));
     }
 
+    #[test]
+    fn test_closure_equals() {
+        assert!(ast_equals(&parse_expr("|x| x + 1"), &parse_expr("|x|x+1")));
+        assert!(!ast_equals(&parse_expr("|x| x + 1"), &parse_expr("|x| x - 1")));
+        assert!(!ast_equals(&parse_expr("|x| x + 1"), &parse_expr("|y| x + 1")));
+        assert!(!ast_equals(&parse_expr("|x| x + 1"), &parse_expr("move |x| x + 1")));
+        assert!(!ast_equals(&parse_expr("|x: i32| x + 1"), &parse_expr("|x| x + 1")));
+    }
+
+    #[test]
+    fn test_range_equals() {
+        let expr = |code: &str| syn::parse_str::<Expr>(code).unwrap();
+
+        assert!(ast_equals(&expr("0..n"), &expr("0 .. n")));
+        // `..` and `..=` are different limits
+        assert!(!ast_equals(&expr("0..n"), &expr("0..=n")));
+        assert!(ast_equals(&expr("0..=n"), &expr("0 ..= n")));
+        assert!(!ast_equals(&expr("0..n"), &expr("1..n")));
+        // Open-ended ranges only match the same missing bound
+        assert!(!ast_equals(&expr("0..n"), &expr("..n")));
+        assert!(!ast_equals(&expr("0.."), &expr("0..n")));
+        assert!(ast_equals(&expr(".."), &expr("..")));
+    }
+
+    #[test]
+    fn test_cast_equals() {
+        let expr = |code: &str| syn::parse_str::<Expr>(code).unwrap();
+
+        assert!(ast_equals(&expr("x as u32"), &expr("x  as  u32")));
+        // Casts differing only in width are different expressions
+        assert!(!ast_equals(&expr("x as u32"), &expr("x as u8")));
+        assert!(!ast_equals(&expr("x as u32"), &expr("x as i32")));
+        assert!(!ast_equals(&expr("x as u32"), &expr("y as u32")));
+        assert!(ast_equals(&expr("x as Vec<u8>"), &expr("x as Vec < u8 >")));
+    }
+
+    #[test]
+    fn test_let_condition_equals() {
+        let cond = |code: &str| match syn::parse_str::<Expr>(code).unwrap() {
+            Expr::If(expr_if) => *expr_if.cond,
+            other => panic!("expected an if expression, got {:?}", other),
+        };
+
+        assert!(ast_equals(
+            &cond("if let Some(x) = compute(a) {}"),
+            &cond("if let Some(x)=compute( a ) {}")
+        ));
+        // Both the pattern and the scrutinee must match
+        assert!(!ast_equals(
+            &cond("if let Some(x) = compute(a) {}"),
+            &cond("if let Some(y) = compute(a) {}")
+        ));
+        assert!(!ast_equals(
+            &cond("if let Some(x) = compute(a) {}"),
+            &cond("if let Some(x) = compute(b) {}")
+        ));
+    }
+
+    #[test]
+    fn test_anchor_lines() {
+        let source = "fn f(x: i32) -> bool {\n    // mutate: boundary\n    x >= 18\n}\n\
+fn g(x: i32) -> i32 {\n    /// mutate: boundary\n    x // mutate: boundary\n}\n\
+fn h(x: i32) -> i32 {\n    //mutate:twice\n    x\n    // mutate: twice\n    x\n}\n";
+
+        assert_eq!(anchor_lines(source, "boundary"), [3]);
+        assert_eq!(anchor_lines(source, "twice"), [11, 13]);
+        assert!(anchor_lines(source, "missing").is_empty());
+    }
+
+    #[test]
+    fn test_expression_on_line() {
+        use quote::ToTokens;
+
+        let source = "fn f(x: i32, y: i32) -> i32 {\n    let sum = x + y * 2;\n    if sum > 10 {\n        sum\n    } else {\n        0\n    }\n}\n";
+        let ast = syn::parse_file(source).unwrap();
+        let code = |line| {
+            expression_on_line(&ast, "f", line).map(|site| site.expr.to_token_stream().to_string())
+        };
+
+        // The outermost expression wins over its operands
+        assert_eq!(code(2).as_deref(), Some("x + y * 2"));
+        // The `if` spans several lines, so its condition is used
+        assert_eq!(code(3).as_deref(), Some("sum > 10"));
+        assert_eq!(code(1), None);
+    }
+
+    #[test]
+    fn test_break_continue_equals() {
+        let expr = |code: &str| syn::parse_str::<Expr>(code).unwrap();
+
+        assert!(ast_equals(&expr("break x+1"), &expr("break x + 1")));
+        assert!(ast_equals(&expr("break"), &expr("break")));
+        assert!(!ast_equals(&expr("break x"), &expr("break")));
+        assert!(!ast_equals(&expr("break x"), &expr("break y")));
+        assert!(ast_equals(&expr("break 'outer x"), &expr("break 'outer x")));
+        assert!(!ast_equals(&expr("break 'outer x"), &expr("break x")));
+
+        assert!(ast_equals(&expr("continue"), &expr("continue")));
+        assert!(ast_equals(&expr("continue 'rows"), &expr("continue 'rows")));
+        assert!(!ast_equals(&expr("continue 'rows"), &expr("continue")));
+        assert!(!ast_equals(&expr("continue"), &expr("break")));
+    }
+
+    #[test]
+    fn test_method_call_equals() {
+        assert!(ast_equals(&parse_expr("x.min(y)"), &parse_expr("x . min ( y )")));
+        assert!(!ast_equals(&parse_expr("x.min(y)"), &parse_expr("x.max(y)")));
+        assert!(!ast_equals(&parse_expr("v.iter()"), &parse_expr("v.iter_mut()")));
+        assert!(!ast_equals(
+            &parse_expr("v.collect::<Vec<_>>()"),
+            &parse_expr("v.collect::<HashSet<_>>()")
+        ));
+    }
+
+    #[test]
+    fn test_negative_literal_equals() {
+        // `-5` parses as a unary negation wrapping the literal `5`
+        assert!(ast_equals(&parse_expr("-5"), &parse_expr("-5")));
+        assert!(ast_equals(&parse_expr("-1.5"), &parse_expr("-1.5")));
+        assert!(!ast_equals(&parse_expr("-5"), &parse_expr("5")));
+    }
+
+    #[test]
+    fn test_find_literal_inside_negation() {
+        let source = r#"
+            fn clamp(x: i32) -> i32 {
+                if x < -5 { -5 } else { x }
+            }
+        "#;
+
+        let ast = syn::parse_file(source).unwrap();
+
+        // By default `5` also matches the operand of each `-5`
+        let matches = find_expression_in_function(&ast, "clamp", &parse_expr("-5"));
+        assert_eq!(matches.len(), 2);
+        let matches = find_expression_in_function(&ast, "clamp", &parse_expr("5"));
+        assert_eq!(matches.len(), 2);
+
+        // With atomic negative literals, `-5` is matched as a whole only
+        let options = MatchOptions {
+            atomic_negative_literals: true,
+            ..Default::default()
+        };
+        let matches = find_expression_in_function_with(&ast, "clamp", &parse_expr("-5"), &options);
+        assert_eq!(matches.len(), 2);
+        let matches = find_expression_in_function_with(&ast, "clamp", &parse_expr("5"), &options);
+        assert_eq!(matches.len(), 0);
+    }
+
+    #[test]
+    fn test_leaf_only_field_chains() {
+        let source = r#"
+            fn total(a: &Outer, i: usize) -> u32 {
+                let inner = a.b.c;
+                let outer = a.b;
+                inner + outer.len() + a.b[i].d[i]
+            }
+        "#;
+
+        let ast = syn::parse_file(source).unwrap();
+        let find = |original: &str, options: &MatchOptions| {
+            find_expression_in_function_with(&ast, "total", &parse_expr(original), options)
+                .iter()
+                .map(|site| (site.line, site.column))
+                .collect::<Vec<_>>()
+        };
+
+        // By default `a.b` also matches the base of `a.b.c` and `a.b[i]`
+        let options = MatchOptions::default();
+        assert_eq!(find("a.b", &options), [(3, 29), (4, 29), (5, 39)]);
+
+        let options = MatchOptions {
+            leaf_only: true,
+            ..Default::default()
+        };
+        assert_eq!(find("a.b", &options), [(4, 29)]);
+        assert_eq!(find("a.b[i]", &options), Vec::new());
+        // Ends of chains and the indices inside them are still matched
+        assert_eq!(find("a.b.c", &options), [(3, 29)]);
+        assert_eq!(find("a.b[i].d[i]", &options), [(5, 39)]);
+        assert_eq!(find("i", &options), [(5, 43), (5, 48)]);
+    }
+
+    #[test]
+    fn test_expressions_in_function() {
+        let source = r#"
+fn add(a: i32, b: i32) -> i32 {
+    a+b
+}
+"#;
+
+        let ast = syn::parse_file(source).unwrap();
+        let expressions = expressions_in_function(&ast, "add");
+
+        // The binary expression, then its two operands
+        assert_eq!(expressions.len(), 3);
+        assert!(matches!(expressions[0].expr, Expr::Binary(_)));
+        assert_eq!(expressions[0].line, 3);
+        assert!(matches!(expressions[1].expr, Expr::Path(_)));
+        assert_eq!(expressions[2].column, 7);
+    }
+
+    #[test]
+    fn test_find_block() {
+        let source = r#"
+            fn sum(values: &[i32]) -> i32 {
+                let mut total = 0;
+                for v in values {
+                    total += v;
+                }
+                total
+            }
+        "#;
+
+        let ast = syn::parse_file(source).unwrap();
+        let target: syn::Block = syn::parse_str("{ total += v; }").unwrap();
+
+        let matches = find_block_in_function(&ast, "sum", &target);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].line, 4);
+
+        let target: syn::Block = syn::parse_str("{ total -= v; }").unwrap();
+        assert!(find_block_in_function(&ast, "sum", &target).is_empty());
+    }
+
     #[test]
     fn test_find_expression() {
         let source = r#"
@@ -338,6 +1616,212 @@ mod tests {
         assert_eq!(matches.len(), 0);
     }
 
+    #[test]
+    fn test_find_expression_by_qualified_name() {
+        let source = r#"
+            impl Circle {
+                fn scale(&self) -> f64 { self.r * 2.0 }
+            }
+
+            impl Shape for Square {
+                fn scale(&self) -> f64 { self.r * 2.0 }
+            }
+        "#;
+
+        let ast = syn::parse_file(source).unwrap();
+        let target = parse_expr("self.r * 2.0");
+
+        assert_eq!(find_expression_in_function(&ast, "scale", &target).len(), 2);
+        assert_eq!(find_expression_in_function(&ast, "Circle::scale", &target).len(), 1);
+        assert_eq!(find_expression_in_function(&ast, "Shape::scale", &target).len(), 1);
+        assert!(find_expression_in_function(&ast, "Other::scale", &target).is_empty());
+    }
+
+    #[test]
+    fn test_qualified_name_with_generics() {
+        let source = r#"
+            impl<T> Foo<T> {
+                fn get(&self) -> usize { self.len + 1 }
+            }
+
+            impl Foo<u8> {
+                fn get(&self) -> usize { self.len + 1 }
+            }
+
+            impl From<u8> for Bar {
+                fn from(v: u8) -> Self { Bar(v as u32 + 1) }
+            }
+
+            impl From<u16> for Bar {
+                fn from(v: u16) -> Self { Bar(v as u32 + 1) }
+            }
+        "#;
+
+        let ast = syn::parse_file(source).unwrap();
+        let get = parse_expr("self.len + 1");
+        let from = parse_expr("v as u32 + 1");
+
+        // Without generic arguments the name matches every impl
+        assert_eq!(find_expression_in_function(&ast, "Foo::get", &get).len(), 2);
+        assert_eq!(find_expression_in_function(&ast, "Foo<T>::get", &get).len(), 1);
+        assert_eq!(find_expression_in_function(&ast, "Foo<u8>::get", &get).len(), 1);
+        assert!(find_expression_in_function(&ast, "Foo<u16>::get", &get).is_empty());
+        assert_eq!(find_expression_in_function(&ast, "From<u16>::from", &from).len(), 1);
+        assert_eq!(find_expression_in_function(&ast, "Bar::from", &from).len(), 2);
+
+        // The generic impl's match is a different site than the concrete one's
+        let generic = find_expression_in_function(&ast, "Foo<T>::get", &get);
+        let concrete = find_expression_in_function(&ast, "Foo<u8>::get", &get);
+        assert_ne!(generic[0].line, concrete[0].line);
+
+        // Listed functions carry the generic arguments, so they can be targeted
+        let owners: Vec<FunctionKind> = list_functions(&ast).into_iter().map(|f| f.kind).collect();
+        assert_eq!(owners[0], FunctionKind::Impl("Foo<T>".to_string()));
+        assert_eq!(owners[1], FunctionKind::Impl("Foo<u8>".to_string()));
+    }
+
+    #[test]
+    fn test_compact() {
+        assert_eq!(compact(" Foo < T , U > "), "Foo<T,U>");
+        assert_eq!(compact("Box < dyn Error >"), "Box<dyn Error>");
+        assert_eq!(compact("Parser<'a>"), "Parser<'a>");
+    }
+
+    #[test]
+    fn test_target_cfgs() {
+        let source = r#"
+#[cfg(feature = "simd")]
+mod fast {
+    #[cfg(target_arch = "x86_64")]
+    pub fn sum(values: &[u32]) -> u32 { values.iter().sum() }
+}
+
+pub fn plain() -> u32 { 1 }
+
+#[cfg(test)]
+mod tests {
+    fn helper() -> u32 { 2 }
+}
+"#;
+        let ast = syn::parse_file(source).unwrap();
+        assert_eq!(
+            target_cfgs(&ast, &TargetName::parse("sum")),
+            vec!["feature = \"simd\"", "target_arch = \"x86_64\""]
+        );
+        assert!(target_cfgs(&ast, &TargetName::parse("plain")).is_empty());
+        // The test run always enables cfg(test)
+        assert!(target_cfgs(&ast, &TargetName::parse("helper")).is_empty());
+    }
+
+    #[test]
+    fn test_macro_sites() {
+        let source = r#"
+#[derive(Debug, Clone, PartialEq)]
+pub struct Plain;
+
+#[derive(Debug, serde::Serialize)]
+pub struct Exported;
+
+#[inline]
+#[tokio::main]
+async fn main() {}
+
+make_getters! { width, height }
+
+macro_rules! local { () => {} }
+"#;
+        let ast = syn::parse_file(source).unwrap();
+        assert_eq!(
+            macro_sites(&ast),
+            vec![
+                "#[derive(Debug,serde::Serialize)] on Exported (line 5)",
+                "#[tokio::main] on main (line 9)",
+                "make_getters! invocation (line 12)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_pub_only_skips_private_functions() {
+        let source = r#"
+            pub fn area(w: u32, h: u32) -> u32 { w * h }
+            pub(crate) fn volume(w: u32, h: u32, d: u32) -> u32 { w * h * d }
+            fn helper(w: u32, h: u32) -> u32 { w * h }
+
+            impl Rect {
+                pub fn area(&self) -> u32 { self.w * self.h }
+                fn scaled(&self) -> u32 { self.w * self.h }
+            }
+
+            impl Shape for Rect {
+                fn size(&self) -> u32 { self.w * self.h }
+            }
+        "#;
+
+        let ast = syn::parse_file(source).unwrap();
+        let options = MatchOptions {
+            pub_only: true,
+            ..Default::default()
+        };
+        let public =
+            |function: &str| target_exists_with(&ast, &TargetName::parse(function), &options);
+
+        assert!(public("area"));
+        assert!(public("volume"));
+        assert!(public("Rect::area"));
+        // Trait impl methods are as public as the trait
+        assert!(public("Rect::size"));
+        assert!(!public("helper"));
+        assert!(!public("Rect::scaled"));
+        assert!(target_exists(&ast, &TargetName::parse("helper")));
+
+        // Only the public `w * h` is matched
+        let target = parse_expr("w * h");
+        assert_eq!(find_expression_in_function(&ast, "helper", &target).len(), 1);
+        assert!(find_expression_in_function_with(&ast, "helper", &target, &options).is_empty());
+    }
+
+    #[test]
+    fn test_skip_tests() {
+        let source = r#"
+            pub fn add(a: i32, b: i32) -> i32 { a + b }
+
+            #[test]
+            fn test_add() { let expected = 1 + 2; assert_eq!(add(1, 2), expected); }
+
+            #[tokio::test]
+            async fn test_async() { assert_eq!(add(1, 2), 1 + 2); }
+
+            #[cfg(test)]
+            mod tests {
+                fn expected(a: i32, b: i32) -> i32 { a + b }
+
+                impl Fixture {
+                    fn total(&self) -> i32 { self.a + self.b }
+                }
+            }
+        "#;
+
+        let ast = syn::parse_file(source).unwrap();
+        let options = MatchOptions {
+            skip_tests: true,
+            ..Default::default()
+        };
+        let outside_tests =
+            |function: &str| target_exists_with(&ast, &TargetName::parse(function), &options);
+
+        assert!(outside_tests("add"));
+        assert!(!outside_tests("test_add"));
+        assert!(!outside_tests("test_async"));
+        assert!(!outside_tests("expected"));
+        assert!(!outside_tests("Fixture::total"));
+        // Without the option, test code is matched as before
+        assert!(target_exists(&ast, &TargetName::parse("test_add")));
+        let target = parse_expr("1 + 2");
+        assert_eq!(find_expression_in_function(&ast, "test_add", &target).len(), 1);
+        assert!(find_expression_in_function_with(&ast, "test_add", &target, &options).is_empty());
+    }
+
     #[test]
     fn test_collect_functions() {
         let source = r#"
@@ -347,6 +1831,11 @@ mod tests {
             impl Thing {
                 fn baz(&self) {}
             }
+
+            trait Shape {
+                fn area(&self) -> f64;
+                fn double_area(&self) -> f64 { self.area() * 2.0 }
+            }
         "#;
 
         let ast = syn::parse_file(source).unwrap();
@@ -355,5 +1844,48 @@ mod tests {
         assert!(functions.contains(&"foo".to_string()));
         assert!(functions.contains(&"bar".to_string()));
         assert!(functions.contains(&"baz".to_string()));
+        assert!(functions.contains(&"double_area".to_string()));
+        // Required trait methods have no body to mutate
+        assert!(!functions.contains(&"area".to_string()));
+    }
+
+    #[test]
+    fn test_list_functions() {
+        let source = r#"
+fn foo() {}
+
+impl Thing {
+    fn baz(&self) {}
+}
+
+trait Shape {
+    fn area(&self) -> f64;
+    fn double_area(&self) -> f64 { self.area() * 2.0 }
+}
+"#;
+
+        let ast = syn::parse_file(source).unwrap();
+        let functions = list_functions(&ast);
+
+        assert_eq!(
+            functions,
+            vec![
+                FunctionRef {
+                    name: "foo".to_string(),
+                    kind: FunctionKind::Free,
+                    line: 2,
+                },
+                FunctionRef {
+                    name: "baz".to_string(),
+                    kind: FunctionKind::Impl("Thing".to_string()),
+                    line: 5,
+                },
+                FunctionRef {
+                    name: "double_area".to_string(),
+                    kind: FunctionKind::Trait("Shape".to_string()),
+                    line: 10,
+                },
+            ]
+        );
     }
 }
@@ -3,10 +3,13 @@
 //! This module applies mutations to the AST by replacing matched expressions
 //! with their replacement counterparts.
 
+use quote::ToTokens;
 use syn::visit_mut::VisitMut;
 
 use crate::error::{MutationError, Result};
-use crate::matcher::{ast_equals, MatchedSite};
+use crate::matcher::{
+    ast_equals, block_equals, macro_args, visit_targets_mut, MatchOptions, MatchedSite, TargetName,
+};
 
 /// Applies a single mutation to the AST
 pub struct Mutator {
@@ -14,54 +17,105 @@ pub struct Mutator {
     target: syn::Expr,
     /// The replacement expression
     replacement: syn::Expr,
-    /// The function to search in
-    function_name: String,
+    /// Options controlling how matches are counted
+    options: MatchOptions,
     /// Index of the match to replace (for disambiguation)
     target_index: usize,
     /// Current match index during traversal
     current_index: usize,
-    /// Whether we're currently in the target function
-    in_target_function: bool,
     /// Whether the mutation was applied
     applied: bool,
+    /// How many macro calls the expression being visited is an argument of
+    macro_depth: usize,
+    /// Whether the expression being visited is a whole macro argument
+    macro_arg: bool,
 }
 
-impl VisitMut for Mutator {
-    fn visit_item_fn_mut(&mut self, func: &mut syn::ItemFn) {
-        if func.sig.ident == self.function_name {
-            self.in_target_function = true;
-            syn::visit_mut::visit_item_fn_mut(self, func);
-            self.in_target_function = false;
-        }
-    }
-
-    fn visit_impl_item_fn_mut(&mut self, func: &mut syn::ImplItemFn) {
-        if func.sig.ident == self.function_name {
-            self.in_target_function = true;
-            syn::visit_mut::visit_impl_item_fn_mut(self, func);
-            self.in_target_function = false;
-        }
-    }
-
-    fn visit_expr_mut(&mut self, expr: &mut syn::Expr) {
+impl Mutator {
+    /// Visit `expr`, counting it as a match unless it is the base of an
+    /// access that leaf-only matching skips
+    fn visit(&mut self, expr: &mut syn::Expr, matchable: bool) {
+        let macro_arg = std::mem::take(&mut self.macro_arg);
         if self.applied {
             return; // Already applied, skip
         }
 
-        if self.in_target_function && ast_equals(expr, &self.target) {
+        if matchable && ast_equals(expr, &self.target) {
             if self.current_index == self.target_index {
-                *expr = self.replacement.clone();
+                // Macro tokens aren't reparenthesized when printed, so an
+                // operand inside one needs its own parentheses
+                let replacement = &self.replacement;
+                *expr = if self.macro_depth > 0 && !macro_arg && !binds_tightly(replacement) {
+                    syn::parse_quote!((#replacement))
+                } else {
+                    replacement.clone()
+                };
                 self.applied = true;
                 return; // Don't recurse into replacement
             }
             self.current_index += 1;
         }
 
+        if self.options.is_atomic(expr) {
+            return;
+        }
+
         // Continue visiting children
-        syn::visit_mut::visit_expr_mut(self, expr);
+        let skips_base = self.options.skips_base(expr);
+        match expr {
+            syn::Expr::Field(field) if skips_base => self.visit(&mut field.base, false),
+            syn::Expr::Index(index) if skips_base => {
+                self.visit(&mut index.expr, false);
+                self.visit(&mut index.index, true);
+            }
+            _ => syn::visit_mut::visit_expr_mut(self, expr),
+        }
+    }
+}
+
+impl VisitMut for Mutator {
+    fn visit_expr_mut(&mut self, expr: &mut syn::Expr) {
+        self.visit(expr, true);
+    }
+
+    /// Mutate inside the macro's arguments and write them back as its tokens
+    fn visit_macro_mut(&mut self, mac: &mut syn::Macro) {
+        if self.applied || !self.options.in_macros {
+            return;
+        }
+        let Some(mut args) = macro_args(mac) else {
+            return;
+        };
+        self.macro_depth += 1;
+        for arg in args.iter_mut() {
+            self.macro_arg = true;
+            self.visit(arg, true);
+        }
+        self.macro_depth -= 1;
+        if self.applied {
+            mac.tokens = args.to_token_stream();
+        }
     }
 }
 
+/// Whether `expr` binds at least as tightly as any operator around it, so it
+/// can replace an operand without parentheses
+fn binds_tightly(expr: &syn::Expr) -> bool {
+    matches!(
+        expr,
+        syn::Expr::Path(_)
+            | syn::Expr::Lit(_)
+            | syn::Expr::Call(_)
+            | syn::Expr::MethodCall(_)
+            | syn::Expr::Field(_)
+            | syn::Expr::Index(_)
+            | syn::Expr::Paren(_)
+            | syn::Expr::Macro(_)
+            | syn::Expr::Tuple(_)
+            | syn::Expr::Block(_)
+    )
+}
+
 impl Mutator {
     /// Apply a mutation to the AST
     ///
@@ -77,18 +131,41 @@ impl Mutator {
         target: &syn::Expr,
         replacement: &syn::Expr,
         target_site: &MatchedSite,
+    ) -> Result<()> {
+        Self::apply_with(
+            ast,
+            function_name,
+            target,
+            replacement,
+            target_site,
+            &MatchOptions::default(),
+        )
+    }
+
+    /// Apply a mutation to the AST, counting matches with the given options
+    ///
+    /// The options must be the same ones used to find `target_site`, otherwise
+    /// the match index may refer to a different occurrence.
+    pub fn apply_with(
+        ast: &mut syn::File,
+        function_name: &str,
+        target: &syn::Expr,
+        replacement: &syn::Expr,
+        target_site: &MatchedSite,
+        options: &MatchOptions,
     ) -> Result<()> {
         let mut mutator = Mutator {
             target: target.clone(),
             replacement: replacement.clone(),
-            function_name: function_name.to_string(),
+            options: options.clone(),
             target_index: target_site.match_index,
             current_index: 0,
-            in_target_function: false,
             applied: false,
+            macro_depth: 0,
+            macro_arg: false,
         };
 
-        mutator.visit_file_mut(ast);
+        visit_targets_mut(ast, &TargetName::parse(function_name), options, &mut mutator);
 
         if !mutator.applied {
             return Err(MutationError::FailedToApply {
@@ -100,10 +177,92 @@ impl Mutator {
     }
 }
 
+/// Applies a single block replacement to the AST
+pub struct BlockMutator {
+    /// The original block to find
+    target: syn::Block,
+    /// The replacement block
+    replacement: syn::Block,
+    /// Index of the match to replace (for disambiguation)
+    target_index: usize,
+    /// Current match index during traversal
+    current_index: usize,
+    /// Whether the mutation was applied
+    applied: bool,
+}
+
+impl VisitMut for BlockMutator {
+    fn visit_block_mut(&mut self, block: &mut syn::Block) {
+        if self.applied {
+            return;
+        }
+
+        if block_equals(block, &self.target) {
+            if self.current_index == self.target_index {
+                // Keep the original braces so the block stays in place
+                block.stmts = self.replacement.stmts.clone();
+                self.applied = true;
+                return;
+            }
+            self.current_index += 1;
+        }
+
+        syn::visit_mut::visit_block_mut(self, block);
+    }
+}
+
+impl BlockMutator {
+    /// Replace the block at `target_site` with `replacement`
+    pub fn apply(
+        ast: &mut syn::File,
+        function_name: &str,
+        target: &syn::Block,
+        replacement: &syn::Block,
+        target_site: &MatchedSite,
+    ) -> Result<()> {
+        Self::apply_with(
+            ast,
+            function_name,
+            target,
+            replacement,
+            target_site,
+            &MatchOptions::default(),
+        )
+    }
+
+    /// Replace the block at `target_site`, locating it with the given options
+    pub fn apply_with(
+        ast: &mut syn::File,
+        function_name: &str,
+        target: &syn::Block,
+        replacement: &syn::Block,
+        target_site: &MatchedSite,
+        options: &MatchOptions,
+    ) -> Result<()> {
+        let mut mutator = BlockMutator {
+            target: target.clone(),
+            replacement: replacement.clone(),
+            target_index: target_site.match_index,
+            current_index: 0,
+            applied: false,
+        };
+
+        visit_targets_mut(ast, &TargetName::parse(function_name), options, &mut mutator);
+
+        if !mutator.applied {
+            return Err(MutationError::FailedToApply {
+                reason: "Target block not found during mutation".to_string(),
+            });
+        }
+
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::matcher::find_expression_in_function;
+    use crate::matcher::{find_block_in_function, find_expression_in_function};
 
     #[test]
     fn test_apply_mutation() {
@@ -151,4 +310,52 @@ fn calc(a: i32, b: i32) -> i32 {
         assert!(mutated_source.contains("a - b"));
         assert!(mutated_source.contains("a + b"));
     }
+
+    #[test]
+    fn test_apply_in_trait_default_method() {
+        let source = r#"
+trait Shape {
+    fn area(&self) -> f64;
+    fn double_area(&self) -> f64 {
+        self.area() * 2.0
+    }
+}
+"#;
+        let mut ast = syn::parse_file(source).unwrap();
+        let target: syn::Expr = syn::parse_str("self.area() * 2.0").unwrap();
+        let replacement: syn::Expr = syn::parse_str("self.area() / 2.0").unwrap();
+
+        let matches = find_expression_in_function(&ast, "double_area", &target);
+        assert_eq!(matches.len(), 1);
+
+        Mutator::apply(&mut ast, "double_area", &target, &replacement, &matches[0]).unwrap();
+
+        let mutated_source = prettyplease::unparse(&ast);
+        assert!(mutated_source.contains("self.area() / 2.0"));
+    }
+
+    #[test]
+    fn test_apply_block_mutation() {
+        let source = r#"
+fn sum(values: &[i32]) -> i32 {
+    let mut total = 0;
+    for v in values {
+        total += v;
+    }
+    total
+}
+"#;
+        let mut ast = syn::parse_file(source).unwrap();
+        let target: syn::Block = syn::parse_str("{ total += v; }").unwrap();
+        let replacement: syn::Block = syn::parse_str("{}").unwrap();
+
+        let matches = find_block_in_function(&ast, "sum", &target);
+        assert_eq!(matches.len(), 1);
+
+        BlockMutator::apply(&mut ast, "sum", &target, &replacement, &matches[0]).unwrap();
+
+        let mutated_source = prettyplease::unparse(&ast);
+        assert!(mutated_source.contains("for v in values {}"));
+        assert!(!mutated_source.contains("total += v"));
+    }
 }
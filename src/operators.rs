@@ -0,0 +1,1092 @@
+//! Built-in mutation operators
+//!
+//! An operator generates the `replacement` for a mutation from its `original`,
+//! so common mutations don't need the replacement spelled out in the config.
+//! Generators go one step further and produce whole mutations for a file;
+//! they implement [`MutationOperator`] and are looked up by name in an
+//! [`OperatorRegistry`], which custom generators can be added to.
+
+use std::path::Path;
+
+use quote::ToTokens;
+use serde::{Deserialize, Serialize};
+use syn::spanned::Spanned;
+use syn::visit::Visit;
+use syn::visit_mut::VisitMut;
+
+use crate::config::MutationConfig;
+use crate::error::{MutationError, Result};
+use crate::matcher::{
+    find_expression_in_function_with, list_functions, macro_args, visit_targets, FunctionKind,
+    MatchOptions, TargetName,
+};
+
+/// A mutation operator that can be named in the config via `mutation_op`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MutationOp {
+    /// Replace a returned value with `Default::default()`
+    ///
+    /// Works on `return <e>` and on tail expressions. Types that don't
+    /// implement `Default` surface as a compile error for the mutant.
+    ReturnDefault,
+}
+
+impl MutationOp {
+    /// Generate the replacement code for `original`
+    pub fn replacement_for(&self, original: &str) -> Result<String> {
+        let expr: syn::Expr =
+            syn::parse_str(original).map_err(|e| MutationError::InvalidOriginal {
+                code: original.to_string(),
+                parse_error: e.to_string(),
+            })?;
+
+        match self {
+            MutationOp::ReturnDefault => match expr {
+                syn::Expr::Return(ret) if ret.expr.is_none() => Err(MutationError::ConfigError {
+                    message: format!(
+                        "return_default needs a returned value, but '{}' returns nothing",
+                        original
+                    ),
+                }),
+                syn::Expr::Return(_) => Ok("return Default::default()".to_string()),
+                _ => Ok("Default::default()".to_string()),
+            },
+        }
+    }
+}
+
+/// A generator of whole mutations for a file
+///
+/// The built-in generators implement this, and so can project-specific rules:
+/// register them in an [`OperatorRegistry`] next to the built-ins.
+pub trait MutationOperator {
+    /// Name the operator is selected by, as in `generate --ops <name>`
+    fn name(&self) -> &str;
+
+    /// Generate mutations for every function in `ast`, parsed from `file`
+    fn generate(&self, ast: &syn::File, file: &Path) -> Vec<MutationConfig>;
+}
+
+/// Macros whose arguments generators skip by default: debug-only assertions,
+/// printing and logging (matched by their last path segment, so `debug` covers
+/// `log::debug!` and `tracing::debug!`), whose arguments tests rarely observe
+pub const SKIPPED_MACROS: &[&str] = &[
+    "debug_assert",
+    "debug_assert_eq",
+    "debug_assert_ne",
+    "print",
+    "println",
+    "eprint",
+    "eprintln",
+    "dbg",
+    "trace",
+    "debug",
+    "info",
+    "warn",
+    "error",
+];
+
+/// A set of mutation operators, looked up by name
+pub struct OperatorRegistry {
+    operators: Vec<Box<dyn MutationOperator>>,
+    skipped_macros: Vec<String>,
+}
+
+impl OperatorRegistry {
+    /// A registry with no operators
+    pub fn new() -> Self {
+        OperatorRegistry {
+            operators: Vec::new(),
+            skipped_macros: SKIPPED_MACROS.iter().map(|name| name.to_string()).collect(),
+        }
+    }
+
+    /// Set the macros whose arguments generators skip, replacing
+    /// [`SKIPPED_MACROS`]; with none, every macro whose arguments parse as
+    /// expressions is mutated inside
+    pub fn skip_macros(&mut self, names: Vec<String>) {
+        self.skipped_macros = names;
+    }
+
+    /// A registry with the built-in operators: `cond`, `arith`, `cmp`,
+    /// `logic` and `overflow`
+    pub fn with_builtins() -> Self {
+        let mut registry = Self::new();
+        registry.register(ConditionConstants);
+        registry.register(BinaryOpSwap::arithmetic());
+        registry.register(BinaryOpSwap::comparison());
+        registry.register(BinaryOpSwap::logical());
+        registry.register(OverflowArithmetic);
+        registry.register(BoundaryShift);
+        registry.register(ReferenceMutability);
+        registry
+    }
+
+    /// Add an operator, replacing any registered under the same name
+    pub fn register(&mut self, operator: impl MutationOperator + 'static) {
+        self.operators.retain(|o| o.name() != operator.name());
+        self.operators.push(Box::new(operator));
+    }
+
+    /// The operator registered under `name`
+    pub fn get(&self, name: &str) -> Option<&dyn MutationOperator> {
+        self.operators.iter().find(|o| o.name() == name).map(|o| o.as_ref())
+    }
+
+    /// Names of the registered operators, in registration order
+    pub fn names(&self) -> Vec<&str> {
+        self.operators.iter().map(|o| o.name()).collect()
+    }
+
+    /// Run the named operators over a file, in the given order
+    ///
+    /// The operators see the arguments of macro calls as expressions, except
+    /// for the skipped macros. Mutations that only match inside macro
+    /// arguments get `in_macros` set.
+    pub fn generate(&self, names: &[String], ast: &syn::File, file: &Path) -> Result<Vec<MutationConfig>> {
+        let mut expanded = ast.clone();
+        let mut expander = MacroExpander {
+            skipped: &self.skipped_macros,
+            expanded: 0,
+        };
+        expander.visit_file_mut(&mut expanded);
+
+        let mut mutations = Vec::new();
+        for name in names {
+            let operator = self.get(name).ok_or_else(|| MutationError::ConfigError {
+                message: format!(
+                    "Unknown mutation operator '{}' (available: {})",
+                    name,
+                    self.names().join(", ")
+                ),
+            })?;
+            mutations.extend(operator.generate(&expanded, file));
+        }
+        if expander.expanded > 0 {
+            for mutation in &mut mutations {
+                mutation.in_macros = !matches_on_line(ast, mutation, false)
+                    && matches_on_line(ast, mutation, true);
+            }
+        }
+        Ok(mutations)
+    }
+}
+
+/// Whether `mutation` matches on its line of `ast`, looking inside macro
+/// arguments or not
+fn matches_on_line(ast: &syn::File, mutation: &MutationConfig, in_macros: bool) -> bool {
+    let Ok(original) = syn::parse_str::<syn::Expr>(&mutation.original) else {
+        return false;
+    };
+    let options = MatchOptions {
+        in_macros,
+        ..mutation.match_options()
+    };
+    find_expression_in_function_with(ast, &mutation.function, &original, &options)
+        .iter()
+        .any(|site| mutation.line.is_none_or(|line| site.line == line))
+}
+
+/// Replaces macro calls whose arguments parse as expressions with a tuple of
+/// those arguments, so generators, which don't look inside macros, visit them
+///
+/// The tuple spans the whole call, so a generator taking the call itself as
+/// an operand still records its code and line.
+struct MacroExpander<'a> {
+    skipped: &'a [String],
+    /// How many macro calls were replaced
+    expanded: usize,
+}
+
+impl MacroExpander<'_> {
+    /// The tuple standing in for a call of `mac`, if its arguments are
+    /// expressions and it isn't skipped
+    fn tuple(&mut self, mac: &syn::Macro, attrs: &[syn::Attribute]) -> Option<syn::Expr> {
+        let name = mac.path.segments.last()?.ident.to_string();
+        if self.skipped.contains(&name) {
+            return None;
+        }
+        let elems = macro_args(mac).filter(|args| !args.is_empty())?;
+        let mut group = proc_macro2::Group::new(
+            proc_macro2::Delimiter::Parenthesis,
+            proc_macro2::TokenStream::new(),
+        );
+        group.set_span(mac.span());
+        self.expanded += 1;
+        Some(syn::Expr::Tuple(syn::ExprTuple {
+            attrs: attrs.to_vec(),
+            paren_token: syn::token::Paren {
+                span: group.delim_span(),
+            },
+            elems,
+        }))
+    }
+}
+
+impl VisitMut for MacroExpander<'_> {
+    fn visit_expr_mut(&mut self, expr: &mut syn::Expr) {
+        if let syn::Expr::Macro(call) = expr {
+            if let Some(tuple) = self.tuple(&call.mac, &call.attrs) {
+                *expr = tuple;
+            }
+        }
+        syn::visit_mut::visit_expr_mut(self, expr);
+    }
+
+    fn visit_stmt_mut(&mut self, stmt: &mut syn::Stmt) {
+        if let syn::Stmt::Macro(call) = stmt {
+            if let Some(tuple) = self.tuple(&call.mac, &call.attrs) {
+                *stmt = syn::Stmt::Expr(tuple, call.semi_token);
+            }
+        }
+        syn::visit_mut::visit_stmt_mut(self, stmt);
+    }
+}
+
+impl Default for OperatorRegistry {
+    fn default() -> Self {
+        Self::with_builtins()
+    }
+}
+
+/// Options generators find their sites with: test code is never mutated
+fn generator_options() -> MatchOptions {
+    MatchOptions {
+        skip_tests: true,
+        ..Default::default()
+    }
+}
+
+/// Sites found in each function of a file, sorted, with the function's
+/// qualified name and a version of it usable in ids (`Foo<T>::get` becomes
+/// `Foo_T_get`)
+fn sites_by_function<T: Ord>(
+    ast: &syn::File,
+    collect: impl Fn(&TargetName) -> Vec<T>,
+) -> Vec<(String, String, Vec<T>)> {
+    let mut functions: Vec<String> = list_functions(ast)
+        .into_iter()
+        .map(|f| match f.kind {
+            FunctionKind::Free => f.name,
+            FunctionKind::Impl(owner) | FunctionKind::Trait(owner) => {
+                format!("{}::{}", owner, f.name)
+            }
+        })
+        .collect();
+    functions.dedup();
+
+    functions
+        .into_iter()
+        .map(|function| {
+            let mut sites = collect(&TargetName::parse(&function));
+            // A free function's name also matches same-named methods, so the
+            // same site can be collected twice
+            sites.sort();
+            sites.dedup();
+            let id_name = function
+                .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .filter(|part| !part.is_empty())
+                .collect::<Vec<_>>()
+                .join("_");
+            (function, id_name, sites)
+        })
+        .collect()
+}
+
+/// The `cond` operator: see [`generate_condition_constant_mutations`]
+pub struct ConditionConstants;
+
+impl MutationOperator for ConditionConstants {
+    fn name(&self) -> &str {
+        "cond"
+    }
+
+    fn generate(&self, ast: &syn::File, file: &Path) -> Vec<MutationConfig> {
+        generate_condition_constant_mutations(file, ast)
+    }
+}
+
+/// Generate condition-coverage mutants for every function in `ast`
+///
+/// Each `if` and `while` condition, and each operand of `&&` and `||`, is
+/// replaced once with `true` and once with `false`. A mutant that survives
+/// means the tests never depend on that condition going the other way.
+/// `if let` and `while let` conditions are skipped, since a pattern can't be
+/// replaced by a constant.
+pub fn generate_condition_constant_mutations(file: &Path, ast: &syn::File) -> Vec<MutationConfig> {
+    let functions = sites_by_function(ast, |target| {
+        let mut collector = ConditionCollector {
+            conditions: Vec::new(),
+        };
+        visit_targets(ast, target, &generator_options(), &mut collector);
+        collector.conditions
+    });
+
+    let mut mutations = Vec::new();
+    for (function, id_name, conditions) in functions {
+        for (index, (line, original)) in conditions.into_iter().enumerate() {
+            for value in ["true", "false"] {
+                mutations.push(MutationConfig {
+                    file: file.to_path_buf(),
+                    function: function.clone(),
+                    original: original.clone(),
+                    replacement: value.to_string(),
+                    id: format!("cond_{}_{}_{}", id_name, index + 1, value),
+                    line: Some(line),
+                    ..Default::default()
+                });
+            }
+        }
+    }
+    mutations
+}
+
+/// Replaces binary operators with a related operator: the `arith`, `cmp` and
+/// `logic` operators
+pub struct BinaryOpSwap {
+    name: &'static str,
+    swap: fn(&syn::BinOp) -> Option<&'static str>,
+}
+
+impl BinaryOpSwap {
+    /// `+` and `-`, `*` and `/` swapped; `%` replaced by `*`
+    pub fn arithmetic() -> Self {
+        BinaryOpSwap {
+            name: "arith",
+            swap: |op| match op {
+                syn::BinOp::Add(_) => Some("-"),
+                syn::BinOp::Sub(_) => Some("+"),
+                syn::BinOp::Mul(_) => Some("/"),
+                syn::BinOp::Div(_) => Some("*"),
+                syn::BinOp::Rem(_) => Some("*"),
+                _ => None,
+            },
+        }
+    }
+
+    /// Boundaries shifted (`<` and `<=`, `>` and `>=`); `==` and `!=` swapped
+    pub fn comparison() -> Self {
+        BinaryOpSwap {
+            name: "cmp",
+            swap: |op| match op {
+                syn::BinOp::Lt(_) => Some("<="),
+                syn::BinOp::Le(_) => Some("<"),
+                syn::BinOp::Gt(_) => Some(">="),
+                syn::BinOp::Ge(_) => Some(">"),
+                syn::BinOp::Eq(_) => Some("!="),
+                syn::BinOp::Ne(_) => Some("=="),
+                _ => None,
+            },
+        }
+    }
+
+    /// `&&` and `||` swapped
+    pub fn logical() -> Self {
+        BinaryOpSwap {
+            name: "logic",
+            swap: |op| match op {
+                syn::BinOp::And(_) => Some("||"),
+                syn::BinOp::Or(_) => Some("&&"),
+                _ => None,
+            },
+        }
+    }
+}
+
+impl MutationOperator for BinaryOpSwap {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn generate(&self, ast: &syn::File, file: &Path) -> Vec<MutationConfig> {
+        let functions = sites_by_function(ast, |target| {
+            let mut collector = BinaryCollector {
+                swap: self.swap,
+                sites: Vec::new(),
+            };
+            visit_targets(ast, target, &generator_options(), &mut collector);
+            collector.sites
+        });
+
+        let mut mutations = Vec::new();
+        for (function, id_name, sites) in functions {
+            for (index, (line, original, replacement)) in sites.into_iter().enumerate() {
+                mutations.push(MutationConfig {
+                    file: file.to_path_buf(),
+                    function: function.clone(),
+                    original,
+                    replacement,
+                    id: format!("{}_{}_{}", self.name, id_name, index + 1),
+                    line: Some(line),
+                    ..Default::default()
+                });
+            }
+        }
+        mutations
+    }
+}
+
+/// The `overflow` operator: `+`, `-` and `*` replaced by their wrapping and
+/// checked method equivalents
+///
+/// `a + b` becomes `a.wrapping_add(b)`, which tests only kill if they expect
+/// the overflow panic of debug builds, and `a.checked_add(b).unwrap()`,
+/// which they only kill if they run with overflow checks off (release
+/// builds). The methods exist on the primitive integer types; operands of
+/// other types (custom `Add` impls, floats in variables) give mutants that
+/// fail to compile. Sites with a float or string literal operand are skipped,
+/// and an unsuffixed integer literal on the left of `+` or `*` is moved to
+/// the right, since methods can't be called on a literal of unknown type.
+pub struct OverflowArithmetic;
+
+impl MutationOperator for OverflowArithmetic {
+    fn name(&self) -> &str {
+        "overflow"
+    }
+
+    fn generate(&self, ast: &syn::File, file: &Path) -> Vec<MutationConfig> {
+        let functions = sites_by_function(ast, |target| {
+            let mut collector = OverflowCollector { sites: Vec::new() };
+            visit_targets(ast, target, &generator_options(), &mut collector);
+            collector.sites
+        });
+
+        let mut mutations = Vec::new();
+        for (function, id_name, sites) in functions {
+            for (index, (line, original, wrapping, checked)) in sites.into_iter().enumerate() {
+                for (variant, replacement) in [("wrapping", wrapping), ("checked", checked)] {
+                    mutations.push(MutationConfig {
+                        file: file.to_path_buf(),
+                        function: function.clone(),
+                        original: original.clone(),
+                        replacement,
+                        id: format!("overflow_{}_{}_{}", id_name, index + 1, variant),
+                        line: Some(line),
+                        ..Default::default()
+                    });
+                }
+            }
+        }
+        mutations
+    }
+}
+
+/// Collects the `+`, `-` and `*` expressions of a function as
+/// `(line, original, wrapping replacement, checked replacement)`
+struct OverflowCollector {
+    sites: Vec<(usize, String, String, String)>,
+}
+
+impl<'ast> Visit<'ast> for OverflowCollector {
+    fn visit_expr_binary(&mut self, expr: &'ast syn::ExprBinary) {
+        if let Some((receiver, method, arg)) = overflow_method(expr) {
+            self.sites.push((
+                expr.span().start().line,
+                source_code(expr),
+                format!("{}.wrapping_{}({})", receiver, method, arg),
+                format!("{}.checked_{}({}).unwrap()", receiver, method, arg),
+            ));
+        }
+        syn::visit::visit_expr_binary(self, expr);
+    }
+}
+
+/// The receiver, method name and argument of the overflow-aware method call
+/// equivalent to `expr`, if it has one
+fn overflow_method(expr: &syn::ExprBinary) -> Option<(String, &'static str, String)> {
+    let (method, commutative) = match expr.op {
+        syn::BinOp::Add(_) => ("add", true),
+        syn::BinOp::Sub(_) => ("sub", false),
+        syn::BinOp::Mul(_) => ("mul", true),
+        _ => return None,
+    };
+    fn literal(operand: &syn::Expr) -> Option<&syn::Lit> {
+        match operand {
+            syn::Expr::Lit(lit) => Some(&lit.lit),
+            _ => None,
+        }
+    }
+    let untyped = |operand: &syn::Expr| {
+        matches!(literal(operand), Some(syn::Lit::Int(int)) if int.suffix().is_empty())
+    };
+    let non_integer = |operand: &syn::Expr| {
+        matches!(literal(operand), Some(lit) if !matches!(lit, syn::Lit::Int(_)))
+    };
+    if non_integer(&expr.left) || non_integer(&expr.right) {
+        return None;
+    }
+
+    let (receiver, arg) = match (untyped(&expr.left), untyped(&expr.right)) {
+        (false, _) => (&*expr.left, &*expr.right),
+        (true, false) if commutative => (&*expr.right, &*expr.left),
+        _ => return None,
+    };
+    // Method calls bind tighter than any binary or prefix operator
+    let receiver_code = match receiver {
+        syn::Expr::Path(_)
+        | syn::Expr::Lit(_)
+        | syn::Expr::MethodCall(_)
+        | syn::Expr::Field(_)
+        | syn::Expr::Call(_)
+        | syn::Expr::Index(_)
+        | syn::Expr::Paren(_)
+        | syn::Expr::Macro(_)
+        | syn::Expr::Tuple(_)
+        | syn::Expr::Try(_) => source_code(receiver),
+        _ => format!("({})", source_code(receiver)),
+    };
+    Some((receiver_code, method, source_code(arg)))
+}
+
+/// The `boundary` operator: see [`generate_boundary_shift_mutations`]
+pub struct BoundaryShift;
+
+impl MutationOperator for BoundaryShift {
+    fn name(&self) -> &str {
+        "boundary"
+    }
+
+    fn generate(&self, ast: &syn::File, file: &Path) -> Vec<MutationConfig> {
+        generate_boundary_shift_mutations(file, ast)
+    }
+}
+
+/// Generate off-by-one mutants for every function in `ast`
+///
+/// Each comparison against an integer literal gets the literal shifted by one
+/// each way: `age >= 18` becomes `age >= 17` and `age >= 19`. Where `cmp`
+/// moves the boundary by changing the operator, this moves the constant, so a
+/// surviving mutant means no test sits right at the boundary. A literal of 0
+/// is only shifted up, since the operand may be unsigned.
+pub fn generate_boundary_shift_mutations(file: &Path, ast: &syn::File) -> Vec<MutationConfig> {
+    let functions = sites_by_function(ast, |target| {
+        let mut collector = BoundaryCollector { sites: Vec::new() };
+        visit_targets(ast, target, &generator_options(), &mut collector);
+        collector.sites
+    });
+
+    let mut mutations = Vec::new();
+    for (function, id_name, sites) in functions {
+        for (index, (line, original, shifted)) in sites.into_iter().enumerate() {
+            for (variant, replacement) in shifted {
+                mutations.push(MutationConfig {
+                    file: file.to_path_buf(),
+                    function: function.clone(),
+                    original: original.clone(),
+                    replacement,
+                    id: format!("boundary_{}_{}_{}", id_name, index + 1, variant),
+                    line: Some(line),
+                    ..Default::default()
+                });
+            }
+        }
+    }
+    mutations
+}
+
+/// Collects the comparisons of a function against an integer literal as
+/// `(line, original, [(variant, replacement)])`, the variants being `dec` and
+/// `inc`
+type BoundarySite = (usize, String, Vec<(&'static str, String)>);
+
+struct BoundaryCollector {
+    sites: Vec<BoundarySite>,
+}
+
+impl<'ast> Visit<'ast> for BoundaryCollector {
+    fn visit_expr_binary(&mut self, expr: &'ast syn::ExprBinary) {
+        if let Some(shifted) = boundary_shifts(expr) {
+            self.sites
+                .push((expr.span().start().line, source_code(expr), shifted));
+        }
+        syn::visit::visit_expr_binary(self, expr);
+    }
+}
+
+/// The `expr` comparisons with its integer literal operand shifted down and up
+/// by one, or `None` if `expr` isn't a comparison against an integer literal
+///
+/// The literal on the right is shifted if both operands are literals. Its
+/// suffix is kept and the value is written in decimal.
+fn boundary_shifts(expr: &syn::ExprBinary) -> Option<Vec<(&'static str, String)>> {
+    if !matches!(
+        expr.op,
+        syn::BinOp::Lt(_)
+            | syn::BinOp::Le(_)
+            | syn::BinOp::Gt(_)
+            | syn::BinOp::Ge(_)
+            | syn::BinOp::Eq(_)
+            | syn::BinOp::Ne(_)
+    ) {
+        return None;
+    }
+    fn int_literal(operand: &syn::Expr) -> Option<&syn::LitInt> {
+        match operand {
+            syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Int(int),
+                ..
+            }) => Some(int),
+            _ => None,
+        }
+    }
+    let (literal, on_right) = match (int_literal(&expr.left), int_literal(&expr.right)) {
+        (_, Some(int)) => (int, true),
+        (Some(int), None) => (int, false),
+        (None, None) => return None,
+    };
+    let value: u128 = literal.base10_parse().ok()?;
+
+    let op = expr.op.to_token_stream().to_string();
+    let comparison = |shifted: u128| {
+        let shifted = format!("{}{}", shifted, literal.suffix());
+        if on_right {
+            format!("{} {} {}", source_code(&expr.left), op, shifted)
+        } else {
+            format!("{} {} {}", shifted, op, source_code(&expr.right))
+        }
+    };
+    let shifts: Vec<(&'static str, String)> = [
+        ("dec", value.checked_sub(1)),
+        ("inc", value.checked_add(1)),
+    ]
+    .into_iter()
+    .filter_map(|(variant, shifted)| Some((variant, comparison(shifted?))))
+    .collect();
+    Some(shifts)
+}
+
+/// The `ref` operator: see [`generate_reference_mutations`]
+pub struct ReferenceMutability;
+
+impl MutationOperator for ReferenceMutability {
+    fn name(&self) -> &str {
+        "ref"
+    }
+
+    fn generate(&self, ast: &syn::File, file: &Path) -> Vec<MutationConfig> {
+        generate_reference_mutations(file, ast)
+    }
+}
+
+/// Generate reference mutants for every function in `ast`
+///
+/// Each `&mut x` becomes `&x`, checking whether the tests notice the changes
+/// made through the reference. Most of these mutants don't compile, since the
+/// borrowed value is then modified through a shared reference; such a compile
+/// error shows the mutability is needed, while one that compiles and survives
+/// points at a `&mut` nothing writes through, or writes no test checks.
+pub fn generate_reference_mutations(file: &Path, ast: &syn::File) -> Vec<MutationConfig> {
+    let functions = sites_by_function(ast, |target| {
+        let mut collector = ReferenceCollector { sites: Vec::new() };
+        visit_targets(ast, target, &generator_options(), &mut collector);
+        collector.sites
+    });
+
+    let mut mutations = Vec::new();
+    for (function, id_name, sites) in functions {
+        for (index, (line, original, replacement)) in sites.into_iter().enumerate() {
+            mutations.push(MutationConfig {
+                file: file.to_path_buf(),
+                function: function.clone(),
+                original,
+                replacement,
+                id: format!("ref_{}_{}", id_name, index + 1),
+                line: Some(line),
+                ..Default::default()
+            });
+        }
+    }
+    mutations
+}
+
+/// Collects the `&mut` expressions of a function as
+/// `(line, original, shared replacement)`
+struct ReferenceCollector {
+    sites: Vec<(usize, String, String)>,
+}
+
+impl<'ast> Visit<'ast> for ReferenceCollector {
+    fn visit_expr_reference(&mut self, expr: &'ast syn::ExprReference) {
+        if expr.mutability.is_some() {
+            self.sites.push((
+                expr.span().start().line,
+                source_code(expr),
+                format!("&{}", source_code(&expr.expr)),
+            ));
+        }
+        syn::visit::visit_expr_reference(self, expr);
+    }
+}
+
+/// Collects the binary expressions of a function whose operator `swap`
+/// replaces, as `(line, original, replacement)`
+struct BinaryCollector {
+    swap: fn(&syn::BinOp) -> Option<&'static str>,
+    sites: Vec<(usize, String, String)>,
+}
+
+impl<'ast> Visit<'ast> for BinaryCollector {
+    fn visit_expr_binary(&mut self, expr: &'ast syn::ExprBinary) {
+        if let Some(op) = (self.swap)(&expr.op) {
+            let replacement =
+                format!("{} {} {}", source_code(&expr.left), op, source_code(&expr.right));
+            self.sites
+                .push((expr.span().start().line, source_code(expr), replacement));
+        }
+        syn::visit::visit_expr_binary(self, expr);
+    }
+}
+
+/// Collects the conditions of a function as `(line, code)`
+struct ConditionCollector {
+    conditions: Vec<(usize, String)>,
+}
+
+impl ConditionCollector {
+    fn push(&mut self, cond: &syn::Expr) {
+        let is_constant = matches!(cond, syn::Expr::Lit(lit) if matches!(lit.lit, syn::Lit::Bool(_)));
+        if is_constant || contains_let(cond) {
+            return;
+        }
+        self.conditions.push((cond.span().start().line, source_code(cond)));
+    }
+}
+
+impl<'ast> Visit<'ast> for ConditionCollector {
+    fn visit_expr_if(&mut self, expr: &'ast syn::ExprIf) {
+        self.push(&expr.cond);
+        syn::visit::visit_expr_if(self, expr);
+    }
+
+    fn visit_expr_while(&mut self, expr: &'ast syn::ExprWhile) {
+        self.push(&expr.cond);
+        syn::visit::visit_expr_while(self, expr);
+    }
+
+    fn visit_expr_binary(&mut self, expr: &'ast syn::ExprBinary) {
+        if matches!(expr.op, syn::BinOp::And(_) | syn::BinOp::Or(_)) {
+            self.push(&expr.left);
+            self.push(&expr.right);
+        }
+        syn::visit::visit_expr_binary(self, expr);
+    }
+}
+
+/// The code of a node as written in the source, or as tokens if unavailable
+fn source_code<T: Spanned + ToTokens>(node: &T) -> String {
+    node.span()
+        .source_text()
+        .unwrap_or_else(|| node.to_token_stream().to_string())
+}
+
+/// Whether a condition contains a `let` pattern (`if let`, let chains)
+fn contains_let(cond: &syn::Expr) -> bool {
+    match cond {
+        syn::Expr::Let(_) => true,
+        syn::Expr::Binary(b) => contains_let(&b.left) || contains_let(&b.right),
+        syn::Expr::Paren(p) => contains_let(&p.expr),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_return_default() {
+        let op = MutationOp::ReturnDefault;
+        assert_eq!(
+            op.replacement_for("return a + b").unwrap(),
+            "return Default::default()"
+        );
+        assert_eq!(op.replacement_for("a + b").unwrap(), "Default::default()");
+        assert!(op.replacement_for("return").is_err());
+        assert!(matches!(
+            op.replacement_for("a +"),
+            Err(MutationError::InvalidOriginal { .. })
+        ));
+    }
+
+    #[test]
+    fn test_generate_condition_constant_mutations() {
+        let source = r#"
+fn check(a: bool, b: bool, n: u32) -> u32 {
+    if a && b {
+        return 1;
+    }
+    if let Some(x) = Some(n) {
+        return x;
+    }
+    let mut i = n;
+    while i > 0 {
+        i -= 1;
+    }
+    i
+}
+
+impl Gate {
+    fn open(&self) -> bool {
+        if self.locked { false } else { true }
+    }
+}
+"#;
+        let ast = syn::parse_file(source).unwrap();
+        let mutations = generate_condition_constant_mutations(Path::new("src/lib.rs"), &ast);
+
+        let summary: Vec<(&str, &str, Option<usize>, &str)> = mutations
+            .iter()
+            .map(|m| {
+                (
+                    m.function.as_str(),
+                    m.original.as_str(),
+                    m.line,
+                    m.replacement.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("check", "a", Some(3), "true"),
+                ("check", "a", Some(3), "false"),
+                ("check", "a && b", Some(3), "true"),
+                ("check", "a && b", Some(3), "false"),
+                ("check", "b", Some(3), "true"),
+                ("check", "b", Some(3), "false"),
+                ("check", "i > 0", Some(10), "true"),
+                ("check", "i > 0", Some(10), "false"),
+                ("Gate::open", "self.locked", Some(18), "true"),
+                ("Gate::open", "self.locked", Some(18), "false"),
+            ]
+        );
+        assert_eq!(mutations[0].id, "cond_check_1_true");
+        assert_eq!(mutations[9].id, "cond_Gate_open_1_false");
+    }
+
+    #[test]
+    fn test_binary_op_swaps() {
+        let source = r#"
+fn score(a: i32, b: i32) -> bool {
+    a * 2 + b >= 10 && a != b
+}
+"#;
+        let ast = syn::parse_file(source).unwrap();
+        let file = Path::new("src/lib.rs");
+        let pairs = |operator: BinaryOpSwap| {
+            operator
+                .generate(&ast, file)
+                .into_iter()
+                .map(|m| (m.original, m.replacement))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            pairs(BinaryOpSwap::arithmetic()),
+            [
+                ("a * 2".to_string(), "a / 2".to_string()),
+                ("a * 2 + b".to_string(), "a * 2 - b".to_string()),
+            ]
+        );
+        assert_eq!(
+            pairs(BinaryOpSwap::comparison()),
+            [
+                ("a != b".to_string(), "a == b".to_string()),
+                ("a * 2 + b >= 10".to_string(), "a * 2 + b > 10".to_string()),
+            ]
+        );
+        let logical = BinaryOpSwap::logical().generate(&ast, file);
+        assert_eq!(logical.len(), 1);
+        assert_eq!(logical[0].replacement, "a * 2 + b >= 10 || a != b");
+        assert_eq!(logical[0].id, "logic_score_1");
+        assert_eq!(logical[0].line, Some(3));
+    }
+
+    #[test]
+    fn test_overflow_arithmetic() {
+        let source = r#"
+fn total(a: u8, b: u8, n: usize) -> u8 {
+    let scaled = 2 * a - b;
+    let rest = 10 - n;
+    let half = w * 0.5;
+    scaled + 1u8
+}
+"#;
+        let ast = syn::parse_file(source).unwrap();
+        let mutations = OverflowArithmetic.generate(&ast, Path::new("src/lib.rs"));
+        let triples: Vec<(&str, &str, &str)> = mutations
+            .iter()
+            .map(|m| (m.id.as_str(), m.original.as_str(), m.replacement.as_str()))
+            .collect();
+        assert_eq!(
+            triples,
+            [
+                ("overflow_total_1_wrapping", "2 * a", "a.wrapping_mul(2)"),
+                ("overflow_total_1_checked", "2 * a", "a.checked_mul(2).unwrap()"),
+                ("overflow_total_2_wrapping", "2 * a - b", "(2 * a).wrapping_sub(b)"),
+                ("overflow_total_2_checked", "2 * a - b", "(2 * a).checked_sub(b).unwrap()"),
+                ("overflow_total_3_wrapping", "scaled + 1u8", "scaled.wrapping_add(1u8)"),
+                ("overflow_total_3_checked", "scaled + 1u8", "scaled.checked_add(1u8).unwrap()"),
+            ]
+        );
+        assert_eq!(mutations[0].line, Some(3));
+        assert_eq!(mutations[4].line, Some(6));
+
+        // Every replacement parses as an expression, so it round-trips
+        // through the config and the matcher
+        for mutation in &mutations {
+            assert!(syn::parse_str::<syn::Expr>(&mutation.replacement).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_boundary_shifts() {
+        let source = r#"
+fn eligible(age: u32, score: i64) -> bool {
+    age >= 18
+        && 0 < score
+        && score != 100i64
+        && age <= score as u32
+}
+"#;
+        let ast = syn::parse_file(source).unwrap();
+        let mutations = BoundaryShift.generate(&ast, Path::new("src/lib.rs"));
+        let triples: Vec<(&str, &str, &str)> = mutations
+            .iter()
+            .map(|m| (m.id.as_str(), m.original.as_str(), m.replacement.as_str()))
+            .collect();
+        assert_eq!(
+            triples,
+            [
+                ("boundary_eligible_1_dec", "age >= 18", "age >= 17"),
+                ("boundary_eligible_1_inc", "age >= 18", "age >= 19"),
+                // 0 is never shifted below zero
+                ("boundary_eligible_2_inc", "0 < score", "1 < score"),
+                ("boundary_eligible_3_dec", "score != 100i64", "score != 99i64"),
+                ("boundary_eligible_3_inc", "score != 100i64", "score != 101i64"),
+            ]
+        );
+        assert_eq!(mutations[0].line, Some(3));
+        assert_eq!(mutations[2].line, Some(4));
+
+        for mutation in &mutations {
+            assert!(syn::parse_str::<syn::Expr>(&mutation.replacement).is_ok());
+        }
+    }
+
+    #[test]
+    fn test_reference_mutations() {
+        let source = r#"
+fn fill(buf: &mut Vec<u8>, extra: &[u8]) {
+    extend(&mut *buf, &extra);
+    let view = &buf[..];
+    let _ = (view, &mut buf.len());
+}
+"#;
+        let ast = syn::parse_file(source).unwrap();
+        let mutations = ReferenceMutability.generate(&ast, Path::new("src/lib.rs"));
+        let triples: Vec<(&str, &str, &str)> = mutations
+            .iter()
+            .map(|m| (m.id.as_str(), m.original.as_str(), m.replacement.as_str()))
+            .collect();
+        // Shared references are left alone
+        assert_eq!(
+            triples,
+            [
+                ("ref_fill_1", "&mut *buf", "&*buf"),
+                ("ref_fill_2", "&mut buf.len()", "&buf.len()"),
+            ]
+        );
+        assert_eq!(mutations[1].line, Some(5));
+    }
+
+    #[test]
+    fn test_operator_registry() {
+        /// Replaces every function's body with `todo!()`
+        struct Todo;
+
+        impl MutationOperator for Todo {
+            fn name(&self) -> &str {
+                "todo"
+            }
+
+            fn generate(&self, ast: &syn::File, file: &Path) -> Vec<MutationConfig> {
+                list_functions(ast)
+                    .into_iter()
+                    .map(|f| MutationConfig {
+                        file: file.to_path_buf(),
+                        id: format!("todo_{}", f.name),
+                        function: f.name,
+                        original: "{ a + b }".to_string(),
+                        replacement: "{ todo!() }".to_string(),
+                        ..Default::default()
+                    })
+                    .collect()
+            }
+        }
+
+        let mut registry = OperatorRegistry::with_builtins();
+        assert_eq!(registry.names(), ["cond", "arith", "cmp", "logic", "overflow", "boundary", "ref"]);
+        registry.register(Todo);
+        assert!(registry.get("todo").is_some());
+
+        let ast = syn::parse_file("fn add(a: i32, b: i32) -> i32 { a + b }").unwrap();
+        let ops = vec!["todo".to_string(), "arith".to_string()];
+        let mutations = registry.generate(&ops, &ast, Path::new("src/lib.rs")).unwrap();
+        let ids: Vec<&str> = mutations.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(ids, ["todo_add", "arith_add_1"]);
+
+        let unknown = registry.generate(&["nope".to_string()], &ast, Path::new("src/lib.rs"));
+        assert_eq!(
+            unknown.unwrap_err().to_string(),
+            "Configuration error: Unknown mutation operator 'nope' (available: cond, arith, cmp, logic, overflow, boundary, ref, todo)"
+        );
+    }
+
+    #[test]
+    fn test_macro_arguments_are_not_generated() {
+        let source = r#"
+fn scale(a: u32, b: u32) -> u32 {
+    debug_assert!(a > 0 && b < 10);
+    log::debug!("scaling {}", a * b);
+    eprintln!("{}", a + 1);
+    a * b
+}
+"#;
+        let ast = syn::parse_file(source).unwrap();
+        let registry = OperatorRegistry::with_builtins();
+        let ops: Vec<String> = registry.names().iter().map(|name| name.to_string()).collect();
+        let mutations = registry.generate(&ops, &ast, Path::new("src/lib.rs")).unwrap();
+
+        // Debug, logging and printing macros are skipped by default
+        assert!(!mutations.is_empty());
+        assert!(mutations.iter().all(|m| m.original == "a * b" && m.line == Some(6)));
+        assert!(mutations.iter().all(|m| !m.in_macros));
+    }
+
+    #[test]
+    fn test_macro_arguments_are_generated() {
+        let source = r#"
+fn scale(a: u32, b: u32) -> Vec<u32> {
+    assert!(a > 0);
+    debug_assert!(b < 10);
+    vec![a * b]
+}
+"#;
+        let ast = syn::parse_file(source).unwrap();
+        let mut registry = OperatorRegistry::with_builtins();
+        let ops = vec!["cmp".to_string(), "arith".to_string()];
+        let mutations = registry.generate(&ops, &ast, Path::new("src/lib.rs")).unwrap();
+
+        let lines: Vec<_> = mutations.iter().map(|m| (m.original.as_str(), m.line)).collect();
+        assert!(lines.contains(&("a > 0", Some(3))));
+        assert!(lines.contains(&("a * b", Some(5))));
+        assert!(!lines.iter().any(|(_, line)| *line == Some(4)));
+        assert!(mutations.iter().all(|m| m.in_macros));
+
+        registry.skip_macros(Vec::new());
+        let mutations = registry.generate(&ops, &ast, Path::new("src/lib.rs")).unwrap();
+        assert!(mutations.iter().any(|m| m.original == "b < 10" && m.line == Some(4)));
+    }
+}
@@ -2,16 +2,146 @@
 //!
 //! This module formats and displays mutation testing results.
 
-use colored::Colorize;
-use std::time::Duration;
+use colored::{ColoredString, Colorize};
+use serde::{Deserialize, Serialize};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+use crate::error::MutationError;
 use crate::runner::{MutationResult, MutationStatus};
+use crate::triage::{sort_survivors, MutationCategory, SurvivorOrder};
+
+/// Header of the CSV written by [`MutationReport::append_history`]
+pub const HISTORY_HEADER: &str = "timestamp,git_sha,total,killed,survived,score";
 
 /// Summary report of mutation testing
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct MutationReport {
     pub results: Vec<MutationResult>,
+    /// Sum of the per-mutation durations
+    #[serde(with = "duration_secs")]
     pub total_duration: Duration,
+    /// Wall-clock time of the whole run, if measured
+    #[serde(default, with = "opt_duration_secs", skip_serializing_if = "Option::is_none")]
+    pub wall_clock: Option<Duration>,
+    /// How the text report lists survivors; `None` keeps the report order
+    #[serde(skip)]
+    pub survivor_order: Option<SurvivorOrder>,
+    /// List one aligned line per mutation in the text report, without the
+    /// detailed sections
+    #[serde(skip)]
+    pub compact: bool,
+}
+
+/// Throughput of a run, for tracking the effect of optimizations over time
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Throughput {
+    /// Mutations run per second of wall-clock time (or of summed durations
+    /// when the wall clock wasn't measured)
+    pub mutations_per_second: f64,
+    /// Median per-mutation duration
+    #[serde(with = "duration_secs")]
+    pub median_duration: Duration,
+    /// 95th percentile per-mutation duration
+    #[serde(with = "duration_secs")]
+    pub p95_duration: Duration,
+}
+
+/// How well the tests cover one function
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionScore {
+    pub file: PathBuf,
+    pub function: String,
+    /// Mutants of the function, whatever their status (its mutation density)
+    pub mutants: usize,
+    pub killed: usize,
+    pub survived: usize,
+}
+
+impl FunctionScore {
+    /// Percentage of killed mutants among killed and survived, like
+    /// [`MutationReport::score`]; `None` if none of them ran to completion
+    pub fn score(&self) -> Option<f64> {
+        let testable = self.killed + self.survived;
+        (testable > 0).then(|| self.killed as f64 / testable as f64 * 100.0)
+    }
+}
+
+/// Mutations that couldn't be applied for the same kind of reason
+#[derive(Debug)]
+pub struct ConfigErrorGroup<'a> {
+    /// The error kind, e.g. `no_match` (see [`MutationError::kind`])
+    pub kind: &'static str,
+    /// The mutations and their errors, in report order
+    pub results: Vec<(&'a MutationResult, &'a MutationError)>,
+}
+
+/// Aggregate counts included at the top of the JSON report
+#[derive(Debug, Serialize)]
+struct ReportSummary {
+    total: usize,
+    killed: usize,
+    /// Included in `killed`
+    crashed: usize,
+    survived: usize,
+    timeouts: usize,
+    compile_errors: usize,
+    config_errors: usize,
+    not_run: usize,
+    not_covered: usize,
+    score: f64,
+    #[serde(flatten)]
+    throughput: Option<Throughput>,
+}
+
+/// The report written by [`MutationReport::to_summary_json`]
+#[derive(Serialize)]
+struct SummaryReport<'a> {
+    #[serde(flatten)]
+    summary: ReportSummary,
+    #[serde(with = "duration_secs")]
+    total_duration: Duration,
+    #[serde(with = "opt_duration_secs", skip_serializing_if = "Option::is_none")]
+    wall_clock: Option<Duration>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    git_sha: Option<&'a str>,
+}
+
+/// Serialize an optional `Duration` as fractional seconds
+mod opt_duration_secs {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(d: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error> {
+        match d {
+            Some(d) => serializer.serialize_some(&d.as_secs_f64()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        Option::<f64>::deserialize(deserializer)?
+            .map(|secs| Duration::try_from_secs_f64(secs).map_err(serde::de::Error::custom))
+            .transpose()
+    }
+}
+
+/// Serialize a `Duration` as fractional seconds
+pub(crate) mod duration_secs {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(d: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(d.as_secs_f64())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        let secs = f64::deserialize(deserializer)?;
+        Duration::try_from_secs_f64(secs).map_err(serde::de::Error::custom)
+    }
 }
 
 impl MutationReport {
@@ -21,14 +151,70 @@ impl MutationReport {
         Self {
             results,
             total_duration,
+            wall_clock: None,
+            survivor_order: None,
+            compact: false,
         }
     }
 
+    /// Record the wall-clock time of the run, used for throughput
+    pub fn with_wall_clock(mut self, wall_clock: Duration) -> Self {
+        self.wall_clock = Some(wall_clock);
+        self
+    }
+
+    /// List survivors in `order` in the text report
+    pub fn with_survivor_order(mut self, order: SurvivorOrder) -> Self {
+        self.survivor_order = Some(order);
+        self
+    }
+
+    /// List one aligned line per mutation in the text report, followed by
+    /// the summary
+    pub fn with_compact(mut self) -> Self {
+        self.compact = true;
+        self
+    }
+
+    /// Throughput of the mutations that were run, or `None` if none were
+    pub fn throughput(&self) -> Option<Throughput> {
+        let mut durations: Vec<Duration> = self
+            .results
+            .iter()
+            .filter(|r| !matches!(r.status, MutationStatus::NotRun | MutationStatus::NotCovered))
+            .map(|r| r.duration)
+            .collect();
+        if durations.is_empty() {
+            return None;
+        }
+        durations.sort();
+
+        let elapsed = self.wall_clock.unwrap_or(self.total_duration).as_secs_f64();
+        let mutations_per_second = if elapsed > 0.0 {
+            durations.len() as f64 / elapsed
+        } else {
+            0.0
+        };
+        Some(Throughput {
+            mutations_per_second,
+            median_duration: percentile(&durations, 50),
+            p95_duration: percentile(&durations, 95),
+        })
+    }
+
     /// Count of mutations that were killed (detected by tests)
     pub fn killed(&self) -> usize {
         self.results
             .iter()
-            .filter(|r| r.status == MutationStatus::Killed)
+            .filter(|r| r.is_killed())
+            .count()
+    }
+
+    /// Count of killed mutations whose tests crashed rather than failed
+    pub fn crashed(&self) -> usize {
+        self.results
+            .iter()
+            .filter(|r| r.status == MutationStatus::Crashed)
             .count()
     }
 
@@ -36,7 +222,7 @@ impl MutationReport {
     pub fn survived(&self) -> usize {
         self.results
             .iter()
-            .filter(|r| r.status == MutationStatus::Survived)
+            .filter(|r| r.is_survived())
             .count()
     }
 
@@ -64,6 +250,22 @@ impl MutationReport {
             .count()
     }
 
+    /// Count of mutations not run because the runtime budget was exhausted
+    pub fn not_run(&self) -> usize {
+        self.results
+            .iter()
+            .filter(|r| r.status == MutationStatus::NotRun)
+            .count()
+    }
+
+    /// Count of mutations not run because no test covers them
+    pub fn not_covered(&self) -> usize {
+        self.results
+            .iter()
+            .filter(|r| r.status == MutationStatus::NotCovered)
+            .count()
+    }
+
     /// Total number of mutations
     pub fn total(&self) -> usize {
         self.results.len()
@@ -79,78 +281,460 @@ impl MutationReport {
         (self.killed() as f64 / testable as f64) * 100.0
     }
 
-    /// Get surviving mutations (test gaps)
+    /// Scores of each mutated function, in the order they were first mutated
+    pub fn score_by_function(&self) -> Vec<FunctionScore> {
+        let mut scores: Vec<FunctionScore> = Vec::new();
+        for result in &self.results {
+            let index = match scores
+                .iter()
+                .position(|s| s.file == result.file && s.function == result.function)
+            {
+                Some(index) => index,
+                None => {
+                    scores.push(FunctionScore {
+                        file: result.file.clone(),
+                        function: result.function.clone(),
+                        mutants: 0,
+                        killed: 0,
+                        survived: 0,
+                    });
+                    scores.len() - 1
+                }
+            };
+            let score = &mut scores[index];
+            score.mutants += 1;
+            if result.is_killed() {
+                score.killed += 1;
+            } else if result.is_survived() {
+                score.survived += 1;
+            }
+        }
+        scores
+    }
+
+    /// Get surviving mutations
     pub fn surviving_mutations(&self) -> Vec<&MutationResult> {
+        self.results.iter().filter(|r| r.is_survived()).collect()
+    }
+
+    /// Get surviving mutations in the report's `survivor_order`
+    pub fn ordered_survivors(&self) -> Vec<&MutationResult> {
+        let mut survivors = self.surviving_mutations();
+        if let Some(order) = self.survivor_order {
+            sort_survivors(&mut survivors, order);
+        }
+        survivors
+    }
+
+    /// Get the mutations that point at gaps in the tests (see
+    /// [`MutationResult::is_gap`])
+    pub fn gaps(&self) -> Vec<&MutationResult> {
+        self.results.iter().filter(|r| r.is_gap()).collect()
+    }
+
+    /// Get mutations that were only caught by timing out
+    ///
+    /// No assertion noticed these mutants; the tests just hung. That is weaker
+    /// coverage than a kill, and usually worth an explicit test.
+    pub fn timeouts_list(&self) -> Vec<&MutationResult> {
         self.results
             .iter()
-            .filter(|r| r.status == MutationStatus::Survived)
+            .filter(|r| r.status == MutationStatus::Timeout)
             .collect()
     }
 
+    /// Config errors grouped by cause, largest group first
+    pub fn config_error_breakdown(&self) -> Vec<ConfigErrorGroup<'_>> {
+        let mut groups: Vec<ConfigErrorGroup> = Vec::new();
+        for result in &self.results {
+            let MutationStatus::ConfigError(error) = &result.status else {
+                continue;
+            };
+            match groups.iter_mut().find(|g| g.kind == error.kind()) {
+                Some(group) => group.results.push((result, error)),
+                None => groups.push(ConfigErrorGroup {
+                    kind: error.kind(),
+                    results: vec![(result, error)],
+                }),
+            }
+        }
+        // Stable, so equally large groups keep the order they first appeared in
+        groups.sort_by_key(|g| std::cmp::Reverse(g.results.len()));
+        groups
+    }
+
+    /// Aggregate counts, score and throughput of the report
+    fn summary(&self) -> ReportSummary {
+        ReportSummary {
+            total: self.total(),
+            killed: self.killed(),
+            crashed: self.crashed(),
+            survived: self.survived(),
+            timeouts: self.timeouts(),
+            compile_errors: self.compile_errors(),
+            config_errors: self.config_errors(),
+            not_run: self.not_run(),
+            not_covered: self.not_covered(),
+            score: self.score(),
+            throughput: self.throughput(),
+        }
+    }
+
+    /// Serialize the report, including a summary of counts, as JSON
+    pub fn to_json(&self) -> String {
+        let mut value = serde_json::to_value(self).expect("report serializes to JSON");
+        value["summary"] = serde_json::to_value(self.summary()).expect("summary serializes to JSON");
+        serde_json::to_string_pretty(&value).expect("report serializes to JSON")
+    }
+
+    /// Serialize only the summary of the report as JSON, without the results
+    ///
+    /// Besides the counts of the full report's `summary`, it has the run's
+    /// durations and the commit that was tested, if known, for tracking the
+    /// score over time.
+    pub fn to_summary_json(&self, git_sha: Option<&str>) -> String {
+        let summary = SummaryReport {
+            summary: self.summary(),
+            total_duration: self.total_duration,
+            wall_clock: self.wall_clock,
+            git_sha,
+        };
+        serde_json::to_string_pretty(&summary).expect("summary serializes to JSON")
+    }
+
+    /// Parse a report previously written with [`MutationReport::to_json`]
+    pub fn from_json(json: &str) -> Result<Self, MutationError> {
+        serde_json::from_str(json).map_err(|e| MutationError::ConfigError {
+            message: format!("Failed to parse JSON report: {}", e),
+        })
+    }
+
+    /// Write the JSON report to a file
+    pub fn write_json(&self, path: &Path) -> Result<(), MutationError> {
+        std::fs::write(path, self.to_json()).map_err(|e| MutationError::WriteError {
+            file: path.to_path_buf(),
+            error: e.to_string(),
+        })
+    }
+
+    /// Write the summary-only JSON report to a file
+    pub fn write_summary_json(&self, path: &Path, git_sha: Option<&str>) -> Result<(), MutationError> {
+        std::fs::write(path, self.to_summary_json(git_sha)).map_err(|e| {
+            MutationError::WriteError {
+                file: path.to_path_buf(),
+                error: e.to_string(),
+            }
+        })
+    }
+
+    /// Write the text report to a file, with ANSI color codes stripped
+    pub fn write_text_file(&self, path: &Path, survivors_only: bool) -> Result<(), MutationError> {
+        let mut text = Vec::new();
+        let rendered = if survivors_only {
+            self.write_survivors_only(&mut text)
+        } else {
+            self.write_text(&mut text)
+        };
+        rendered
+            .and_then(|()| std::fs::write(path, strip_ansi(&String::from_utf8_lossy(&text))))
+            .map_err(|e| MutationError::WriteError {
+                file: path.to_path_buf(),
+                error: e.to_string(),
+            })
+    }
+
+    /// Write the HTML report, with its per-function heatmap, to a file
+    pub fn write_html(&self, path: &Path) -> Result<(), MutationError> {
+        std::fs::write(path, crate::html::render(self)).map_err(|e| MutationError::WriteError {
+            file: path.to_path_buf(),
+            error: e.to_string(),
+        })
+    }
+
+    /// One line of the score history: `timestamp,git_sha,total,killed,survived,score`
+    ///
+    /// `timestamp` is in seconds since the Unix epoch; `git_sha` is empty
+    /// outside a git repository.
+    pub fn history_row(&self, timestamp: u64, git_sha: Option<&str>) -> String {
+        format!(
+            "{},{},{},{},{},{:.2}",
+            timestamp,
+            git_sha.unwrap_or(""),
+            self.total(),
+            self.killed(),
+            self.survived(),
+            self.score()
+        )
+    }
+
+    /// Append this run to a CSV score history, writing the header first if the
+    /// file is new
+    pub fn append_history(&self, path: &Path, git_sha: Option<&str>) -> Result<(), MutationError> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let new = std::fs::metadata(path).map_or(true, |m| m.len() == 0);
+
+        let mut line = String::new();
+        if new {
+            line.push_str(HISTORY_HEADER);
+            line.push('\n');
+        }
+        line.push_str(&self.history_row(timestamp, git_sha));
+        line.push('\n');
+
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| file.write_all(line.as_bytes()))
+            .map_err(|e| MutationError::WriteError {
+                file: path.to_path_buf(),
+                error: e.to_string(),
+            })
+    }
+
+    /// Load a JSON report from a file
+    pub fn load_json(path: &Path) -> Result<Self, MutationError> {
+        let json = std::fs::read_to_string(path).map_err(|e| MutationError::FileReadError {
+            file: path.to_path_buf(),
+            error: e.to_string(),
+        })?;
+        Self::from_json(&json)
+    }
+
+    /// How the score is computed, with this run's numbers
+    ///
+    /// Only killed and surviving mutants count towards the score, e.g.
+    /// `score = killed (74) / (killed 74 + survived 16) = 82.2%; excluded: 3
+    /// timeouts, 2 compile errors, 1 config error, 4 not run`.
+    pub fn explain_score(&self) -> String {
+        let formula = if self.killed() + self.survived() == 0 {
+            format!("score = {:.1}% (no mutants were killed or survived)", self.score())
+        } else {
+            format!(
+                "score = killed ({}) / (killed {} + survived {}) = {:.1}%",
+                self.killed(),
+                self.killed(),
+                self.survived(),
+                self.score()
+            )
+        };
+
+        let excluded: Vec<String> = [
+            (self.timeouts(), "timeout", "timeouts"),
+            (self.compile_errors(), "compile error", "compile errors"),
+            (self.config_errors(), "config error", "config errors"),
+            (self.not_run(), "not run", "not run"),
+            (self.not_covered(), "not covered", "not covered"),
+        ]
+        .into_iter()
+        .filter(|(count, _, _)| *count > 0)
+        .map(|(count, one, many)| format!("{} {}", count, if count == 1 { one } else { many }))
+        .collect();
+        if excluded.is_empty() {
+            format!("{}; excluded: none", formula)
+        } else {
+            format!("{}; excluded: {}", formula, excluded.join(", "))
+        }
+    }
+
+    /// Single-line, colorless summary intended for CI scripts to grep
+    ///
+    /// The format is stable across minor versions: a `MUTATION_SCORE` prefix
+    /// followed by space-separated `key=value` pairs. New keys may only be
+    /// appended at the end.
+    pub fn summary_line(&self) -> String {
+        format!(
+            "MUTATION_SCORE score={:.1} killed={} survived={} total={} timeouts={} compile_errors={} config_errors={} not_run={} not_covered={} crashed={}",
+            self.score(),
+            self.killed(),
+            self.survived(),
+            self.total(),
+            self.timeouts(),
+            self.compile_errors(),
+            self.config_errors(),
+            self.not_run(),
+            self.not_covered(),
+            self.crashed()
+        )
+    }
+
     /// Print the report to stdout
     pub fn print(&self) {
-        println!();
-        println!("{}", "Mutation Testing Report".bold());
-        println!("{}", "=".repeat(60));
-        println!();
+        let _ = self.write_text(&mut std::io::stdout());
+    }
+
+    /// Print only the surviving mutations followed by the summary line
+    pub fn print_survivors_only(&self) {
+        let _ = self.write_survivors_only(&mut std::io::stdout());
+    }
+
+    /// Write the text report
+    pub fn write_text(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out)?;
+        writeln!(out, "{}", "Mutation Testing Report".bold())?;
+        writeln!(out, "{}", "=".repeat(60))?;
+        writeln!(out)?;
+        if self.compact {
+            return self.write_compact(out);
+        }
 
-        // Print each result
+        // Write each result
         for result in &self.results {
-            let status_str = match &result.status {
-                MutationStatus::Killed => "[KILLED]".green().bold(),
-                MutationStatus::Survived => "[SURVIVED]".red().bold(),
-                MutationStatus::Timeout => "[TIMEOUT]".yellow().bold(),
-                MutationStatus::CompileError => "[COMPILE ERROR]".yellow().bold(),
-                MutationStatus::ConfigError(_) => "[CONFIG ERROR]".yellow().bold(),
-            };
+            let status_str =
+                status_colored(&result.status, format!("[{}]", status_name(&result.status)));
 
-            let location = if let Some(line) = result.line {
-                format!("{}:{}", result.file.display(), line)
-            } else {
-                result.file.display().to_string()
-            };
+            let location = result.location();
 
-            println!(
-                "{} {} - {} -> {}",
+            let killer = match &result.killed_by_test {
+                Some(test) => format!(" by test {}", test),
+                None => String::new(),
+            };
+            writeln!(
+                out,
+                "{} {} - {} -> {}{}",
                 status_str,
                 result.mutation_id.dimmed(),
                 result.original,
-                result.replacement
-            );
-            println!(
+                result.replacement,
+                killer.dimmed()
+            )?;
+            writeln!(
+                out,
                 "        {} in function '{}'",
                 location.dimmed(),
                 result.function
-            );
+            )?;
+        }
+
+        self.write_summary(out)?;
+        self.write_survivors(out)?;
+        self.write_timeouts(out)?;
+        self.write_unapplied(out)?;
+
+        // Score interpretation
+        writeln!(out)?;
+        writeln!(out, "{}", "Score Interpretation".dimmed())?;
+        writeln!(out, "{}", "-".repeat(40).dimmed())?;
+        writeln!(out, "{}", "90-100%: Excellent test coverage".dimmed())?;
+        writeln!(out, "{}", "70-89%:  Good coverage, some gaps".dimmed())?;
+        writeln!(out, "{}", "50-69%:  Moderate, needs improvement".dimmed())?;
+        writeln!(out, "{}", "<50%:    Poor, significant gaps".dimmed())?;
+
+        self.write_left_mutated(out)?;
+
+        writeln!(out)?;
+        writeln!(out, "{}", self.summary_line())?;
+        Ok(())
+    }
+
+    /// Write one line per mutation, with aligned columns, then the summary
+    ///
+    /// Multi-line code is collapsed onto the line.
+    fn write_compact(&self, out: &mut impl Write) -> io::Result<()> {
+        let one_line = |code: &str| code.split_whitespace().collect::<Vec<_>>().join(" ");
+        let rows: Vec<[String; 4]> = self
+            .results
+            .iter()
+            .map(|result| {
+                let location = match result.line {
+                    Some(line) => format!("{}:{}", result.file.display(), line),
+                    None => result.file.display().to_string(),
+                };
+                [
+                    status_name(&result.status).to_string(),
+                    result.mutation_id.clone(),
+                    location,
+                    result.function.clone(),
+                ]
+            })
+            .collect();
+        let mut widths = [0; 4];
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.chars().count());
+            }
+        }
+
+        for (result, [status, id, location, function]) in self.results.iter().zip(&rows) {
+            // Pad before coloring, so escape codes don't count towards the width
+            writeln!(
+                out,
+                "{}  {}  {:<location_width$}  {:<function_width$}  {} -> {}",
+                status_colored(&result.status, format!("{:<1$}", status, widths[0])),
+                format!("{:<1$}", id, widths[1]).dimmed(),
+                location,
+                function,
+                one_line(&result.original),
+                one_line(&result.replacement),
+                location_width = widths[2],
+                function_width = widths[3],
+            )?;
         }
 
-        // Print summary
-        println!();
-        println!("{}", "Summary".bold());
-        println!("{}", "-".repeat(40));
-        println!("Total mutations:   {}", self.total());
-        println!(
+        self.write_summary(out)?;
+        self.write_left_mutated(out)?;
+        writeln!(out)?;
+        writeln!(out, "{}", self.summary_line())?;
+        Ok(())
+    }
+
+    /// Write the summary: the counts by status, the score and the run's
+    /// duration and throughput
+    fn write_summary(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out)?;
+        writeln!(out, "{}", "Summary".bold())?;
+        writeln!(out, "{}", "-".repeat(40))?;
+        writeln!(out, "Total mutations:   {}", self.total())?;
+        writeln!(
+            out,
             "Killed:            {} {}",
             self.killed(),
             "(good - tests caught the mutation)".dimmed()
-        );
-        println!(
+        )?;
+        writeln!(
+            out,
             "Survived:          {} {}",
             self.survived(),
             "(bad - tests missed the mutation)".dimmed()
-        );
+        )?;
 
+        if self.crashed() > 0 {
+            writeln!(
+                out,
+                "  Crashed:         {} {}",
+                self.crashed(),
+                "(of the killed; check for missing guards)".dimmed()
+            )?;
+        }
         if self.timeouts() > 0 {
-            println!("Timeouts:          {}", self.timeouts());
+            writeln!(out, "Timeouts:          {}", self.timeouts())?;
         }
         if self.compile_errors() > 0 {
-            println!("Compile errors:    {}", self.compile_errors());
+            writeln!(out, "Compile errors:    {}", self.compile_errors())?;
         }
         if self.config_errors() > 0 {
-            println!("Config errors:     {}", self.config_errors());
+            writeln!(out, "Config errors:     {}", self.config_errors())?;
+        }
+        if self.not_run() > 0 {
+            writeln!(
+                out,
+                "Not run:           {} {}",
+                self.not_run(),
+                "(runtime budget exceeded)".dimmed()
+            )?;
+        }
+        if self.not_covered() > 0 {
+            writeln!(
+                out,
+                "Not covered:       {} {}",
+                self.not_covered(),
+                "(no test executes the line)".dimmed()
+            )?;
         }
 
-        println!();
+        writeln!(out)?;
         let score = self.score();
         let score_str = format!("{:.1}%", score);
         let score_colored = if score >= 90.0 {
@@ -160,62 +744,721 @@ impl MutationReport {
         } else {
             score_str.red().bold()
         };
-        println!("Mutation Score:    {}", score_colored);
-        println!(
-            "Duration:          {:.2}s",
-            self.total_duration.as_secs_f64()
-        );
+        writeln!(out, "Mutation Score:    {}", score_colored)?;
+        if self.not_run() > 0 {
+            writeln!(
+                out,
+                "{}",
+                format!(
+                    "Partial run: score covers {} of {} mutations",
+                    self.total() - self.not_run(),
+                    self.total()
+                )
+                .yellow()
+            )?;
+        }
+        writeln!(out, "Duration:          {}", format_duration(self.total_duration))?;
+        if let Some(throughput) = self.throughput() {
+            writeln!(
+                out,
+                "Throughput:        {:.2} mutations/s {}",
+                throughput.mutations_per_second,
+                format!(
+                    "(median {}, p95 {})",
+                    format_duration(throughput.median_duration),
+                    format_duration(throughput.p95_duration)
+                )
+                .dimmed()
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Write only the surviving mutations followed by the summary line
+    pub fn write_survivors_only(&self, out: &mut impl Write) -> io::Result<()> {
+        self.write_survivors(out)?;
+        self.write_left_mutated(out)?;
+        writeln!(out)?;
+        writeln!(out, "{}", self.summary_line())?;
+        Ok(())
+    }
+
+    /// Warn about files that were left mutated by `--no-restore`
+    fn write_left_mutated(&self, out: &mut impl Write) -> io::Result<()> {
+        for result in self.results.iter().filter(|r| r.left_mutated) {
+            writeln!(out)?;
+            writeln!(
+                out,
+                "{} {} was left mutated ({} -> {}); run `clean` to restore it",
+                "WARNING:".red().bold(),
+                result.location(),
+                result.original,
+                result.replacement
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Write the section on mutants only caught by timing out, if any
+    fn write_timeouts(&self, out: &mut impl Write) -> io::Result<()> {
+        let timeouts = self.timeouts_list();
+        if timeouts.is_empty() {
+            return Ok(());
+        }
+        writeln!(out)?;
+        writeln!(out, "{}", "Killed Only by Timeout".yellow().bold())?;
+        writeln!(out, "{}", "-".repeat(40))?;
+        for mutation in timeouts {
+            writeln!(
+                out,
+                "  • {} -> {}",
+                mutation.original.yellow(),
+                mutation.replacement.yellow()
+            )?;
+            writeln!(
+                out,
+                "    in function '{}' at {}",
+                mutation.function,
+                mutation.location()
+            )?;
+        }
+        writeln!(
+            out,
+            "{}",
+            "These mutants weren't detected by assertions, only by hanging; consider adding explicit tests."
+                .dimmed()
+        )?;
+        Ok(())
+    }
+
+    /// Write the config errors grouped by cause, with a few examples of each
+    fn write_unapplied(&self, out: &mut impl Write) -> io::Result<()> {
+        const EXAMPLES: usize = 3;
+        let groups = self.config_error_breakdown();
+        if groups.is_empty() {
+            return Ok(());
+        }
+        writeln!(out)?;
+        writeln!(out, "{}", "Unapplied Mutations".yellow().bold())?;
+        writeln!(out, "{}", "-".repeat(40))?;
+        for group in groups {
+            writeln!(out, "  {} ({})", group.kind.bold(), group.results.len())?;
+            for (result, error) in group.results.iter().take(EXAMPLES) {
+                let message = error.to_string();
+                let first_line = message.lines().next().unwrap_or_default();
+                writeln!(out, "    • {}: {}", result.mutation_id, first_line)?;
+            }
+            if group.results.len() > EXAMPLES {
+                let more = format!("    ... and {} more", group.results.len() - EXAMPLES);
+                writeln!(out, "{}", more.dimmed())?;
+            }
+        }
+        Ok(())
+    }
 
-        // Print surviving mutations if any
-        let survivors = self.surviving_mutations();
+    /// Write the "Surviving Mutations" section, if there are any survivors
+    fn write_survivors(&self, out: &mut impl Write) -> io::Result<()> {
+        let survivors = self.ordered_survivors();
+        // Show the category the survivors were ranked by
+        let show_category = matches!(
+            self.survivor_order,
+            Some(SurvivorOrder::Category | SurvivorOrder::Complexity)
+        );
         if !survivors.is_empty() {
-            println!();
-            println!(
+            writeln!(out)?;
+            writeln!(
+                out,
                 "{}",
                 "Surviving Mutations (improve your tests!)".red().bold()
-            );
-            println!("{}", "-".repeat(40));
+            )?;
+            writeln!(out, "{}", "-".repeat(40))?;
             for mutation in survivors {
-                let location = if let Some(line) = mutation.line {
-                    format!("{}:{}", mutation.file.display(), line)
+                let location = mutation.location();
+                let category = if show_category {
+                    let category = MutationCategory::of(&mutation.original, &mutation.replacement);
+                    format!(" ({})", category.label())
                 } else {
-                    mutation.file.display().to_string()
+                    String::new()
                 };
 
-                println!(
-                    "  • {} -> {}",
+                writeln!(
+                    out,
+                    "  • {} -> {}{}",
                     mutation.original.yellow(),
-                    mutation.replacement.yellow()
-                );
-                println!(
+                    mutation.replacement.yellow(),
+                    category.dimmed()
+                )?;
+                writeln!(
+                    out,
                     "    in function '{}' at {}",
                     mutation.function, location
-                );
+                )?;
+                if let Some(note) = &mutation.details {
+                    writeln!(out, "    {}", note.dimmed())?;
+                }
             }
         }
+        Ok(())
+    }
+}
 
-        // Score interpretation
-        println!();
-        println!("{}", "Score Interpretation".dimmed());
-        println!("{}", "-".repeat(40).dimmed());
-        println!("{}", "90-100%: Excellent test coverage".dimmed());
-        println!("{}", "70-89%:  Good coverage, some gaps".dimmed());
-        println!("{}", "50-69%:  Moderate, needs improvement".dimmed());
-        println!("{}", "<50%:    Poor, significant gaps".dimmed());
+/// Name of a status in the text report
+fn status_name(status: &MutationStatus) -> &'static str {
+    match status {
+        MutationStatus::Killed => "KILLED",
+        MutationStatus::Crashed => "CRASHED",
+        MutationStatus::Survived => "SURVIVED",
+        MutationStatus::Timeout => "TIMEOUT",
+        MutationStatus::CompileError => "COMPILE ERROR",
+        MutationStatus::ConfigError(_) => "CONFIG ERROR",
+        MutationStatus::NotRun => "NOT RUN",
+        MutationStatus::NotCovered => "NOT COVERED",
+    }
+}
+
+/// `text` in the color of `status`
+fn status_colored(status: &MutationStatus, text: String) -> ColoredString {
+    match status {
+        MutationStatus::Killed | MutationStatus::Crashed => text.green().bold(),
+        MutationStatus::Survived => text.red().bold(),
+        MutationStatus::Timeout | MutationStatus::CompileError | MutationStatus::ConfigError(_) => {
+            text.yellow().bold()
+        }
+        MutationStatus::NotRun => text.dimmed(),
+        MutationStatus::NotCovered => text.red(),
+    }
+}
+
+/// Nearest-rank percentile of sorted, non-empty `durations`
+fn percentile(durations: &[Duration], percent: usize) -> Duration {
+    let rank = (durations.len() * percent).div_ceil(100).max(1);
+    durations[rank - 1]
+}
+
+/// Remove ANSI escape sequences (`ESC [ ... <letter>`) from `text`
+fn strip_ansi(text: &str) -> String {
+    let mut plain = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Skip the sequence up to and including its final letter
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            plain.push(c);
+        }
+    }
+    plain
+}
+
+/// Exit code for a run where every mutant was killed (or the score met the threshold)
+pub const EXIT_OK: u8 = 0;
+/// Exit code for a run with surviving mutants, or a score below the threshold
+pub const EXIT_SURVIVORS: u8 = 1;
+/// Exit code for a run where some mutations couldn't be applied
+pub const EXIT_CONFIG_ERRORS: u8 = 2;
+/// Exit code for a run where some mutants didn't compile, with
+/// `--fail-on-compile-error`
+pub const EXIT_COMPILE_ERRORS: u8 = 3;
+
+/// Exit code for a finished run
+///
+/// Precedence, highest first:
+/// - [`EXIT_CONFIG_ERRORS`] if any mutation had a config error, since the
+///   score then doesn't cover everything that was configured; tests failing
+///   with an exit code outside `settings.kill_on_exit_codes` don't count, as
+///   the mutation was applied and tested
+/// - [`EXIT_COMPILE_ERRORS`] if `fail_on_compile_error` is set and any mutant
+///   didn't compile, which usually means a replacement no longer type-checks
+/// - [`EXIT_SURVIVORS`] if the score is below `threshold` or, without a
+///   threshold, if any mutant survived
+/// - [`EXIT_OK`] otherwise
+///
+/// Timeouts, mutations that were not run and, by default, compile errors don't
+/// affect the exit code.
+pub fn exit_code(
+    report: &MutationReport,
+    threshold: Option<f64>,
+    fail_on_compile_error: bool,
+) -> u8 {
+    let config_errors = report.results.iter().filter(|r| match &r.status {
+        MutationStatus::ConfigError(e) => !matches!(**e, MutationError::UnexpectedExitCode { .. }),
+        _ => false,
+    });
+    if config_errors.count() > 0 {
+        return EXIT_CONFIG_ERRORS;
+    }
+    if fail_on_compile_error && report.compile_errors() > 0 {
+        return EXIT_COMPILE_ERRORS;
+    }
+
+    let failed = match threshold {
+        Some(threshold) => report.score() < threshold,
+        None => report.survived() > 0,
+    };
+    if failed {
+        EXIT_SURVIVORS
+    } else {
+        EXIT_OK
     }
 }
 
 /// Format duration in a human-readable way
-#[allow(dead_code)]
-fn format_duration(d: Duration) -> String {
+pub fn format_duration(d: Duration) -> String {
     let secs = d.as_secs_f64();
     if secs < 1.0 {
         format!("{:.0}ms", secs * 1000.0)
     } else if secs < 60.0 {
         format!("{:.1}s", secs)
     } else {
-        let mins = (secs / 60.0).floor();
-        let remaining_secs = secs % 60.0;
-        format!("{}m {:.0}s", mins, remaining_secs)
+        let total = d.as_secs();
+        let (hours, mins, remaining_secs) = (total / 3600, (total % 3600) / 60, total % 60);
+        if hours > 0 {
+            format!("{}h {}m {}s", hours, mins, remaining_secs)
+        } else {
+            format!("{}m {}s", mins, remaining_secs)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::NearMatch;
+
+    fn result(status: MutationStatus) -> MutationResult {
+        MutationResult {
+            mutation_id: "m".to_string(),
+            file: PathBuf::from("src/lib.rs"),
+            function: "f".to_string(),
+            original: "a + b".to_string(),
+            replacement: "a - b".to_string(),
+            status,
+            duration: Duration::from_millis(10),
+            line: Some(1),
+            column: Some(5),
+            start_byte: None,
+            end_byte: None,
+            details: None,
+            killed_by_test: None,
+            started_at: None,
+            finished_at: None,
+            left_mutated: false,
+        }
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_millis(0)), "0ms");
+        assert_eq!(format_duration(Duration::from_millis(999)), "999ms");
+        assert_eq!(format_duration(Duration::from_millis(1000)), "1.0s");
+        assert_eq!(format_duration(Duration::from_millis(12_340)), "12.3s");
+        assert_eq!(format_duration(Duration::from_secs(60)), "1m 0s");
+        assert_eq!(format_duration(Duration::from_millis(312_400)), "5m 12s");
+        assert_eq!(format_duration(Duration::from_secs(3599)), "59m 59s");
+        assert_eq!(format_duration(Duration::from_secs(3600)), "1h 0m 0s");
+        assert_eq!(format_duration(Duration::from_secs(7384)), "2h 3m 4s");
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let killed = MutationResult {
+            started_at: Some(1_700_000_000_000),
+            finished_at: Some(1_700_000_000_010),
+            ..result(MutationStatus::Killed)
+        };
+        let report = MutationReport::new(vec![
+            killed,
+            result(MutationStatus::ConfigError(Box::new(MutationError::NoMatch {
+                file: PathBuf::from("src/lib.rs"),
+                function: "add".to_string(),
+                original: "a * b".to_string(),
+                suggestions: vec![NearMatch {
+                    line: 2,
+                    column: 5,
+                    code: "a + b".to_string(),
+                }],
+            }))),
+        ]);
+
+        let json = report.to_json();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["summary"]["killed"], 1);
+        assert_eq!(value["summary"]["config_errors"], 1);
+        assert_eq!(value["results"][0]["status"], "killed");
+        assert_eq!(value["results"][0]["duration"], 0.01);
+        assert_eq!(value["results"][0]["column"], 5);
+        assert_eq!(value["results"][0]["started_at"], 1_700_000_000_000u64);
+        assert_eq!(value["results"][0]["finished_at"], 1_700_000_000_010u64);
+        assert!(value["results"][1].get("started_at").is_none());
+        // Config errors keep their kind, so consumers can branch on it
+        assert_eq!(value["results"][1]["status"]["config_error"]["kind"], "no_match");
+        assert_eq!(value["results"][1]["status"]["config_error"]["original"], "a * b");
+
+        let parsed = MutationReport::from_json(&json).unwrap();
+        assert_eq!(parsed.total(), 2);
+        assert_eq!(parsed.results[1].status, report.results[1].status);
+        assert_eq!(parsed.results[0].finished_at, Some(1_700_000_000_010));
+
+        // Older reports stored config errors as a message
+        let mut old = value.clone();
+        old["results"][1]["status"]["config_error"] = "bad original".into();
+        let parsed = MutationReport::from_json(&old.to_string()).unwrap();
+        assert!(matches!(
+            &parsed.results[1].status,
+            MutationStatus::ConfigError(e) if e.to_string() == "Configuration error: bad original"
+        ));
+        assert_eq!(parsed.total_duration, report.total_duration);
+        assert_eq!(parsed.results[0].location(), "src/lib.rs:1:5");
+    }
+
+    #[test]
+    fn test_summary_json() {
+        let report = MutationReport::new(vec![
+            result(MutationStatus::Killed),
+            result(MutationStatus::Survived),
+            result(MutationStatus::Timeout),
+        ])
+        .with_wall_clock(Duration::from_millis(40));
+
+        let json = report.to_summary_json(Some("abc123"));
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["total"], 3);
+        assert_eq!(value["killed"], 1);
+        assert_eq!(value["survived"], 1);
+        assert_eq!(value["timeouts"], 1);
+        assert_eq!(value["score"], 50.0);
+        assert_eq!(value["total_duration"], 0.03);
+        assert_eq!(value["wall_clock"], 0.04);
+        assert_eq!(value["git_sha"], "abc123");
+        assert!(value.get("results").is_none());
+
+        // Without a known commit, the field is left out
+        let value: serde_json::Value = serde_json::from_str(&report.to_summary_json(None)).unwrap();
+        assert!(value.get("git_sha").is_none());
+    }
+
+    #[test]
+    fn test_throughput() {
+        let mut results: Vec<MutationResult> =
+            (1..=20).map(|_| result(MutationStatus::Killed)).collect();
+        for (i, result) in results.iter_mut().enumerate() {
+            result.duration = Duration::from_secs(i as u64 + 1);
+        }
+        results.push(result(MutationStatus::NotRun));
+        let report = MutationReport::new(results).with_wall_clock(Duration::from_secs(40));
+
+        let throughput = report.throughput().unwrap();
+        assert_eq!(throughput.mutations_per_second, 0.5);
+        assert_eq!(throughput.median_duration, Duration::from_secs(10));
+        assert_eq!(throughput.p95_duration, Duration::from_secs(19));
+
+        let value: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+        assert_eq!(value["wall_clock"], 40.0);
+        assert_eq!(value["summary"]["mutations_per_second"], 0.5);
+        assert_eq!(value["summary"]["median_duration"], 10.0);
+        assert_eq!(value["summary"]["p95_duration"], 19.0);
+        let parsed = MutationReport::from_json(&report.to_json()).unwrap();
+        assert_eq!(parsed.wall_clock, Some(Duration::from_secs(40)));
+
+        assert!(MutationReport::new(vec![result(MutationStatus::NotRun)])
+            .throughput()
+            .is_none());
+    }
+
+    #[test]
+    fn test_summary_line_format() {
+        let report = MutationReport::new(vec![
+            result(MutationStatus::Killed),
+            result(MutationStatus::Killed),
+            result(MutationStatus::Killed),
+            result(MutationStatus::Survived),
+            result(MutationStatus::Timeout),
+        ]);
+
+        assert_eq!(
+            report.summary_line(),
+            "MUTATION_SCORE score=75.0 killed=3 survived=1 total=5 timeouts=1 compile_errors=0 config_errors=0 not_run=0 not_covered=0 crashed=0"
+        );
+    }
+
+    #[test]
+    fn test_score_by_function() {
+        let in_function = |function: &str, status| MutationResult {
+            function: function.to_string(),
+            ..result(status)
+        };
+        let report = MutationReport::new(vec![
+            in_function("add", MutationStatus::Killed),
+            in_function("sub", MutationStatus::Timeout),
+            in_function("add", MutationStatus::Survived),
+            in_function("add", MutationStatus::Killed),
+        ]);
+
+        let scores = report.score_by_function();
+        assert_eq!(scores.len(), 2);
+        assert_eq!(scores[0].function, "add");
+        assert_eq!((scores[0].mutants, scores[0].killed, scores[0].survived), (3, 2, 1));
+        assert!((scores[0].score().unwrap() - 66.67).abs() < 0.01);
+        // No mutant of `sub` was killed or survived
+        assert_eq!(scores[1].mutants, 1);
+        assert_eq!(scores[1].score(), None);
+    }
+
+    #[test]
+    fn test_config_error_breakdown() {
+        let config_error = |id: &str, error: MutationError| MutationResult {
+            mutation_id: id.to_string(),
+            ..result(MutationStatus::ConfigError(Box::new(error)))
+        };
+        let no_match = |function: &str| MutationError::NoMatch {
+            file: PathBuf::from("src/lib.rs"),
+            function: function.to_string(),
+            original: "a + b".to_string(),
+            suggestions: Vec::new(),
+        };
+        let missing = MutationError::FileNotFound {
+            file: PathBuf::from("src/gone.rs"),
+        };
+        let mut results = vec![config_error("gone", missing), result(MutationStatus::Killed)];
+        for i in 0..4 {
+            results.push(config_error(&format!("stale{}", i), no_match("add")));
+        }
+        let report = MutationReport::new(results);
+
+        let groups = report.config_error_breakdown();
+        let counts: Vec<_> = groups.iter().map(|g| (g.kind, g.results.len())).collect();
+        assert_eq!(counts, [("no_match", 4), ("file_not_found", 1)]);
+        assert_eq!(groups[0].results[0].0.mutation_id, "stale0");
+
+        let mut text = Vec::new();
+        report.write_text(&mut text).unwrap();
+        let text = strip_ansi(&String::from_utf8(text).unwrap());
+        assert!(text.contains("Unapplied Mutations"));
+        assert!(text.contains("  no_match (4)\n    • stale0: Expression 'a + b' not found in function 'add'\n"));
+        assert!(text.contains("    ... and 1 more\n  file_not_found (1)\n    • gone: File not found: src/gone.rs\n"));
+    }
+
+    #[test]
+    fn test_status_predicates() {
+        use MutationStatus::*;
+        let bad = MutationError::FailedToApply {
+            reason: "x".to_string(),
+        };
+        let mut statuses = vec![Killed, Survived, Timeout, CompileError];
+        statuses.extend([ConfigError(Box::new(bad)), NotRun, NotCovered]);
+        let report = MutationReport::new(statuses.into_iter().map(result).collect());
+        let flags: Vec<(bool, bool, bool, bool)> = report
+            .results
+            .iter()
+            .map(|r| (r.is_killed(), r.is_survived(), r.is_gap(), r.is_error()))
+            .collect();
+        assert_eq!(
+            flags,
+            [
+                (true, false, false, false),
+                (false, true, true, false),
+                (false, false, false, false),
+                (false, false, false, true),
+                (false, false, false, true),
+                (false, false, false, false),
+                (false, false, true, false),
+            ]
+        );
+
+        let gaps = report.gaps();
+        assert_eq!(gaps.len(), 2);
+        assert_eq!(gaps[0].status, Survived);
+        assert_eq!(gaps[1].status, NotCovered);
+    }
+
+    #[test]
+    fn test_timeouts_list() {
+        let report = MutationReport::new(vec![
+            result(MutationStatus::Killed),
+            result(MutationStatus::Timeout),
+            result(MutationStatus::Survived),
+        ]);
+        let timeouts = report.timeouts_list();
+        assert_eq!(timeouts.len(), 1);
+        assert_eq!(timeouts[0].status, MutationStatus::Timeout);
+
+        let mut text = Vec::new();
+        report.write_text(&mut text).unwrap();
+        let text = strip_ansi(&String::from_utf8(text).unwrap());
+        assert!(text.contains("Killed Only by Timeout"));
+        assert!(text.contains("weren't detected by assertions, only by hanging"));
+
+        let mut text = Vec::new();
+        MutationReport::new(vec![result(MutationStatus::Killed)])
+            .write_text(&mut text)
+            .unwrap();
+        assert!(!String::from_utf8(text).unwrap().contains("Killed Only by Timeout"));
+    }
+
+    #[test]
+    fn test_write_text_file_is_plain() {
+        assert_eq!(strip_ansi("\x1b[1;31m[SURVIVED]\x1b[0m m"), "[SURVIVED] m");
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("report.txt");
+        let report = MutationReport::new(vec![result(MutationStatus::Survived)]);
+        report.write_text_file(&path, false).unwrap();
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(!text.contains('\x1b'));
+        assert!(text.contains("[SURVIVED] m - a + b -> a - b"));
+        assert!(text.ends_with(&format!("{}\n", report.summary_line())));
+    }
+
+    #[test]
+    fn test_survivor_order() {
+        let mut overflow = result(MutationStatus::Survived);
+        overflow.replacement = "a.wrapping_add(b)".to_string();
+        let results = vec![overflow, result(MutationStatus::Survived)];
+
+        let survivors = |report: &MutationReport| {
+            let mut text = Vec::new();
+            report.write_survivors_only(&mut text).unwrap();
+            let text = strip_ansi(&String::from_utf8(text).unwrap());
+            text.lines()
+                .filter(|line| line.starts_with("  •"))
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        };
+        let report = MutationReport::new(results);
+        assert_eq!(
+            survivors(&report),
+            ["  • a + b -> a.wrapping_add(b)", "  • a + b -> a - b"]
+        );
+        let report = report.with_survivor_order(SurvivorOrder::Category);
+        assert_eq!(
+            survivors(&report),
+            [
+                "  • a + b -> a - b (operator swap)",
+                "  • a + b -> a.wrapping_add(b) (method call)"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compact_report() {
+        let mut killed = result(MutationStatus::Killed);
+        killed.mutation_id = "add_swap".to_string();
+        killed.file = PathBuf::from("src/math.rs");
+        killed.line = Some(12);
+        let mut removed = result(MutationStatus::Survived);
+        removed.function = "log_total".to_string();
+        removed.original = "{\n    log(total);\n}".to_string();
+        removed.replacement = "{}".to_string();
+        let report = MutationReport::new(vec![killed, removed]).with_compact();
+
+        let mut text = Vec::new();
+        report.write_text(&mut text).unwrap();
+        let text = strip_ansi(&String::from_utf8(text).unwrap());
+        let rows: Vec<&str> = text.lines().filter(|line| line.contains(" -> ")).collect();
+        assert_eq!(
+            rows,
+            [
+                "KILLED    add_swap  src/math.rs:12  f          a + b -> a - b",
+                "SURVIVED  m         src/lib.rs:1    log_total  { log(total); } -> {}",
+            ]
+        );
+        assert!(!text.contains("Surviving Mutations"));
+        assert!(text.contains("Mutation Score:"));
+        assert!(text.ends_with(&format!("{}\n", report.summary_line())));
+    }
+
+    #[test]
+    fn test_explain_score() {
+        use MutationStatus::*;
+        let bad = MutationError::FailedToApply {
+            reason: "x".to_string(),
+        };
+        let mut statuses = vec![Killed, Killed, Killed, Survived, Timeout, Timeout];
+        statuses.extend([CompileError, ConfigError(Box::new(bad)), NotRun]);
+        let report = MutationReport::new(statuses.into_iter().map(result).collect());
+        assert_eq!(
+            report.explain_score(),
+            "score = killed (3) / (killed 3 + survived 1) = 75.0%; \
+             excluded: 2 timeouts, 1 compile error, 1 config error, 1 not run"
+        );
+
+        let clean = MutationReport::new(vec![result(Killed), result(Survived)]);
+        assert_eq!(
+            clean.explain_score(),
+            "score = killed (1) / (killed 1 + survived 1) = 50.0%; excluded: none"
+        );
+
+        let nothing = MutationReport::new(vec![result(CompileError)]);
+        assert_eq!(
+            nothing.explain_score(),
+            "score = 100.0% (no mutants were killed or survived); excluded: 1 compile error"
+        );
+    }
+
+    #[test]
+    fn test_append_history() {
+        use MutationStatus::*;
+        let report = MutationReport::new(vec![result(Killed), result(Killed), result(Survived)]);
+        assert_eq!(
+            report.history_row(1_700_000_000, Some("abc123")),
+            "1700000000,abc123,3,2,1,66.67"
+        );
+        assert_eq!(report.history_row(1, None), "1,,3,2,1,66.67");
+
+        // The header is only written when the file is created
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("mutation-history.csv");
+        report.append_history(&path, Some("abc123")).unwrap();
+        report.append_history(&path, Some("def456")).unwrap();
+
+        let history = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = history.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], HISTORY_HEADER);
+        assert!(lines[1].ends_with(",abc123,3,2,1,66.67"));
+        assert!(lines[2].ends_with(",def456,3,2,1,66.67"));
+    }
+
+    #[test]
+    fn test_exit_code() {
+        use MutationStatus::*;
+        let report = |statuses: Vec<MutationStatus>| {
+            MutationReport::new(statuses.into_iter().map(result).collect())
+        };
+
+        assert_eq!(exit_code(&report(vec![Killed, Timeout, CompileError]), None, false), EXIT_OK);
+        assert_eq!(exit_code(&report(vec![Killed, Survived]), None, false), EXIT_SURVIVORS);
+
+        // Config errors win over survivors
+        let bad = MutationError::FailedToApply {
+            reason: "bad".to_string(),
+        };
+        let mixed = report(vec![Survived, ConfigError(Box::new(bad))]);
+        assert_eq!(exit_code(&mixed, None, false), EXIT_CONFIG_ERRORS);
+        assert_eq!(exit_code(&mixed, Some(0.0), false), EXIT_CONFIG_ERRORS);
+
+        // A threshold tolerates survivors as long as the score meets it
+        let half = report(vec![Killed, Survived]);
+        assert_eq!(exit_code(&half, Some(50.0), false), EXIT_OK);
+        assert_eq!(exit_code(&half, Some(75.0), false), EXIT_SURVIVORS);
+
+        // Compile errors only count with --fail-on-compile-error, and win over
+        // survivors but not config errors
+        let uncompiled = report(vec![Killed, Survived, CompileError]);
+        assert_eq!(exit_code(&uncompiled, None, false), EXIT_SURVIVORS);
+        assert_eq!(exit_code(&uncompiled, None, true), EXIT_COMPILE_ERRORS);
+        assert_eq!(exit_code(&report(vec![Killed]), None, true), EXIT_OK);
+        assert_eq!(exit_code(&mixed, None, true), EXIT_CONFIG_ERRORS);
+
+        // Unexpected exit codes were tested, just not as kills
+        let unexpected = MutationError::UnexpectedExitCode { code: Some(2) };
+        let inconclusive = report(vec![Killed, ConfigError(Box::new(unexpected))]);
+        assert_eq!(exit_code(&inconclusive, None, false), EXIT_OK);
     }
 }
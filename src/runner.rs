@@ -5,19 +5,35 @@
 //! - Runs tests
 //! - Collects results
 
-use std::path::Path;
-use std::process::Command;
-use std::time::{Duration, Instant};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use crate::codegen::apply_mutation_to_file;
-use crate::config::{Config, MutationConfig};
+use serde::{Deserialize, Serialize};
+
+use crate::backup::{remove_backup, write_backup, RunLock};
+use crate::codegen::{
+    apply_mutation_to_file, near_match_error, prepare_mutation, prepare_mutations,
+};
+use crate::config::{Config, MutationConfig, Settings, TestKind};
 use crate::error::{MutationError, Result};
+use crate::files::is_glob;
+use crate::report::{duration_secs, format_duration, MutationReport};
+use crate::targets::{
+    check_doc_targets, discover_test_targets, select_targets, TargetKind, TestTarget,
+};
 
 /// Status of a mutation after testing
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum MutationStatus {
     /// Tests failed - mutation was detected (good!)
     Killed,
+    /// A test process crashed (killed by a signal, e.g. a segfault, abort or
+    /// stack overflow); counts as killed, but such a kill may point at a
+    /// missing guard rather than an asserted behavior
+    Crashed,
     /// Tests passed - mutation was NOT detected (bad!)
     Survived,
     /// Tests timed out
@@ -25,11 +41,50 @@ pub enum MutationStatus {
     /// Mutated code failed to compile
     CompileError,
     /// Configuration error (couldn't apply mutation)
-    ConfigError(String),
+    #[serde(deserialize_with = "config_error_or_message")]
+    ConfigError(Box<MutationError>),
+    /// Not run because the runtime budget was exhausted
+    NotRun,
+    /// Not run because the coverage data shows no test executes the mutated
+    /// line (`--coverage`)
+    NotCovered,
+}
+
+impl MutationStatus {
+    /// Short name of the status, as used in the JSON report
+    pub fn label(&self) -> &'static str {
+        match self {
+            MutationStatus::Killed => "killed",
+            MutationStatus::Crashed => "crashed",
+            MutationStatus::Survived => "survived",
+            MutationStatus::Timeout => "timeout",
+            MutationStatus::CompileError => "compile_error",
+            MutationStatus::ConfigError(_) => "config_error",
+            MutationStatus::NotRun => "not_run",
+            MutationStatus::NotCovered => "not_covered",
+        }
+    }
+}
+
+/// Read a config error, or the plain message older reports stored instead
+fn config_error_or_message<'de, D>(deserializer: D) -> std::result::Result<Box<MutationError>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Stored {
+        Error(Box<MutationError>),
+        Message(String),
+    }
+    Ok(match Stored::deserialize(deserializer)? {
+        Stored::Error(error) => error,
+        Stored::Message(message) => Box::new(MutationError::ConfigError { message }),
+    })
 }
 
 /// Result of running a single mutation
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct MutationResult {
     pub mutation_id: String,
     pub file: std::path::PathBuf,
@@ -37,12 +92,66 @@ pub struct MutationResult {
     pub original: String,
     pub replacement: String,
     pub status: MutationStatus,
+    #[serde(with = "duration_secs")]
     pub duration: Duration,
     pub line: Option<usize>,
+    #[serde(default)]
+    pub column: Option<usize>,
+    /// Byte offset of the start of the mutated code in the original file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_byte: Option<usize>,
+    /// Byte offset just past the end of the mutated code in the original file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_byte: Option<usize>,
     pub details: Option<String>,
+    /// The first test that failed, if the mutant was killed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub killed_by_test: Option<String>,
+    /// When testing the mutation started, in milliseconds since the Unix epoch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<u64>,
+    /// When testing the mutation finished, in milliseconds since the Unix epoch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<u64>,
+    /// The mutant was left in place instead of being restored (`--no-restore`)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub left_mutated: bool,
 }
 
 impl MutationResult {
+    /// The tests detected the mutant, including by crashing
+    pub fn is_killed(&self) -> bool {
+        matches!(self.status, MutationStatus::Killed | MutationStatus::Crashed)
+    }
+
+    /// The tests passed with the mutant in place
+    pub fn is_survived(&self) -> bool {
+        self.status == MutationStatus::Survived
+    }
+
+    /// The mutant points at a gap in the tests: it survived, including when
+    /// only tests outside its `killed_by` list failed, or no test covers it
+    pub fn is_gap(&self) -> bool {
+        self.is_survived() || self.status == MutationStatus::NotCovered
+    }
+
+    /// The mutant couldn't be tested: it didn't compile or couldn't be applied
+    pub fn is_error(&self) -> bool {
+        matches!(
+            self.status,
+            MutationStatus::CompileError | MutationStatus::ConfigError(_)
+        )
+    }
+
+    /// Where the mutation was applied, as `file:line:col` when known
+    pub fn location(&self) -> String {
+        match (self.line, self.column) {
+            (Some(line), Some(column)) => format!("{}:{}:{}", self.file.display(), line, column),
+            (Some(line), None) => format!("{}:{}", self.file.display(), line),
+            _ => self.file.display().to_string(),
+        }
+    }
+
     pub fn description(&self) -> String {
         format!(
             "{} -> {} in {}::{}",
@@ -55,14 +164,101 @@ impl MutationResult {
 }
 
 /// Run mutation testing with the given configuration
+///
+/// Mutations whose `file` is a glob are expanded first (see
+/// [`Config::expand_file_globs`]). Fails with [`MutationError::RunInProgress`] if another run holds the
+/// crate's lock file.
 pub fn run_mutation_tests(
     config: &Config,
     project_dir: &Path,
     verbose: bool,
-) -> Vec<MutationResult> {
+) -> Result<Vec<MutationResult>> {
+    let expanded;
+    let config = if config.mutations.iter().any(|m| is_glob(&m.file)) {
+        let mut owned = config.clone();
+        owned.expand_file_globs(project_dir)?;
+        expanded = owned;
+        &expanded
+    } else {
+        config
+    };
+    let project_dir = &config.settings.crate_dir(project_dir);
+    let _lock = RunLock::acquire(project_dir)?;
     let mut results = Vec::new();
+    let started = Instant::now();
+    let budget = config.settings.max_runtime.map(Duration::from_secs);
+
+    if let Some(binary) = &config.settings.test_binary {
+        if !project_dir.join(binary).is_file() {
+            return Err(MutationError::ConfigError {
+                message: format!("settings.test_binary '{}' not found", binary.display()),
+            });
+        }
+    }
+
+    // Enumerate test targets once; each mutation runs them in parallel, or
+    // selects the kinds of tests among them
+    let kinds = &config.settings.test_kinds;
+    let selects_kinds = config.settings.test_binary.is_none() && !kinds.is_empty();
+    let discovered = if config.settings.split_targets || selects_kinds {
+        match discover_test_targets(project_dir) {
+            Ok(targets) => Some(targets),
+            Err(e) if config.settings.split_targets => {
+                eprintln!("WARNING: {}; running all targets together", e);
+                None
+            }
+            Err(_) => None,
+        }
+    } else {
+        None
+    };
+    let mut settings = config.settings.clone();
+    if let (true, Some(discovered)) = (selects_kinds, &discovered) {
+        check_doc_targets(discovered, kinds)?;
+        settings.lib_target = Some(discovered.iter().any(|t| t.kind == TargetKind::Lib));
+    }
+    let settings = &settings;
+    let targets = match discovered {
+        Some(targets) if config.settings.split_targets => select_targets(targets, kinds),
+        _ => Vec::new(),
+    };
+
+    for unit in test_units(&config.mutations) {
+        if let Some(group) = &unit[0].group {
+            let group_mutation = group_config(group, &unit);
+            if budget.is_some_and(|budget| started.elapsed() >= budget) {
+                results.push(not_run_result(&group_mutation));
+                continue;
+            }
+            if verbose {
+                eprintln!(
+                    "Testing group {} ({} mutations): {}",
+                    group,
+                    unit.len(),
+                    group_mutation.description()
+                );
+            }
+            let result = run_mutation_group(
+                group,
+                &unit,
+                project_dir,
+                settings,
+                &targets,
+                verbose,
+            );
+            if verbose {
+                eprintln!("  {:?} after {}", result.status, format_duration(result.duration));
+            }
+            results.push(result);
+            continue;
+        }
+
+        let mutation = unit[0];
+        if budget.is_some_and(|budget| started.elapsed() >= budget) {
+            results.push(not_run_result(mutation));
+            continue;
+        }
 
-    for mutation in &config.mutations {
         if verbose {
             eprintln!(
                 "Testing mutation: {} -> {} in {}::{}",
@@ -70,114 +266,557 @@ pub fn run_mutation_tests(
             );
         }
 
-        let result = run_single_mutation(mutation, project_dir, config.settings.timeout, verbose);
+        let mutate = |source: &str| {
+            let prepared = prepare_mutation(source, mutation)?;
+            Ok(Mutant {
+                source: prepared.mutated_source,
+                line: prepared.site.line,
+                column: prepared.site.column,
+                start_byte: prepared.site.start_byte,
+                end_byte: prepared.site.end_byte,
+            })
+        };
+        let result = run_single_mutation(
+            mutation,
+            project_dir,
+            settings,
+            &targets,
+            &mutate,
+            verbose,
+        );
+        if verbose {
+            eprintln!(
+                "  {:?} after {}",
+                result.status,
+                format_duration(result.duration)
+            );
+        }
         results.push(result);
     }
 
-    results
+    Ok(results)
+}
+
+/// Reorder mutations so those that survived in a previous run go first
+///
+/// Mutations are matched to the previous report by the code they change, not
+/// by id, since generated ids shift when mutations are added or removed.
+/// Previously killed mutations go last; everything else keeps its config order
+/// in between.
+pub fn prioritize_survivors(mutations: &mut [MutationConfig], previous: &MutationReport) {
+    let rank = |mutation: &MutationConfig| {
+        match previous
+            .results
+            .iter()
+            .find(|r| same_mutation(mutation, r))
+            .map(|r| &r.status)
+        {
+            Some(MutationStatus::Survived) => 0,
+            Some(MutationStatus::Killed | MutationStatus::Crashed) => 2,
+            _ => 1,
+        }
+    };
+    mutations.sort_by_key(rank);
+}
+
+/// Whether `result` is a previous run of `mutation`
+///
+/// An original or replacement the config leaves to an anchor or operator, and
+/// a line it doesn't pin, match anything.
+fn same_mutation(mutation: &MutationConfig, result: &MutationResult) -> bool {
+    let matches =
+        |configured: &str, recorded: &str| configured.is_empty() || configured == recorded;
+    mutation.file == result.file
+        && mutation.function == result.function
+        && matches(&mutation.original, &result.original)
+        && matches(&mutation.replacement, &result.replacement)
+        && (mutation.line.is_none() || mutation.line == result.line)
+}
+
+/// Result for a mutation skipped because the runtime budget was exhausted
+pub(crate) fn not_run_result(mutation: &MutationConfig) -> MutationResult {
+    MutationResult {
+        mutation_id: mutation.id.clone(),
+        file: mutation.file.clone(),
+        function: mutation.function.clone(),
+        original: mutation.original.clone(),
+        replacement: mutation.replacement.clone(),
+        status: MutationStatus::NotRun,
+        duration: Duration::ZERO,
+        line: None,
+        column: None,
+        start_byte: None,
+        end_byte: None,
+        details: None,
+        killed_by_test: None,
+        started_at: None,
+        finished_at: None,
+        left_mutated: false,
+    }
+}
+
+/// A mutated file, ready to be tested
+pub struct Mutant {
+    /// The full mutated content of the file
+    pub source: String,
+    /// Line of the mutated code (1-indexed)
+    pub line: usize,
+    /// Column of the mutated code (0-indexed)
+    pub column: usize,
+    /// Byte offsets of the mutated code in the original file, if known
+    pub start_byte: Option<usize>,
+    pub end_byte: Option<usize>,
+}
+
+/// One file changed for a test run, with the function producing its mutant
+pub(crate) struct FileMutation<'a> {
+    /// The file, relative to the crate
+    pub(crate) file: &'a Path,
+    pub(crate) mutate: &'a dyn Fn(&str) -> Result<Mutant>,
 }
 
 /// Run a single mutation test
-fn run_single_mutation(
+///
+/// `mutate` produces the mutant from the file's original content. A panic
+/// while preparing or running the mutation (e.g. from an exotic AST) is
+/// reported as a configuration error so the remaining mutations still run.
+pub fn run_single_mutation(
     mutation: &MutationConfig,
     project_dir: &Path,
-    timeout_secs: u64,
+    settings: &Settings,
+    targets: &[TestTarget],
+    mutate: &dyn Fn(&str) -> Result<Mutant>,
+    verbose: bool,
+) -> MutationResult {
+    let files = [FileMutation {
+        file: &mutation.file,
+        mutate,
+    }];
+    run_file_mutations(mutation, &files, project_dir, settings, targets, verbose)
+}
+
+/// Run a group of mutations as one higher-order mutant
+///
+/// Every member is applied, in config order, before a single test run; members
+/// in the same file are applied on top of each other. The result is that of
+/// [`group_config`].
+pub fn run_mutation_group(
+    name: &str,
+    members: &[&MutationConfig],
+    project_dir: &Path,
+    settings: &Settings,
+    targets: &[TestTarget],
+    verbose: bool,
+) -> MutationResult {
+    let mutation = group_config(name, members);
+
+    // Members by file, in order of first appearance
+    let mut by_file: Vec<(&Path, Vec<&MutationConfig>)> = Vec::new();
+    for member in members {
+        match by_file.iter_mut().find(|(file, _)| *file == member.file) {
+            Some((_, file_members)) => file_members.push(member),
+            None => by_file.push((&member.file, vec![member])),
+        }
+    }
+    let mutates: Vec<_> = by_file
+        .iter()
+        .map(|(_, file_members)| move |source: &str| mutate_all(source, file_members))
+        .collect();
+    let files: Vec<FileMutation> = by_file
+        .iter()
+        .zip(&mutates)
+        .map(|((file, _), mutate)| FileMutation { file, mutate })
+        .collect();
+    run_file_mutations(&mutation, &files, project_dir, settings, targets, verbose)
+}
+
+/// Run `mutation` by changing each of `files` at once
+///
+/// A panic while preparing or running the mutation is reported as a
+/// configuration error.
+pub(crate) fn run_file_mutations(
+    mutation: &MutationConfig,
+    files: &[FileMutation],
+    project_dir: &Path,
+    settings: &Settings,
+    targets: &[TestTarget],
     verbose: bool,
 ) -> MutationResult {
     let start = Instant::now();
+    let started_at = unix_millis();
 
-    // Resolve file path relative to project directory
-    let file_path = project_dir.join(&mutation.file);
+    let outcome = catch_panic(|| {
+        run_mutation_inner(mutation, files, project_dir, settings, targets, verbose, start)
+    });
+    let result = outcome.unwrap_or_else(|message| MutationResult {
+        mutation_id: mutation.id.clone(),
+        file: mutation.file.clone(),
+        function: mutation.function.clone(),
+        original: mutation.original.clone(),
+        replacement: mutation.replacement.clone(),
+        status: MutationStatus::ConfigError(Box::new(MutationError::FailedToApply {
+            reason: format!("internal error: {}", message),
+        })),
+        duration: start.elapsed(),
+        line: None,
+        column: None,
+        start_byte: None,
+        end_byte: None,
+        details: Some(message),
+        killed_by_test: None,
+        started_at: None,
+        finished_at: None,
+        left_mutated: false,
+    });
+    timestamped(result, started_at)
+}
 
-    // Check file exists
-    if !file_path.exists() {
-        return MutationResult {
-            mutation_id: mutation.id.clone(),
-            file: mutation.file.clone(),
-            function: mutation.function.clone(),
-            original: mutation.original.clone(),
-            replacement: mutation.replacement.clone(),
-            status: MutationStatus::ConfigError(format!(
-                "File not found: {}",
-                file_path.display()
-            )),
-            duration: start.elapsed(),
-            line: None,
-            details: None,
+/// The current time in milliseconds since the Unix epoch
+fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+/// Record that testing `result` started at `started_at` and finished now
+fn timestamped(mut result: MutationResult, started_at: u64) -> MutationResult {
+    result.started_at = Some(started_at);
+    result.finished_at = Some(unix_millis());
+    result
+}
+
+/// Apply `mutations` to `source` together (see [`prepare_mutations`])
+///
+/// The mutant's location is that of the first mutation.
+fn mutate_all(source: &str, mutations: &[&MutationConfig]) -> Result<Mutant> {
+    let (mutated, sites) = prepare_mutations(source, mutations)?;
+    let site = sites.first();
+    Ok(Mutant {
+        source: mutated,
+        line: site.map_or(0, |site| site.line),
+        column: site.map_or(0, |site| site.column),
+        start_byte: site.and_then(|site| site.start_byte),
+        end_byte: site.and_then(|site| site.end_byte),
+    })
+}
+
+/// The mutation a group is reported as
+///
+/// It is named after the group and located in the first member's file. The
+/// members' functions, originals and replacements are joined with `; `, their
+/// `env`s merged and `killed_by` lists combined, and the longest `timeout` is
+/// used.
+pub fn group_config(name: &str, members: &[&MutationConfig]) -> MutationConfig {
+    let join = |field: fn(&MutationConfig) -> &str| {
+        let mut values: Vec<&str> = members.iter().map(|m| field(m)).collect();
+        values.dedup();
+        values.join("; ")
+    };
+    let killed_by: Vec<String> = members
+        .iter()
+        .filter_map(|m| m.killed_by.as_ref())
+        .flatten()
+        .cloned()
+        .collect();
+    let env: HashMap<String, String> = members
+        .iter()
+        .filter_map(|m| m.env.as_ref())
+        .flatten()
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+
+    MutationConfig {
+        file: members.first().map(|m| m.file.clone()).unwrap_or_default(),
+        function: join(|m| &m.function),
+        original: join(|m| &m.original),
+        replacement: join(|m| &m.replacement),
+        id: name.to_string(),
+        killed_by: members.iter().any(|m| m.killed_by.is_some()).then_some(killed_by),
+        env: members.iter().any(|m| m.env.is_some()).then_some(env),
+        timeout: members.iter().filter_map(|m| m.timeout).max(),
+        group: Some(name.to_string()),
+        ..Default::default()
+    }
+}
+
+/// Split mutations into what is tested together: each ungrouped mutation on
+/// its own, and each group as a whole at the position of its first member
+pub fn test_units(mutations: &[MutationConfig]) -> Vec<Vec<&MutationConfig>> {
+    let mut units: Vec<Vec<&MutationConfig>> = Vec::new();
+    for mutation in mutations {
+        let unit = mutation.group.as_ref().and_then(|group| {
+            units
+                .iter_mut()
+                .find(|unit| unit[0].group.as_ref() == Some(group))
+        });
+        match unit {
+            Some(unit) => unit.push(mutation),
+            None => units.push(vec![mutation]),
+        }
+    }
+    units
+}
+
+/// Run `f`, converting a panic into its message
+fn catch_panic<T>(f: impl FnOnce() -> T) -> std::result::Result<T, String> {
+    std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)).map_err(|payload| {
+        if let Some(message) = payload.downcast_ref::<&str>() {
+            message.to_string()
+        } else if let Some(message) = payload.downcast_ref::<String>() {
+            message.clone()
+        } else {
+            "unknown panic".to_string()
+        }
+    })
+}
+
+/// Restores a file's original content when dropped, including while unwinding
+///
+/// The on-disk backup is only removed once the restore succeeded, so a failed
+/// restore can still be recovered with the `clean` command.
+struct RestoreGuard<'a> {
+    path: &'a Path,
+    content: &'a str,
+    crate_dir: &'a Path,
+    backup: Option<PathBuf>,
+}
+
+impl Drop for RestoreGuard<'_> {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::write(self.path, self.content) {
+            eprintln!("WARNING: Failed to restore original file: {}", e);
+        } else if let Some(backup) = &self.backup {
+            remove_backup(self.crate_dir, backup);
+        }
+    }
+}
+
+fn run_mutation_inner(
+    mutation: &MutationConfig,
+    files: &[FileMutation],
+    project_dir: &Path,
+    settings: &Settings,
+    targets: &[TestTarget],
+    verbose: bool,
+    start: Instant,
+) -> MutationResult {
+    let failed = |error: MutationError, line, column, details| MutationResult {
+        mutation_id: mutation.id.clone(),
+        file: mutation.file.clone(),
+        function: mutation.function.clone(),
+        original: mutation.original.clone(),
+        replacement: mutation.replacement.clone(),
+        status: MutationStatus::ConfigError(Box::new(error)),
+        duration: start.elapsed(),
+        line,
+        column,
+        start_byte: None,
+        end_byte: None,
+        details,
+        killed_by_test: None,
+        started_at: None,
+        finished_at: None,
+        left_mutated: false,
+    };
+
+    // Prepare every mutant before touching any file
+    let mut prepared = Vec::new();
+    for file in files {
+        // Resolve file path relative to project directory
+        let file_path = project_dir.join(file.file);
+
+        // Check file exists
+        if !file_path.exists() {
+            return failed(MutationError::FileNotFound { file: file_path }, None, None, None);
+        }
+
+        // Read original file content for restoration
+        let original_content = match std::fs::read_to_string(&file_path) {
+            Ok(content) => content,
+            Err(e) => {
+                let error = MutationError::FileReadError {
+                    file: file_path,
+                    error: e.to_string(),
+                };
+                return failed(error, None, None, None);
+            }
+        };
+
+        // Prepare the mutation
+        let mutant = match (file.mutate)(&original_content) {
+            Ok(mutant) => mutant,
+            Err(e) => {
+                let details = Some(e.to_string());
+                return failed(e, None, None, details);
+            }
         };
+        prepared.push((file.file, file_path, original_content, mutant));
     }
 
-    // Read original file content for restoration
-    let original_content = match std::fs::read_to_string(&file_path) {
-        Ok(content) => content,
-        Err(e) => {
+    // The location reported is that of the first mutated file
+    let line = prepared.first().map(|(_, _, _, mutant)| mutant.line);
+    let column = prepared.first().map(|(_, _, _, mutant)| mutant.column);
+    let start_byte = prepared.first().and_then(|(_, _, _, mutant)| mutant.start_byte);
+    let end_byte = prepared.first().and_then(|(_, _, _, mutant)| mutant.end_byte);
+
+    // A mutant on lines no test executes would trivially survive
+    if let Some(coverage) = &settings.coverage {
+        if prepared
+            .iter()
+            .all(|(_, file_path, _, mutant)| coverage.is_uncovered(file_path, mutant.line))
+        {
             return MutationResult {
                 mutation_id: mutation.id.clone(),
                 file: mutation.file.clone(),
                 function: mutation.function.clone(),
                 original: mutation.original.clone(),
                 replacement: mutation.replacement.clone(),
-                status: MutationStatus::ConfigError(format!("Failed to read file: {}", e)),
+                status: MutationStatus::NotCovered,
                 duration: start.elapsed(),
-                line: None,
+                line,
+                column,
+                start_byte,
+                end_byte,
                 details: None,
+                killed_by_test: None,
+                started_at: None,
+                finished_at: None,
+                left_mutated: false,
             };
         }
-    };
+    }
 
-    // Prepare the mutation
-    let prepared = match apply_mutation_to_file(&file_path, mutation) {
-        Ok(p) => p,
-        Err(e) => {
-            return MutationResult {
-                mutation_id: mutation.id.clone(),
-                file: mutation.file.clone(),
-                function: mutation.function.clone(),
-                original: mutation.original.clone(),
-                replacement: mutation.replacement.clone(),
-                status: MutationStatus::ConfigError(e.to_string()),
-                duration: start.elapsed(),
-                line: None,
-                details: Some(e.to_string()),
+    // A prebuilt binary still runs the original code of files compiled into it
+    if let Some(binary) = &settings.test_binary {
+        let binary = project_dir.join(binary);
+        let compiled_from = binary_dependencies(&binary).unwrap_or_default();
+        if let Some((_, file_path, _, _)) = prepared
+            .iter()
+            .find(|(_, file_path, _, _)| is_dependency(&compiled_from, file_path))
+        {
+            let error = MutationError::RecompilationRequired {
+                file: file_path.clone(),
+                binary,
             };
+            return failed(error, line, column, None);
         }
-    };
-
-    let line = Some(prepared.site.line);
+    }
 
-    // Write the mutated file
-    if let Err(e) = std::fs::write(&file_path, &prepared.mutated_source) {
-        return MutationResult {
-            mutation_id: mutation.id.clone(),
-            file: mutation.file.clone(),
-            function: mutation.function.clone(),
-            original: mutation.original.clone(),
-            replacement: mutation.replacement.clone(),
-            status: MutationStatus::ConfigError(format!("Failed to write mutated file: {}", e)),
-            duration: start.elapsed(),
-            line,
-            details: None,
+    let mut guards = Vec::new();
+    for (file, file_path, original_content, mutant) in &prepared {
+        // Back up the original in case the process is killed while it is mutated
+        let backup = match write_backup(project_dir, file, original_content) {
+            Ok(backup) => backup,
+            Err(e) => return failed(e, line, column, None),
         };
+
+        // Write the mutated file; the original is restored when the guard
+        // drops, which also undoes files already mutated if a later one
+        // fails. With `no_restore` there is no guard and the backup is kept
+        // for `clean`.
+        if !settings.no_restore {
+            guards.push(RestoreGuard {
+                path: file_path,
+                content: original_content,
+                crate_dir: project_dir,
+                backup: Some(backup),
+            });
+        }
+        if let Err(e) = std::fs::write(file_path, &mutant.source) {
+            let error = MutationError::WriteError {
+                file: file_path.to_path_buf(),
+                error: e.to_string(),
+            };
+            return failed(error, line, column, None);
+        }
     }
 
     // Run tests
-    let test_result = run_cargo_test(project_dir, timeout_secs, verbose);
-
-    // Restore original file
-    if let Err(e) = std::fs::write(&file_path, &original_content) {
-        eprintln!("WARNING: Failed to restore original file: {}", e);
+    let env = mutation.env.as_ref();
+    let timeout = settings.timeout_for(mutation);
+    let run_tests = |env: Option<&HashMap<String, String>>| {
+        if let Some(binary) = &settings.test_binary {
+            run_test_binary(project_dir, binary, settings, env, timeout, verbose)
+        } else if targets.is_empty() {
+            let runs = test_kind_args(&settings.test_kinds, settings.lib_target.unwrap_or(true));
+            match runs.as_slice() {
+                [args] => run_cargo_test(project_dir, settings, args, env, timeout, verbose),
+                _ => combine_results(
+                    runs.iter()
+                        .map(|args| {
+                            let result =
+                                run_cargo_test(project_dir, settings, args, env, timeout, verbose);
+                            (args.join(" "), result)
+                        })
+                        .collect(),
+                ),
+            }
+        } else {
+            run_targets_in_parallel(project_dir, settings, targets, env, timeout, verbose)
+        }
+    };
+    let mut test_result = run_tests(env);
+    if settings.clean_build_retry {
+        test_result = retry_compile_error(test_result, || {
+            if verbose {
+                eprintln!("  Compile error; retrying without incremental compilation");
+            }
+            let mut env = env.cloned().unwrap_or_default();
+            env.insert("CARGO_INCREMENTAL".to_string(), "0".to_string());
+            run_tests(Some(&env))
+        });
     }
 
+    // Restore original files
+    drop(guards);
+
     let duration = start.elapsed();
 
     let (status, details) = match test_result {
         TestResult::Passed => (MutationStatus::Survived, None),
         TestResult::Failed(output) => (MutationStatus::Killed, Some(output)),
+        TestResult::Crashed(output) => (MutationStatus::Crashed, Some(output)),
         TestResult::CompileError(output) => (MutationStatus::CompileError, Some(output)),
         TestResult::Timeout => (MutationStatus::Timeout, None),
-        TestResult::Error(e) => (MutationStatus::ConfigError(e.clone()), Some(e)),
+        TestResult::UnexpectedExit(code, output) => {
+            let error = MutationError::UnexpectedExitCode { code };
+            (MutationStatus::ConfigError(Box::new(error)), Some(output))
+        }
+        TestResult::Error(e) => {
+            let error = MutationError::TestExecutionError { error: e.clone() };
+            (MutationStatus::ConfigError(Box::new(error)), Some(e))
+        }
+    };
+    let (status, details) = match (&status, &details, &mutation.killed_by) {
+        (MutationStatus::Killed | MutationStatus::Crashed, Some(output), Some(killed_by)) => {
+            match unexpected_kill(killed_by, output) {
+                Some(note) => (MutationStatus::Survived, Some(note)),
+                None => (status, details),
+            }
+        }
+        _ => (status, details),
     };
 
+    // Keep the full output in the log, and only its start in the report
+    let log = details.as_ref().and_then(|output| {
+        let dir = settings.output_dir.as_ref()?;
+        match write_log(dir, &mutation.id, output) {
+            Ok(path) => Some(path),
+            Err(e) => {
+                eprintln!("WARNING: Failed to write log for {}: {}", mutation.id, e);
+                None
+            }
+        }
+    });
+    let killed_by_test = match (&status, &details) {
+        (MutationStatus::Killed | MutationStatus::Crashed, Some(output)) => {
+            first_failing_test(output)
+        }
+        _ => None,
+    };
+    let details =
+        details.map(|output| truncate_details(&output, settings.details_lines, log.as_deref()));
+
     MutationResult {
         mutation_id: mutation.id.clone(),
         file: mutation.file.clone(),
@@ -187,78 +826,1579 @@ fn run_single_mutation(
         status,
         duration,
         line,
+        column,
+        start_byte,
+        end_byte,
         details,
+        killed_by_test,
+        started_at: None,
+        finished_at: None,
+        left_mutated: settings.no_restore,
     }
 }
 
+/// Keep the first `max_lines` lines of `details`, ending with a marker saying
+/// how many were cut and where the full log is, if it was written
+///
+/// `max_lines` 0 keeps everything.
+pub fn truncate_details(details: &str, max_lines: usize, log: Option<&Path>) -> String {
+    let total = details.lines().count();
+    if max_lines == 0 || total <= max_lines {
+        return details.to_string();
+    }
+
+    let mut truncated: String = details
+        .lines()
+        .take(max_lines)
+        .flat_map(|line| [line, "\n"])
+        .collect();
+    let cut = total - max_lines;
+    let unit = if cut == 1 { "line" } else { "lines" };
+    match log {
+        Some(log) => truncated.push_str(&format!(
+            "...(truncated, {} more {}; full log in {})",
+            cut,
+            unit,
+            log.display()
+        )),
+        None => truncated.push_str(&format!("...(truncated, {} more {})", cut, unit)),
+    }
+    truncated
+}
+
+/// Write a mutation's full output to `<dir>/<id>.log`
+///
+/// Characters that aren't safe in file names, like the `/` in ids of
+/// mutations expanded from globs, are replaced with `_`.
+fn write_log(dir: &Path, id: &str, output: &str) -> std::io::Result<PathBuf> {
+    std::fs::create_dir_all(dir)?;
+    let name: String = id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | '@') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let path = dir.join(format!("{}.log", name));
+    std::fs::write(&path, output)?;
+    Ok(path)
+}
+
+/// Run the tests again with `rerun` if they failed to compile
+///
+/// rustc's incremental cache occasionally reports a bogus compile error after
+/// files were rewritten in quick succession, which goes away with a clean
+/// build. The compile error stands only if it reproduces.
+fn retry_compile_error(result: TestResult, rerun: impl FnOnce() -> TestResult) -> TestResult {
+    match result {
+        TestResult::CompileError(_) => rerun(),
+        other => other,
+    }
+}
+
+/// Names of the tests that failed in `cargo test` output
+///
+/// Reads libtest's `---- <name> stdout ----` headers, which are printed with
+/// and without `--quiet`, and `test <name> ... FAILED` lines.
+pub fn failing_tests(output: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for line in output.lines() {
+        let name = line
+            .strip_prefix("---- ")
+            .and_then(|rest| rest.strip_suffix(" stdout ----"))
+            .or_else(|| {
+                line.strip_prefix("test ")
+                    .and_then(|rest| rest.strip_suffix(" ... FAILED"))
+            });
+        if let Some(name) = name {
+            if !names.iter().any(|n| n == name) {
+                names.push(name.to_string());
+            }
+        }
+    }
+    names
+}
+
+/// The first test that failed in `cargo test` output, to show which test
+/// caught a mutant
+pub fn first_failing_test(output: &str) -> Option<String> {
+    failing_tests(output).into_iter().next()
+}
+
+/// A note explaining why a kill doesn't count, if none of the `killed_by`
+/// tests are among those that failed
+fn unexpected_kill(killed_by: &[String], output: &str) -> Option<String> {
+    let failed = failing_tests(output);
+    let matches = |wanted: &String| {
+        failed
+            .iter()
+            .any(|name| name == wanted || name.ends_with(&format!("::{}", wanted)))
+    };
+    if killed_by.iter().any(matches) {
+        return None;
+    }
+    Some(format!(
+        "tests failed, but none of killed_by ({}); failed: {}",
+        killed_by.join(", "),
+        if failed.is_empty() {
+            "unknown".to_string()
+        } else {
+            failed.join(", ")
+        }
+    ))
+}
+
 enum TestResult {
     Passed,
     Failed(String),
+    Crashed(String),
     CompileError(String),
     Timeout,
+    /// Failed with an exit code that isn't in `settings.kill_on_exit_codes`
+    UnexpectedExit(Option<i32>, String),
     Error(String),
 }
 
-/// Run cargo test and return the result
-fn run_cargo_test(project_dir: &Path, timeout_secs: u64, verbose: bool) -> TestResult {
+/// Arguments for a `cargo test` run, starting with `+<toolchain>` if one is
+/// pinned
+///
+/// Arguments for the test binaries, like `--include-ignored`, come last, after
+/// a `--` separator.
+pub fn cargo_test_args(settings: &Settings, target_args: &[String], verbose: bool) -> Vec<String> {
+    let mut args = Vec::new();
+    if let Some(toolchain) = &settings.toolchain {
+        args.push(toolchain_arg(toolchain));
+    }
+    args.push("test".to_string());
+    args.extend(target_args.iter().cloned());
+    args.push("--no-fail-fast".to_string());
+    if !verbose {
+        args.push("--quiet".to_string());
+    }
+    if settings.include_ignored {
+        args.push("--".to_string());
+        args.push("--include-ignored".to_string());
+    }
+    args
+}
+
+/// Target selection arguments for each `cargo test` run needed to run the
+/// selected kinds of tests
+///
+/// Cargo refuses to mix `--doc` with other target selection, so doctests get
+/// a run of their own. With no kinds selected there is one run of everything.
+/// Without a library (`lib` is false), `--lib` and `--doc` would fail the run
+/// with "no library targets found", so they are left out.
+pub fn test_kind_args(kinds: &[TestKind], lib: bool) -> Vec<Vec<String>> {
+    let mut targets = Vec::new();
+    if kinds.contains(&TestKind::Unit) {
+        if lib {
+            targets.push("--lib".to_string());
+        }
+        targets.push("--bins".to_string());
+    }
+    if kinds.contains(&TestKind::Integration) {
+        targets.extend(["--test", "*"].map(String::from));
+    }
+
+    let mut runs = Vec::new();
+    if !targets.is_empty() || kinds.is_empty() {
+        runs.push(targets);
+    }
+    if lib && kinds.contains(&TestKind::Doc) {
+        runs.push(vec!["--doc".to_string()]);
+    }
+    runs
+}
+
+/// Environment variables that change what `cargo test` builds, shown with the
+/// command in verbose mode
+const INHERITED_ENV: &[&str] = &[
+    "CARGO_TARGET_DIR",
+    "CARGO_BUILD_TARGET",
+    "RUSTFLAGS",
+    "RUSTDOCFLAGS",
+    "RUSTUP_TOOLCHAIN",
+    "CARGO_INCREMENTAL",
+];
+
+/// A command as a copy-pastable shell line: `cd <dir> && [VAR=value...] program args...`
+///
+/// `env` lists inherited variables to show in addition to those set on `cmd`.
+fn format_command(cmd: &Command, env: &[(String, String)]) -> String {
+    let mut parts = Vec::new();
+    if let Some(dir) = cmd.get_current_dir() {
+        parts.push(format!("cd {} &&", shell_quote(&dir.to_string_lossy())));
+    }
+    let explicit = cmd
+        .get_envs()
+        .filter_map(|(name, value)| Some((name.to_string_lossy().to_string(), value?)))
+        .map(|(name, value)| (name, value.to_string_lossy().to_string()));
+    for (name, value) in env.iter().cloned().chain(explicit) {
+        parts.push(format!("{}={}", name, shell_quote(&value)));
+    }
+    parts.push(shell_quote(&cmd.get_program().to_string_lossy()));
+    parts.extend(cmd.get_args().map(|arg| shell_quote(&arg.to_string_lossy())));
+    parts.join(" ")
+}
+
+/// Quote `arg` for a POSIX shell if it contains anything but safe characters
+fn shell_quote(arg: &str) -> String {
+    let safe = |c: char| c.is_ascii_alphanumeric() || "-_+=./:,@%".contains(c);
+    if !arg.is_empty() && arg.chars().all(safe) {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/// The `+<toolchain>` argument selecting a rustup toolchain
+fn toolchain_arg(toolchain: &str) -> String {
+    format!("+{}", toolchain.trim_start_matches('+'))
+}
+
+/// The `cargo test` command for a mutation, with its `env` on top of the
+/// inherited environment
+fn cargo_test_command(
+    project_dir: &Path,
+    settings: &Settings,
+    target_args: &[String],
+    env: Option<&HashMap<String, String>>,
+    verbose: bool,
+) -> Command {
     let mut cmd = Command::new("cargo");
-    cmd.arg("test")
-        .arg("--no-fail-fast")
+    cmd.args(cargo_test_args(settings, target_args, verbose))
         .current_dir(project_dir);
+    if let Some(env) = env {
+        cmd.envs(env);
+    }
+    cmd
+}
 
-    if !verbose {
-        cmd.arg("--quiet");
+/// Run cargo test and return the result, killing it after `timeout`
+///
+/// The tests are built first with `--no-run`, so `timeout` only covers running
+/// them and a slow build of the mutant doesn't count as a timeout.
+fn run_cargo_test(
+    project_dir: &Path,
+    settings: &Settings,
+    target_args: &[String],
+    env: Option<&HashMap<String, String>>,
+    timeout: Duration,
+    verbose: bool,
+) -> TestResult {
+    let mut build_args = target_args.to_vec();
+    build_args.push("--no-run".to_string());
+    let build = cargo_test_command(project_dir, settings, &build_args, env, verbose);
+    match run_test_process(build, "cargo test --no-run", settings, None, verbose) {
+        TestResult::Passed => {}
+        result => return result,
     }
 
-    // TODO: Implement proper timeout handling
-    let _timeout = Duration::from_secs(timeout_secs);
+    let cmd = cargo_test_command(project_dir, settings, target_args, env, verbose);
+    run_test_process(cmd, "cargo test", settings, Some(timeout), verbose)
+}
 
-    match cmd.output() {
-        Ok(output) => {
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            let combined = format!("{}\n{}", stdout, stderr);
+/// Run the prebuilt `settings.test_binary` in place of `cargo test`
+fn run_test_binary(
+    project_dir: &Path,
+    binary: &Path,
+    settings: &Settings,
+    env: Option<&HashMap<String, String>>,
+    timeout: Duration,
+    verbose: bool,
+) -> TestResult {
+    let mut cmd = Command::new(project_dir.join(binary));
+    cmd.current_dir(project_dir);
+    if settings.include_ignored {
+        cmd.arg("--include-ignored");
+    }
+    if let Some(env) = env {
+        cmd.envs(env);
+    }
+    run_test_process(cmd, "the test binary", settings, Some(timeout), verbose)
+}
 
-            if output.status.success() {
-                TestResult::Passed
-            } else {
-                // Check if it's a compile error
-                if stderr.contains("error[E")
-                    || stderr.contains("could not compile")
-                    || stderr.contains("aborting due to")
-                {
-                    TestResult::CompileError(combined)
-                } else {
-                    TestResult::Failed(combined)
+/// Run a test command, killing it after `timeout` if one is given, and
+/// classify its outcome
+fn run_test_process(
+    mut cmd: Command,
+    name: &str,
+    settings: &Settings,
+    timeout: Option<Duration>,
+    verbose: bool,
+) -> TestResult {
+    if verbose {
+        let env: Vec<(String, String)> = INHERITED_ENV
+            .iter()
+            .filter_map(|name| Some((name.to_string(), std::env::var(name).ok()?)))
+            .collect();
+        eprintln!("  $ {}", format_command(&cmd, &env));
+    }
+
+    cmd.stdout(Stdio::piped()).stderr(Stdio::piped());
+    // In its own process group, so a timeout also kills the test binaries
+    // cargo started
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut cmd, 0);
+    let retries = settings.test_command_retries;
+    let backoff = Duration::from_millis(settings.retry_backoff_ms);
+    let mut child = match retry_spawn(retries, backoff, verbose, || cmd.spawn()) {
+        Ok(child) => child,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            let program = Path::new(cmd.get_program());
+            let on_path = if program.components().count() > 1 { "" } else { " on PATH" };
+            return TestResult::Error(format!("`{}` not found{}", program.display(), on_path));
+        }
+        Err(e) if retries > 0 => {
+            return TestResult::Error(format!(
+                "Failed to run {} (retried {} times): {}",
+                name, retries, e
+            ))
+        }
+        Err(e) => return TestResult::Error(format!("Failed to run {}: {}", name, e)),
+    };
+
+    // Read the pipes while waiting, so a chatty test can't fill them and block
+    let stdout = read_in_background(child.stdout.take());
+    let stderr = read_in_background(child.stderr.take());
+    let waited = wait_with_timeout(&mut child, timeout);
+    // Every process holding the pipes is gone by now
+    let stdout = stdout.join().unwrap_or_default();
+    let stderr = stderr.join().unwrap_or_default();
+    match waited {
+        Ok(Some(status)) => {
+            let result = classify_output(status.success(), &stdout, &stderr);
+            let result = detect_crash(result, &status, &stderr);
+            apply_kill_criterion(result, status.code(), &settings.kill_on_exit_codes)
+        }
+        Ok(None) => TestResult::Timeout,
+        Err(e) => TestResult::Error(format!("Failed to wait for {}: {}", name, e)),
+    }
+}
+
+/// Start a test command with `spawn`, retrying up to `retries` times if it
+/// fails with an I/O error
+///
+/// The first retry waits `backoff`, and every further one twice as long as
+/// the last. A missing program isn't retried, since it won't appear by
+/// itself. This only covers starting the command: test outcomes are never
+/// retried.
+fn retry_spawn<T>(
+    retries: u32,
+    backoff: Duration,
+    verbose: bool,
+    mut spawn: impl FnMut() -> std::io::Result<T>,
+) -> std::io::Result<T> {
+    let mut delay = backoff;
+    for attempt in 1..=retries {
+        match spawn() {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                if verbose {
+                    eprintln!(
+                        "  Failed to start the tests ({}); retry {}/{} in {:?}",
+                        e, attempt, retries, delay
+                    );
                 }
+                std::thread::sleep(delay);
+                delay = delay.saturating_mul(2);
             }
+            result => return result,
         }
-        Err(e) => {
-            if e.kind() == std::io::ErrorKind::TimedOut {
-                TestResult::Timeout
-            } else {
-                TestResult::Error(format!("Failed to run cargo test: {}", e))
+    }
+    spawn()
+}
+
+/// The source files a binary was compiled from, as listed in the dep-info
+/// file rustc writes next to it (`<binary>.d`)
+///
+/// Returns `None` if there is no dep-info, e.g. for a binary cargo didn't
+/// build.
+pub fn binary_dependencies(binary: &Path) -> Option<Vec<PathBuf>> {
+    let dep_info = std::fs::read_to_string(binary.with_extension("d")).ok()?;
+    // The first rule names the binary's prerequisites; spaces in paths are
+    // escaped
+    let (_, prerequisites) = dep_info.lines().next()?.split_once(": ")?;
+    let prerequisites = prerequisites.replace("\\ ", "\0");
+    Some(
+        prerequisites
+            .split_whitespace()
+            .map(|path| PathBuf::from(path.replace('\0', " ")))
+            .collect(),
+    )
+}
+
+/// Whether `file` is among a binary's dependencies
+///
+/// Relative dependencies are relative to the directory rustc ran in, which
+/// isn't recorded, so they match any `file` they are a suffix of.
+fn is_dependency(dependencies: &[PathBuf], file: &Path) -> bool {
+    let file = file.canonicalize().unwrap_or_else(|_| file.to_path_buf());
+    dependencies.iter().any(|dependency| {
+        if dependency.is_relative() {
+            file.ends_with(dependency)
+        } else {
+            dependency.canonicalize().is_ok_and(|dependency| dependency == file)
+        }
+    })
+}
+
+/// Read a child's output pipe to the end on a separate thread
+fn read_in_background(
+    pipe: Option<impl std::io::Read + Send + 'static>,
+) -> std::thread::JoinHandle<String> {
+    std::thread::spawn(move || {
+        let mut bytes = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut bytes);
+        }
+        String::from_utf8_lossy(&bytes).into_owned()
+    })
+}
+
+/// Wait for `child` to exit, killing it and every process it started once
+/// `timeout` has passed
+///
+/// Without a `timeout` it waits as long as it takes. Returns `None` if it was
+/// killed. On error, the processes are killed too.
+fn wait_with_timeout(
+    child: &mut Child,
+    timeout: Option<Duration>,
+) -> std::io::Result<Option<ExitStatus>> {
+    let deadline = timeout.and_then(|timeout| Instant::now().checked_add(timeout));
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => return Ok(Some(status)),
+            Ok(None) if deadline.is_none_or(|deadline| Instant::now() < deadline) => {}
+            Ok(None) => {
+                kill_process_tree(child);
+                return Ok(None);
+            }
+            Err(e) => {
+                kill_process_tree(child);
+                return Err(e);
             }
         }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+/// Kill `child` and the processes it started, such as the test binaries
+/// `cargo test` runs
+///
+/// On Unix, `child` leads its own process group (see [`run_test_process`]),
+/// which is killed as a whole.
+fn kill_process_tree(child: &mut Child) {
+    #[cfg(unix)]
+    if let Ok(group) = libc::pid_t::try_from(child.id()) {
+        // SAFETY: `kill` has no memory-safety preconditions; a negative pid
+        // signals the group, which only contains processes of this test run
+        unsafe {
+            libc::kill(-group, libc::SIGKILL);
+        }
+    }
+    #[cfg(windows)]
+    let _ = Command::new("taskkill")
+        .args(["/T", "/F", "/PID", &child.id().to_string()])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// Run each test target as a separate, concurrent `cargo test` invocation
+fn run_targets_in_parallel(
+    project_dir: &Path,
+    settings: &Settings,
+    targets: &[TestTarget],
+    env: Option<&HashMap<String, String>>,
+    timeout: Duration,
+    verbose: bool,
+) -> TestResult {
+    let results = std::thread::scope(|scope| {
+        let handles: Vec<_> = targets
+            .iter()
+            .map(|target| {
+                scope.spawn(move || {
+                    let args = target.cargo_args();
+                    let result = run_cargo_test(project_dir, settings, &args, env, timeout, verbose);
+                    (target.label(), result)
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle.join().unwrap_or_else(|_| {
+                    let error = TestResult::Error("test thread panicked".to_string());
+                    ("unknown target".to_string(), error)
+                })
+            })
+            .collect::<Vec<_>>()
+    });
+
+    combine_results(results)
+}
+
+/// Combine per-target results: the mutant is killed if any target fails
+fn combine_results(results: Vec<(String, TestResult)>) -> TestResult {
+    let mut details = String::new();
+    let (mut compile_error, mut crashed, mut failed, mut timeout) = (false, false, false, false);
+    let mut error = None;
+    let mut unexpected_exit = None;
+
+    for (label, result) in results {
+        let output = match result {
+            TestResult::Passed => continue,
+            TestResult::Failed(output) => {
+                failed = true;
+                output
+            }
+            TestResult::Crashed(output) => {
+                crashed = true;
+                output
+            }
+            TestResult::CompileError(output) => {
+                compile_error = true;
+                output
+            }
+            TestResult::Timeout => {
+                timeout = true;
+                "timed out".to_string()
+            }
+            TestResult::UnexpectedExit(code, output) => {
+                unexpected_exit.get_or_insert(code);
+                output
+            }
+            TestResult::Error(e) => {
+                error.get_or_insert_with(|| e.clone());
+                e
+            }
+        };
+        details.push_str(&format!("=== {} ===\n{}\n", label, output));
+    }
+
+    if compile_error {
+        TestResult::CompileError(details)
+    } else if crashed {
+        TestResult::Crashed(details)
+    } else if failed {
+        TestResult::Failed(details)
+    } else if timeout {
+        TestResult::Timeout
+    } else if let Some(e) = error {
+        TestResult::Error(e)
+    } else if let Some(code) = unexpected_exit {
+        TestResult::UnexpectedExit(code, details)
+    } else {
+        TestResult::Passed
+    }
+}
+
+/// Classify the outcome of a `cargo test` invocation from its output
+fn classify_output(success: bool, stdout: &str, stderr: &str) -> TestResult {
+    if success {
+        return TestResult::Passed;
+    }
+
+    let combined = format!("{}\n{}", stdout, stderr);
+    if is_compile_error(stdout, stderr) {
+        TestResult::CompileError(combined)
+    } else {
+        TestResult::Failed(combined)
+    }
+}
+
+/// Mark a failed run as a crash if cargo or a test binary it ran was killed by
+/// a signal
+///
+/// A crashing test binary doesn't make cargo itself die by a signal; cargo
+/// reports it as `process didn't exit successfully: ... (signal: 11, SIGSEGV:
+/// ...)` and exits with 101, so its output is checked too.
+fn detect_crash(result: TestResult, status: &ExitStatus, stderr: &str) -> TestResult {
+    #[cfg(unix)]
+    let signaled = std::os::unix::process::ExitStatusExt::signal(status).is_some();
+    #[cfg(not(unix))]
+    let signaled = {
+        let _ = status;
+        false
+    };
+    match result {
+        TestResult::Failed(output)
+            if signaled
+                || stderr.lines().any(|line| {
+                    line.contains("process didn't exit successfully") && line.contains("(signal: ")
+                }) =>
+        {
+            TestResult::Crashed(output)
+        }
+        result => result,
+    }
+}
+
+/// Only count a failed run as a kill if it exited with one of
+/// `kill_on_exit_codes`
+///
+/// Other exits, including failures without an exit code, are reported as
+/// such rather than as kills.
+/// Crashes are recognized before this and always kill. With no codes
+/// configured, every failure is a kill.
+fn apply_kill_criterion(
+    result: TestResult,
+    code: Option<i32>,
+    kill_on_exit_codes: &[i32],
+) -> TestResult {
+    match result {
+        TestResult::Failed(output)
+            if !kill_on_exit_codes.is_empty()
+                && !code.is_some_and(|code| kill_on_exit_codes.contains(&code)) =>
+        {
+            TestResult::UnexpectedExit(code, output)
+        }
+        result => result,
+    }
+}
+
+/// Check whether a failed `cargo test` run failed during the build phase
+///
+/// Cargo builds every test target before running any of them, so if a test
+/// harness reported a result the build succeeded. Otherwise only diagnostics
+/// starting at column 0 count; test output that merely mentions `error[E`
+/// (e.g. a test checking compiler messages) is indented or prefixed.
+fn is_compile_error(stdout: &str, stderr: &str) -> bool {
+    let tests_ran = stdout
+        .lines()
+        .chain(stderr.lines())
+        .any(|line| line.starts_with("test result:"));
+    if tests_ran {
+        return false;
+    }
+
+    stderr.lines().any(|line| {
+        line.starts_with("error[E")
+            || line.starts_with("error: could not compile")
+            || line.starts_with("error: aborting due to")
+    })
+}
+
+/// Check that `cargo` can be run, so a missing toolchain fails the run once
+/// up front instead of as an error on every mutation
+///
+/// With a pinned `toolchain`, also checks that rustup has it installed.
+pub fn check_cargo(toolchain: Option<&str>) -> Result<()> {
+    check_program("cargo")?;
+    let Some(toolchain) = toolchain else {
+        return Ok(());
+    };
+    let output = Command::new("cargo")
+        .args([toolchain_arg(toolchain), "--version".to_string()])
+        .output()
+        .map_err(|e| MutationError::TestExecutionError {
+            error: format!("Failed to run cargo: {}", e),
+        })?;
+    if !output.status.success() {
+        return Err(MutationError::TestExecutionError {
+            error: format!(
+                "Toolchain '{}' is not available: {}",
+                toolchain,
+                String::from_utf8_lossy(&output.stderr).lines().next().unwrap_or("").trim()
+            ),
+        });
+    }
+    Ok(())
+}
+
+/// `cargo --version` for the given toolchain, if cargo can be run
+pub fn cargo_version(toolchain: Option<&str>) -> Option<String> {
+    let mut cmd = Command::new("cargo");
+    if let Some(toolchain) = toolchain {
+        cmd.arg(toolchain_arg(toolchain));
+    }
+    let output = cmd.arg("--version").output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn check_program(program: &str) -> Result<()> {
+    match Command::new(program).arg("--version").output() {
+        Ok(_) => Ok(()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            Err(MutationError::CommandNotFound {
+                program: program.to_string(),
+            })
+        }
+        Err(e) => Err(MutationError::TestExecutionError {
+            error: format!("Failed to run {}: {}", program, e),
+        }),
     }
 }
 
 /// Validate all mutations without running tests
+///
+/// There is one result per configured mutation; one whose `file` is a glob is
+/// valid if it is for every file it expands to.
 pub fn validate_mutations(config: &Config, project_dir: &Path) -> Vec<Result<()>> {
+    let crate_dir = &config.settings.crate_dir(project_dir);
     config
         .mutations
         .iter()
         .map(|mutation| {
-            let file_path = project_dir.join(&mutation.file);
+            config
+                .expand_file_glob(mutation, project_dir)?
+                .iter()
+                .try_for_each(|mutation| validate_mutation(config, crate_dir, mutation))
+        })
+        .collect()
+}
+
+/// Validate one mutation whose `file` isn't a glob
+fn validate_mutation(config: &Config, crate_dir: &Path, mutation: &MutationConfig) -> Result<()> {
+    let file_path = crate_dir.join(&mutation.file);
+
+    if !file_path.exists() {
+        return Err(MutationError::FileNotFound {
+            file: mutation.file.clone(),
+        });
+    }
+
+    // Try to prepare the mutation (this validates everything)
+    match apply_mutation_to_file(&file_path, mutation) {
+        Ok(_) => Ok(()),
+        Err(e @ MutationError::NoMatch { .. }) if config.settings.loose => {
+            let near = std::fs::read_to_string(&file_path)
+                .ok()
+                .and_then(|source| near_match_error(&source, mutation));
+            Err(near.unwrap_or(e))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// A mutation's validation result, as written by `validate --format json`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Validation {
+    pub mutation_id: String,
+    pub file: PathBuf,
+    pub function: String,
+    pub original: String,
+    pub replacement: String,
+    pub valid: bool,
+    /// Why the mutation is invalid
+    pub error: Option<String>,
+}
+
+impl Validation {
+    pub fn new(mutation: &MutationConfig, result: &Result<()>) -> Self {
+        Validation {
+            mutation_id: mutation.id.clone(),
+            file: mutation.file.clone(),
+            function: mutation.function.clone(),
+            original: mutation.original.clone(),
+            replacement: mutation.replacement.clone(),
+            valid: result.is_ok(),
+            error: result.as_ref().err().map(|e| e.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::OsStr;
+
+    #[test]
+    fn test_classify_compile_error() {
+        let stderr = "   Compiling demo v0.1.0\n\
+error[E0308]: mismatched types\n \
+--> src/lib.rs:3:5\n\
+error: could not compile `demo` (lib) due to 1 previous error\n";
+        assert!(matches!(
+            classify_output(false, "", stderr),
+            TestResult::CompileError(_)
+        ));
+    }
+
+    #[test]
+    fn test_classify_test_output_mentioning_error_code() {
+        // A failing test whose assertion message contains compiler-like text
+        let stdout = "\nrunning 2 tests\n\
+test tests::reports_error ... FAILED\n\
+\n\
+---- tests::reports_error stdout ----\n\
+error[E0308]: mismatched types\n\
+error: could not compile `fixture`\n\
+\n\
+test result: FAILED. 1 passed; 1 failed; 0 ignored\n";
+        let stderr = "error: test failed, to rerun pass `--lib`\n";
+        assert!(matches!(
+            classify_output(false, stdout, stderr),
+            TestResult::Failed(_)
+        ));
+    }
+
+    #[test]
+    fn test_classify_indented_error_code_in_stderr() {
+        let stderr = "thread 'main' panicked: expected `error[E0308]`\n\
+error: test failed, to rerun pass `--lib`\n";
+        assert!(matches!(
+            classify_output(false, "", stderr),
+            TestResult::Failed(_)
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_detect_crash() {
+        use std::os::unix::process::ExitStatusExt;
+
+        let crashed = |result: TestResult| matches!(result, TestResult::Crashed(_));
+        let failed = || TestResult::Failed("test result: FAILED".to_string());
+        // Raw wait statuses: killed by SIGSEGV, and exited with 101
+        let signaled = ExitStatus::from_raw(11);
+        let exited = ExitStatus::from_raw(101 << 8);
+
+        assert!(crashed(detect_crash(failed(), &signaled, "")));
+        assert!(!crashed(detect_crash(failed(), &exited, "")));
+        // Cargo survives a crashing test binary and reports the signal
+        let stderr = "error: test failed, to rerun pass `--lib`\n\n\
+Caused by:\n  process didn't exit successfully: `/work/target/debug/deps/demo-1a2b` \
+(signal: 11, SIGSEGV: invalid memory reference)\n";
+        assert!(crashed(detect_crash(failed(), &exited, stderr)));
+        // Only failures are reinterpreted
+        assert!(matches!(
+            detect_crash(TestResult::Passed, &signaled, ""),
+            TestResult::Passed
+        ));
+
+        let report = crate::report::MutationReport::new(vec![MutationResult {
+            status: MutationStatus::Crashed,
+            ..not_run_result(&MutationConfig::default())
+        }]);
+        assert_eq!((report.killed(), report.crashed()), (1, 1));
+        assert_eq!(report.score(), 100.0);
+    }
+
+    #[test]
+    fn test_kill_on_exit_codes() {
+        let status = |result: TestResult| match result {
+            TestResult::Passed => "passed",
+            TestResult::Failed(_) | TestResult::Crashed(_) => "failed",
+            TestResult::CompileError(_) => "compile_error",
+            TestResult::Timeout => "timeout",
+            TestResult::UnexpectedExit(..) => "unexpected_exit",
+            TestResult::Error(_) => "error",
+        };
+        let failed = || TestResult::Failed("test result: FAILED".to_string());
+
+        // By default any failure kills
+        assert_eq!(status(apply_kill_criterion(failed(), Some(2), &[])), "failed");
+        assert_eq!(status(apply_kill_criterion(failed(), None, &[])), "failed");
+
+        let codes = [101];
+        assert_eq!(status(apply_kill_criterion(failed(), Some(101), &codes)), "failed");
+        assert_eq!(status(apply_kill_criterion(failed(), Some(2), &codes)), "unexpected_exit");
+        assert_eq!(status(apply_kill_criterion(failed(), None, &codes)), "unexpected_exit");
+        // Only failures are reinterpreted
+        assert_eq!(status(apply_kill_criterion(TestResult::Passed, Some(0), &codes)), "passed");
+        let compile_error = TestResult::CompileError("error[E0308]".to_string());
+        assert_eq!(status(apply_kill_criterion(compile_error, Some(101), &codes)), "compile_error");
+    }
+
+    #[test]
+    fn test_failing_tests() {
+        let stdout = "\nrunning 3 tests\n\
+test tests::test_add ... ok\n\
+test tests::test_sub ... FAILED\n\
+test flaky::test_network ... FAILED\n\
+\n\
+failures:\n\
+\n\
+---- tests::test_sub stdout ----\n\
+assertion failed\n\
+\n\
+---- flaky::test_network stdout ----\n\
+connection refused\n\
+\n\
+test result: FAILED. 1 passed; 2 failed; 0 ignored\n";
+        assert_eq!(failing_tests(stdout), ["tests::test_sub", "flaky::test_network"]);
+
+        // With --quiet only the stdout headers name the failures
+        let quiet = "running 2 tests\n.F\nfailures:\n\n---- it::works stdout ----\n";
+        assert_eq!(failing_tests(quiet), ["it::works"]);
 
-            if !file_path.exists() {
-                return Err(MutationError::FileNotFound {
-                    file: mutation.file.clone(),
-                });
+        // Allowlisted names match whole trailing path segments
+        let wanted = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+        assert_eq!(unexpected_kill(&wanted(&["test_sub"]), stdout), None);
+        assert_eq!(unexpected_kill(&wanted(&["tests::test_sub"]), stdout), None);
+        assert_eq!(
+            unexpected_kill(&wanted(&["sub"]), stdout).unwrap(),
+            "tests failed, but none of killed_by (sub); failed: tests::test_sub, flaky::test_network"
+        );
+    }
+
+    #[test]
+    fn test_first_failing_test() {
+        let stdout = "\nrunning 2 tests\n\
+test auth::tests::accepts_valid ... ok\n\
+test auth::tests::rejects_expired ... FAILED\n\
+\n\
+failures:\n\
+\n\
+---- auth::tests::rejects_expired stdout ----\n\
+thread 'auth::tests::rejects_expired' panicked at src/auth.rs:40:9\n\
+\n\
+test result: FAILED. 1 passed; 1 failed; 0 ignored\n";
+        assert_eq!(
+            first_failing_test(stdout).as_deref(),
+            Some("auth::tests::rejects_expired")
+        );
+
+        // Doctests, labelled by combined per-target output
+        let doc = "=== core --doc ===\n\
+test src/lib.rs - add (line 3) ... FAILED\n";
+        assert_eq!(first_failing_test(doc).as_deref(), Some("src/lib.rs - add (line 3)"));
+
+        assert_eq!(first_failing_test("test result: ok. 2 passed\n"), None);
+    }
+
+    #[test]
+    fn test_catch_panic() {
+        assert_eq!(catch_panic(|| 42), Ok(42));
+        assert_eq!(
+            catch_panic::<()>(|| panic!("exotic AST")),
+            Err("exotic AST".to_string())
+        );
+        let index = 3;
+        assert_eq!(
+            catch_panic::<()>(|| panic!("bad index {}", index)),
+            Err("bad index 3".to_string())
+        );
+    }
+
+    #[test]
+    fn test_restore_guard_restores_on_panic() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("lib.rs");
+        std::fs::write(&path, "original").unwrap();
+
+        let result = catch_panic(|| {
+            let _guard = RestoreGuard {
+                path: &path,
+                content: "original",
+                crate_dir: dir.path(),
+                backup: None,
+            };
+            std::fs::write(&path, "mutated").unwrap();
+            panic!("failure after writing");
+        });
+
+        assert!(result.is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "original");
+    }
+
+    #[test]
+    fn test_combine_results_killed_if_any_target_fails() {
+        let result = combine_results(vec![
+            ("core --lib".to_string(), TestResult::Passed),
+            (
+                "core --test integration".to_string(),
+                TestResult::Failed("assertion failed".to_string()),
+            ),
+        ]);
+        match result {
+            TestResult::Failed(details) => {
+                assert!(details.contains("=== core --test integration ==="));
+                assert!(details.contains("assertion failed"));
+                assert!(!details.contains("core --lib"));
             }
+            _ => panic!("expected a failed result"),
+        }
 
-            // Try to prepare the mutation (this validates everything)
-            apply_mutation_to_file(&file_path, mutation).map(|_| ())
-        })
-        .collect()
+        let result = combine_results(vec![
+            ("core --lib".to_string(), TestResult::Passed),
+            ("core --test integration".to_string(), TestResult::Passed),
+        ]);
+        assert!(matches!(result, TestResult::Passed));
+    }
+
+    #[test]
+    fn test_check_missing_program() {
+        let result = check_program("mutation-testing-rust-no-such-program");
+        assert!(matches!(result, Err(MutationError::CommandNotFound { .. })));
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "`mutation-testing-rust-no-such-program` not found on PATH"
+        );
+    }
+
+    #[test]
+    fn test_cargo_test_args_with_toolchain() {
+        let targets = vec!["--lib".to_string()];
+        let toolchain = |toolchain: Option<&str>| Settings {
+            toolchain: toolchain.map(String::from),
+            ..Default::default()
+        };
+        assert_eq!(
+            cargo_test_args(&toolchain(None), &targets, false),
+            ["test", "--lib", "--no-fail-fast", "--quiet"]
+        );
+        assert_eq!(
+            cargo_test_args(&toolchain(Some("1.75.0")), &targets, true),
+            ["+1.75.0", "test", "--lib", "--no-fail-fast"]
+        );
+        // A leading `+` in the setting isn't doubled
+        assert_eq!(cargo_test_args(&toolchain(Some("+nightly")), &[], true)[0], "+nightly");
+    }
+
+    #[test]
+    fn test_test_kind_args() {
+        let settings = Settings::default();
+        let runs = test_kind_args(&[TestKind::Doc], true);
+        assert_eq!(runs, [["--doc"]]);
+        assert_eq!(
+            cargo_test_args(&settings, &runs[0], false),
+            ["test", "--doc", "--no-fail-fast", "--quiet"]
+        );
+
+        assert_eq!(test_kind_args(&[], true), [Vec::<String>::new()]);
+        let all = [TestKind::Doc, TestKind::Unit, TestKind::Integration];
+        assert_eq!(
+            test_kind_args(&all, true),
+            [vec!["--lib", "--bins", "--test", "*"], vec!["--doc"]]
+        );
+
+        // A bin-only crate
+        assert_eq!(test_kind_args(&all, false), [["--bins", "--test", "*"]]);
+    }
+
+    #[test]
+    fn test_cargo_test_args_include_ignored() {
+        let settings = Settings {
+            include_ignored: true,
+            ..Default::default()
+        };
+        let targets = vec!["--test".to_string(), "integration".to_string()];
+        // `--include-ignored` is a test harness flag, so it must follow `--`
+        assert_eq!(
+            cargo_test_args(&settings, &targets, false),
+            [
+                "test",
+                "--test",
+                "integration",
+                "--no-fail-fast",
+                "--quiet",
+                "--",
+                "--include-ignored"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cargo_test_command_env() {
+        let env = HashMap::from([("FEATURE_FAST_PATH".to_string(), "1".to_string())]);
+        let cmd = cargo_test_command(Path::new("/work"), &Settings::default(), &[], Some(&env), false);
+        let envs: Vec<_> = cmd.get_envs().collect();
+        assert_eq!(envs, [(OsStr::new("FEATURE_FAST_PATH"), Some(OsStr::new("1")))]);
+
+        let cmd = cargo_test_command(Path::new("/work"), &Settings::default(), &[], None, false);
+        assert_eq!(cmd.get_envs().count(), 0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_wait_with_timeout() {
+        let mut child = Command::new("sleep").arg("10").spawn().unwrap();
+        let started = Instant::now();
+        let timeout = Some(Duration::from_millis(100));
+        assert!(wait_with_timeout(&mut child, timeout).unwrap().is_none());
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        let mut child = Command::new("true").spawn().unwrap();
+        let status = wait_with_timeout(&mut child, Some(Duration::from_secs(10))).unwrap();
+        assert!(status.unwrap().success());
+
+        let mut child = Command::new("true").spawn().unwrap();
+        assert!(wait_with_timeout(&mut child, None).unwrap().unwrap().success());
+    }
+
+    #[test]
+    fn test_format_command() {
+        let mut cmd = Command::new("cargo");
+        cmd.args(["+1.75.0", "test", "--no-fail-fast"])
+            .current_dir("/work/my crate")
+            .env("RUSTFLAGS", "-C debuginfo=0");
+        let env = vec![("CARGO_TARGET_DIR".to_string(), "/tmp/target".to_string())];
+
+        assert_eq!(
+            format_command(&cmd, &env),
+            "cd '/work/my crate' && CARGO_TARGET_DIR=/tmp/target RUSTFLAGS='-C debuginfo=0' \
+             cargo +1.75.0 test --no-fail-fast"
+        );
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+    }
+
+    #[test]
+    fn test_prioritize_survivors() {
+        let mutation = |id: &str| MutationConfig {
+            id: id.to_string(),
+            file: "src/lib.rs".into(),
+            function: "f".to_string(),
+            original: "a".to_string(),
+            replacement: id.to_string(),
+            ..Default::default()
+        };
+        // Ids were renumbered since; the changes still identify the mutations
+        let result = |replacement: &str, status| MutationResult {
+            mutation_id: format!("old_{}", replacement),
+            file: "src/lib.rs".into(),
+            function: "f".to_string(),
+            original: "a".to_string(),
+            replacement: replacement.to_string(),
+            status,
+            duration: Duration::ZERO,
+            line: None,
+            column: None,
+            start_byte: None,
+            end_byte: None,
+            details: None,
+            killed_by_test: None,
+            started_at: None,
+            finished_at: None,
+            left_mutated: false,
+        };
+
+        let mut mutations = vec![mutation("a"), mutation("b"), mutation("c"), mutation("d")];
+        let previous = MutationReport::new(vec![
+            result("a", MutationStatus::Killed),
+            result("b", MutationStatus::Timeout),
+            result("d", MutationStatus::Survived),
+        ]);
+
+        prioritize_survivors(&mut mutations, &previous);
+        let order: Vec<&str> = mutations.iter().map(|m| m.id.as_str()).collect();
+        assert_eq!(order, ["d", "b", "c", "a"]);
+    }
+
+    #[test]
+    fn test_classify_success() {
+        assert!(matches!(
+            classify_output(true, "test result: ok.", "error[E0308]"),
+            TestResult::Passed
+        ));
+    }
+
+    #[test]
+    fn test_truncate_details() {
+        let output = |lines: usize| -> String {
+            (1..=lines).map(|i| format!("line {}\n", i)).collect()
+        };
+
+        // At the limit, nothing is cut
+        assert_eq!(truncate_details(&output(3), 3, None), output(3));
+        assert_eq!(
+            truncate_details(&output(4), 3, None),
+            "line 1\nline 2\nline 3\n...(truncated, 1 more line)"
+        );
+        assert_eq!(
+            truncate_details(&output(5), 3, Some(Path::new("logs/m.log"))),
+            "line 1\nline 2\nline 3\n...(truncated, 2 more lines; full log in logs/m.log)"
+        );
+        // 0 keeps everything
+        assert_eq!(truncate_details(&output(100), 0, None), output(100));
+    }
+
+    #[test]
+    fn test_write_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let logs = dir.path().join("logs");
+        let path = write_log(&logs, "plus@src/a.rs", "full output").unwrap();
+        assert_eq!(path, logs.join("plus@src_a.rs.log"));
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "full output");
+    }
+
+    #[test]
+    fn test_retry_compile_error() {
+        use std::cell::Cell;
+
+        // A transient compile error followed by a clean build that passes
+        let reruns = Cell::new(0);
+        let rerun = |result: TestResult| {
+            reruns.set(reruns.get() + 1);
+            result
+        };
+        let result = retry_compile_error(TestResult::CompileError("bogus".into()), || {
+            rerun(TestResult::Passed)
+        });
+        assert!(matches!(result, TestResult::Passed));
+        assert_eq!(reruns.get(), 1);
+
+        // A compile error that reproduces is kept
+        let result = retry_compile_error(TestResult::CompileError("first".into()), || {
+            rerun(TestResult::CompileError("second".into()))
+        });
+        assert!(matches!(result, TestResult::CompileError(output) if output == "second"));
+        assert_eq!(reruns.get(), 2);
+
+        // Other results are never retried
+        let result = retry_compile_error(TestResult::Failed("x".into()), || {
+            rerun(TestResult::Passed)
+        });
+        assert!(matches!(result, TestResult::Failed(_)));
+        assert_eq!(reruns.get(), 2);
+    }
+
+    #[test]
+    fn test_retry_spawn() {
+        use std::io::{Error, ErrorKind};
+
+        // A transient spawn error followed by a successful start
+        let mut attempts = 0;
+        let spawned = retry_spawn(2, Duration::from_millis(1), false, || {
+            attempts += 1;
+            match attempts {
+                1 => Err(Error::new(ErrorKind::WouldBlock, "Resource temporarily unavailable")),
+                _ => Ok("child"),
+            }
+        });
+        assert_eq!(spawned.unwrap(), "child");
+        assert_eq!(attempts, 2);
+
+        // Retries run out: the last error is kept
+        let mut attempts = 0;
+        let spawned = retry_spawn(2, Duration::from_millis(1), false, || {
+            attempts += 1;
+            Err::<(), _>(Error::other(format!("attempt {}", attempts)))
+        });
+        assert_eq!(spawned.unwrap_err().to_string(), "attempt 3");
+
+        // A missing program isn't retried
+        let mut attempts = 0;
+        let spawned = retry_spawn(2, Duration::from_millis(1), false, || {
+            attempts += 1;
+            Err::<(), _>(Error::from(ErrorKind::NotFound))
+        });
+        assert_eq!(spawned.unwrap_err().kind(), ErrorKind::NotFound);
+        assert_eq!(attempts, 1);
+
+        // Without retries, the error is reported right away
+        let mut attempts = 0;
+        let spawned = retry_spawn(0, Duration::from_millis(1), false, || {
+            attempts += 1;
+            Err::<(), _>(Error::other("busy"))
+        });
+        assert!(spawned.is_err());
+        assert_eq!(attempts, 1);
+    }
+
+    #[test]
+    fn test_test_units() {
+        let mutation = |id: &str, group: Option<&str>| MutationConfig {
+            id: id.to_string(),
+            group: group.map(str::to_string),
+            ..Default::default()
+        };
+        let mutations = vec![
+            mutation("a", None),
+            mutation("b", Some("g")),
+            mutation("c", None),
+            mutation("d", Some("h")),
+            mutation("e", Some("g")),
+        ];
+
+        let units: Vec<Vec<&str>> = test_units(&mutations)
+            .iter()
+            .map(|unit| unit.iter().map(|m| m.id.as_str()).collect())
+            .collect();
+        assert_eq!(units, [vec!["a"], vec!["b", "e"], vec!["c"], vec!["d"]]);
+    }
+
+    #[test]
+    fn test_group_config() {
+        let first = MutationConfig {
+            file: PathBuf::from("src/lib.rs"),
+            function: "add".to_string(),
+            original: "a + b".to_string(),
+            replacement: "a - b".to_string(),
+            timeout: Some(10),
+            ..Default::default()
+        };
+        let second = MutationConfig {
+            file: PathBuf::from("src/b.rs"),
+            function: "double".to_string(),
+            original: "x * 2".to_string(),
+            replacement: "x + 2".to_string(),
+            killed_by: Some(vec!["test_both".to_string()]),
+            timeout: Some(30),
+            ..Default::default()
+        };
+
+        let group = group_config("pair", &[&first, &second]);
+        assert_eq!(group.id, "pair");
+        assert_eq!(group.file, PathBuf::from("src/lib.rs"));
+        assert_eq!(group.description(), "a + b; x * 2 -> a - b; x + 2 in src/lib.rs::add; double");
+        assert_eq!(group.killed_by, Some(vec!["test_both".to_string()]));
+        assert_eq!(group.env, None);
+        assert_eq!(group.timeout, Some(30));
+    }
+
+    #[test]
+    fn test_mutate_all_rejects_overlapping_members() {
+        let source = "fn scale(a: i32, b: i32) -> i32 {\n    let x = a + 1;\n    x + b * 2\n}\n";
+        let member = |id: &str, original: &str, replacement: &str| MutationConfig {
+            file: PathBuf::from("src/lib.rs"),
+            function: "scale".to_string(),
+            original: original.to_string(),
+            replacement: replacement.to_string(),
+            id: id.to_string(),
+            group: Some("pair".to_string()),
+            ..Default::default()
+        };
+
+        // Separate code in the same file is mutated together
+        let first = member("first", "a + 1", "a - 1");
+        let second = member("second", "b * 2", "b / 2");
+        let mutant = mutate_all(source, &[&first, &second]).unwrap();
+        assert!(mutant.source.contains("let x = a - 1;"));
+        assert!(mutant.source.contains("x + b / 2"));
+        assert_eq!((mutant.line, mutant.column), (2, 13));
+
+        // `b * 2` is part of `x + b * 2`, which the other member replaces
+        let outer = member("outer", "x + b * 2", "x - b * 2");
+        let Err(error) = mutate_all(source, &[&outer, &second]) else {
+            panic!("overlapping members were applied");
+        };
+        assert_eq!(error.kind(), "overlapping_mutations");
+        assert!(matches!(
+            &error,
+            MutationError::OverlappingMutations { mutations, locations, .. }
+                if mutations == &["outer", "second"] && locations[0].line == 3
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_file_globs_are_expanded() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(dir.path().join("src/a.rs"), "fn add(a: i32) -> i32 { a + 1 }").unwrap();
+        std::fs::write(dir.path().join("src/b.rs"), "fn add(a: i32) -> i32 { a * 2 }").unwrap();
+        let binary = dir.path().join("tests-bin");
+        std::fs::write(&binary, "#!/bin/sh\n! grep -q 'a - 1' src/a.rs\n").unwrap();
+        std::fs::set_permissions(&binary, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let yaml = r#"
+version: "1.0"
+settings:
+  test_binary: tests-bin
+mutations:
+  - id: plus
+    file: src/*.rs
+    function: add
+    original: a + 1
+    replacement: a - 1
+"#;
+        let config = Config::from_yaml_str(yaml).unwrap();
+
+        // One result for the glob, invalid because `src/b.rs` has no `a + 1`
+        let validations = validate_mutations(&config, dir.path());
+        assert_eq!(validations.len(), 1);
+        assert!(matches!(validations[0], Err(MutationError::NoMatch { .. })));
+
+        let results = run_mutation_tests(&config, dir.path(), false).unwrap();
+        let ids: Vec<&str> = results.iter().map(|r| r.mutation_id.as_str()).collect();
+        assert_eq!(ids, ["plus@src/a.rs", "plus@src/b.rs"]);
+        assert_eq!(results[0].status, MutationStatus::Killed, "{:?}", results[0].details);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_mutation_group_across_files() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        let lib = "mod b;\n\npub fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n";
+        let b = "pub fn double(x: i32) -> i32 {\n    x * 2\n}\n";
+        std::fs::write(dir.path().join("src/lib.rs"), lib).unwrap();
+        std::fs::write(dir.path().join("src/b.rs"), b).unwrap();
+        // Stands in for tests that only fail when both files are mutated; it
+        // reads the sources at runtime, so neither counts as compiled into it
+        let binary = dir.path().join("tests-bin");
+        let script = "#!/bin/sh\n! { grep -q 'a - b' src/lib.rs && grep -q 'x + 2' src/b.rs; }\n";
+        std::fs::write(&binary, script).unwrap();
+        std::fs::set_permissions(&binary, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let settings = Settings {
+            test_binary: Some(PathBuf::from("tests-bin")),
+            ..Default::default()
+        };
+
+        let add = MutationConfig {
+            file: PathBuf::from("src/lib.rs"),
+            function: "add".to_string(),
+            original: "a + b".to_string(),
+            replacement: "a - b".to_string(),
+            id: "add".to_string(),
+            group: Some("pair".to_string()),
+            ..Default::default()
+        };
+        let double = MutationConfig {
+            file: PathBuf::from("src/b.rs"),
+            function: "double".to_string(),
+            original: "x * 2".to_string(),
+            replacement: "x + 2".to_string(),
+            id: "double".to_string(),
+            group: Some("pair".to_string()),
+            ..Default::default()
+        };
+
+        // The test only fails when both files are mutated at once
+        let members = [&add, &double];
+        let result = run_mutation_group("pair", &members, dir.path(), &settings, &[], false);
+        assert_eq!(result.mutation_id, "pair");
+        assert_eq!(result.status, MutationStatus::Killed, "{:?}", result.details);
+        assert_eq!(result.line, Some(4));
+
+        let result = run_mutation_group("add", &[&add], dir.path(), &settings, &[], false);
+        assert_eq!(result.status, MutationStatus::Survived, "{:?}", result.details);
+
+        // Both files are restored
+        assert_eq!(std::fs::read_to_string(dir.path().join("src/lib.rs")).unwrap(), lib);
+        assert_eq!(std::fs::read_to_string(dir.path().join("src/b.rs")).unwrap(), b);
+    }
+
+    #[test]
+    fn test_binary_dependencies() {
+        let dir = tempfile::tempdir().unwrap();
+        let binary = dir.path().join("demo-1a2b");
+        assert!(binary_dependencies(&binary).is_none());
+
+        std::fs::write(
+            dir.path().join("demo-1a2b.d"),
+            "/work/target/debug/deps/demo-1a2b: src/lib.rs src/my\\ mod.rs\n\nsrc/lib.rs:\n",
+        )
+        .unwrap();
+        let dependencies = binary_dependencies(&binary).unwrap();
+        assert_eq!(
+            dependencies,
+            [PathBuf::from("src/lib.rs"), PathBuf::from("src/my mod.rs")]
+        );
+        assert!(is_dependency(&dependencies, Path::new("/project/src/lib.rs")));
+        assert!(!is_dependency(&dependencies, Path::new("/project/plugins/lib.rs")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_timeout_kills_started_processes() {
+        // A test binary cargo started, still holding the pipes after the
+        // command itself is killed
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "sleep 30 & wait"]);
+        let start = Instant::now();
+        let result = run_test_process(
+            cmd,
+            "the tests",
+            &Settings::default(),
+            Some(Duration::from_millis(200)),
+            false,
+        );
+        assert!(matches!(result, TestResult::Timeout));
+        // The background `sleep` was killed too, so the pipes closed
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_prebuilt_test_binary() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::create_dir(dir.path().join("plugins")).unwrap();
+        let lib = "pub fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n";
+        std::fs::write(dir.path().join("src/lib.rs"), lib).unwrap();
+        std::fs::write(dir.path().join("plugins/rule.rs"), lib).unwrap();
+        // Stands in for a binary compiled from src/lib.rs that loads the
+        // plugin at runtime
+        let binary = dir.path().join("tests-bin");
+        std::fs::write(&binary, "#!/bin/sh\ngrep -q 'a + b' plugins/rule.rs\n").unwrap();
+        std::fs::set_permissions(&binary, std::fs::Permissions::from_mode(0o755)).unwrap();
+        std::fs::write(dir.path().join("tests-bin.d"), "tests-bin: src/lib.rs\n").unwrap();
+
+        let settings = Settings {
+            test_binary: Some(PathBuf::from("tests-bin")),
+            ..Default::default()
+        };
+        let run = |file: &str| {
+            let mutation = MutationConfig {
+                file: PathBuf::from(file),
+                function: "add".to_string(),
+                original: "a + b".to_string(),
+                replacement: "a - b".to_string(),
+                id: "add".to_string(),
+                ..Default::default()
+            };
+            let mutate = |source: &str| {
+                let prepared = prepare_mutation(source, &mutation)?;
+                Ok(Mutant {
+                    source: prepared.mutated_source,
+                    line: prepared.site.line,
+                    column: prepared.site.column,
+                    start_byte: prepared.site.start_byte,
+                    end_byte: prepared.site.end_byte,
+                })
+            };
+            run_single_mutation(&mutation, dir.path(), &settings, &[], &mutate, false).status
+        };
+
+        assert_eq!(run("plugins/rule.rs"), MutationStatus::Killed);
+        match run("src/lib.rs") {
+            MutationStatus::ConfigError(error) => {
+                assert_eq!(error.kind(), "recompilation_required")
+            }
+            status => panic!("expected a recompilation error, got {:?}", status),
+        }
+        // The file compiled into the binary was never touched
+        assert_eq!(std::fs::read_to_string(dir.path().join("src/lib.rs")).unwrap(), lib);
+    }
+
+    #[test]
+    fn test_validation_json() {
+        let mutation = MutationConfig {
+            file: PathBuf::from("src/lib.rs"),
+            function: "add".to_string(),
+            original: "a + b".to_string(),
+            replacement: "a - b".to_string(),
+            id: "add_sub".to_string(),
+            ..Default::default()
+        };
+
+        let valid = serde_json::to_value(Validation::new(&mutation, &Ok(()))).unwrap();
+        assert_eq!(
+            valid,
+            serde_json::json!({
+                "mutation_id": "add_sub",
+                "file": "src/lib.rs",
+                "function": "add",
+                "original": "a + b",
+                "replacement": "a - b",
+                "valid": true,
+                "error": null,
+            })
+        );
+
+        let missing = MutationError::FileNotFound {
+            file: PathBuf::from("src/lib.rs"),
+        };
+        let invalid = Validation::new(&mutation, &Err(missing.clone()));
+        assert!(!invalid.valid);
+        assert_eq!(invalid.error, Some(missing.to_string()));
+    }
 }
@@ -0,0 +1,257 @@
+//! Test target discovery
+//!
+//! This module enumerates a project's test targets via `cargo metadata` so
+//! each one can be run as its own `cargo test` invocation.
+
+use std::path::Path;
+use std::process::Command;
+
+use serde::Deserialize;
+
+use crate::config::TestKind;
+use crate::error::{MutationError, Result};
+
+/// A single test target within a package
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestTarget {
+    /// Package the target belongs to
+    pub package: String,
+    /// Kind of target
+    pub kind: TargetKind,
+    /// Target name
+    pub name: String,
+}
+
+/// Kinds of targets that `cargo test` can run individually
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TargetKind {
+    Lib,
+    Bin,
+    Test,
+    /// The doctests of a library
+    Doc,
+}
+
+impl TestTarget {
+    /// Arguments that select only this target for `cargo test`
+    pub fn cargo_args(&self) -> Vec<String> {
+        let mut args = vec!["--package".to_string(), self.package.clone()];
+        match self.kind {
+            TargetKind::Lib => args.push("--lib".to_string()),
+            TargetKind::Bin => {
+                args.push("--bin".to_string());
+                args.push(self.name.clone());
+            }
+            TargetKind::Test => {
+                args.push("--test".to_string());
+                args.push(self.name.clone());
+            }
+            TargetKind::Doc => args.push("--doc".to_string()),
+        }
+        args
+    }
+
+    /// Short human-readable label, e.g. `core --test integration`
+    pub fn label(&self) -> String {
+        self.cargo_args()[1..].join(" ")
+    }
+}
+
+#[derive(Deserialize)]
+struct Metadata {
+    packages: Vec<Package>,
+}
+
+#[derive(Deserialize)]
+struct Package {
+    name: String,
+    targets: Vec<Target>,
+}
+
+#[derive(Deserialize)]
+struct Target {
+    name: String,
+    kind: Vec<String>,
+    #[serde(default = "default_test")]
+    test: bool,
+}
+
+fn default_test() -> bool {
+    true
+}
+
+/// Enumerate the test targets of the project in `project_dir`
+pub fn discover_test_targets(project_dir: &Path) -> Result<Vec<TestTarget>> {
+    let output = Command::new("cargo")
+        .args(["metadata", "--no-deps", "--format-version", "1"])
+        .current_dir(project_dir)
+        .output()
+        .map_err(|e| MutationError::TestExecutionError {
+            error: format!("Failed to run cargo metadata: {}", e),
+        })?;
+
+    if !output.status.success() {
+        return Err(MutationError::TestExecutionError {
+            error: format!(
+                "cargo metadata failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ),
+        });
+    }
+
+    parse_test_targets(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Parse test targets out of `cargo metadata` JSON output
+pub fn parse_test_targets(json: &str) -> Result<Vec<TestTarget>> {
+    let metadata: Metadata =
+        serde_json::from_str(json).map_err(|e| MutationError::TestExecutionError {
+            error: format!("Failed to parse cargo metadata: {}", e),
+        })?;
+
+    let mut targets = Vec::new();
+    for package in metadata.packages {
+        for target in package.targets {
+            if !target.test {
+                continue;
+            }
+            let kind = if target.kind.iter().any(|k| k == "test") {
+                TargetKind::Test
+            } else if target.kind.iter().any(|k| k == "bin") {
+                TargetKind::Bin
+            } else if target.kind.iter().any(|k| is_lib_kind(k)) {
+                TargetKind::Lib
+            } else {
+                // Examples, benches and build scripts aren't run by `cargo test`
+                continue;
+            };
+            targets.push(TestTarget {
+                package: package.name.clone(),
+                kind,
+                name: target.name,
+            });
+        }
+    }
+
+    Ok(targets)
+}
+
+/// Keep the targets running the selected kinds of tests, adding each
+/// library's doctests if `doc` is selected
+///
+/// With no kinds selected, every kind is, as a plain `cargo test` runs
+/// doctests too.
+pub fn select_targets(targets: Vec<TestTarget>, kinds: &[TestKind]) -> Vec<TestTarget> {
+    let all = [TestKind::Unit, TestKind::Doc, TestKind::Integration];
+    let kinds = if kinds.is_empty() { &all[..] } else { kinds };
+    let mut selected = Vec::new();
+    for target in targets {
+        if target.kind == TargetKind::Lib && kinds.contains(&TestKind::Doc) {
+            selected.push(TestTarget {
+                kind: TargetKind::Doc,
+                ..target.clone()
+            });
+        }
+        let keep = match target.kind {
+            TargetKind::Lib | TargetKind::Bin => kinds.contains(&TestKind::Unit),
+            TargetKind::Test => kinds.contains(&TestKind::Integration),
+            TargetKind::Doc => kinds.contains(&TestKind::Doc),
+        };
+        if keep {
+            selected.push(target);
+        }
+    }
+    selected
+}
+
+/// Fail if `doc` is the only kind selected but none of `targets` is a library
+///
+/// `cargo test --doc` then fails with "no library targets found" for every
+/// mutant, which would count as killing it.
+pub fn check_doc_targets(targets: &[TestTarget], kinds: &[TestKind]) -> Result<()> {
+    let doc_only = !kinds.is_empty() && kinds.iter().all(|k| *k == TestKind::Doc);
+    if doc_only && !targets.iter().any(|t| t.kind == TargetKind::Lib) {
+        return Err(MutationError::ConfigError {
+            message: "settings.test_kinds is only `doc`, but the crate has no library target \
+                      and so no doctests"
+                .to_string(),
+        });
+    }
+    Ok(())
+}
+
+fn is_lib_kind(kind: &str) -> bool {
+    matches!(
+        kind,
+        "lib" | "rlib" | "dylib" | "cdylib" | "staticlib" | "proc-macro"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_test_targets() {
+        let json = r#"{
+            "packages": [{
+                "name": "core",
+                "targets": [
+                    {"name": "core", "kind": ["lib"], "test": true},
+                    {"name": "core-cli", "kind": ["bin"], "test": true},
+                    {"name": "integration", "kind": ["test"], "test": true},
+                    {"name": "no_harness", "kind": ["test"], "test": false},
+                    {"name": "demo", "kind": ["example"], "test": false},
+                    {"name": "build-script-build", "kind": ["custom-build"], "test": false}
+                ]
+            }],
+            "version": 1
+        }"#;
+
+        let targets = parse_test_targets(json).unwrap();
+        assert_eq!(targets.len(), 3);
+        assert_eq!(targets[0].cargo_args(), ["--package", "core", "--lib"]);
+        assert_eq!(targets[1].cargo_args(), ["--package", "core", "--bin", "core-cli"]);
+        assert_eq!(targets[2].label(), "core --test integration");
+
+        let doc_only = select_targets(targets.clone(), &[TestKind::Doc]);
+        assert_eq!(doc_only.len(), 1);
+        assert_eq!(doc_only[0].cargo_args(), ["--package", "core", "--doc"]);
+        let unit = select_targets(targets.clone(), &[TestKind::Unit]);
+        assert_eq!(unit, targets[..2]);
+    }
+
+    #[test]
+    fn test_select_all_targets_includes_doctests() {
+        let lib = TestTarget {
+            package: "core".to_string(),
+            kind: TargetKind::Lib,
+            name: "core".to_string(),
+        };
+        let bin = TestTarget {
+            kind: TargetKind::Bin,
+            name: "core-cli".to_string(),
+            ..lib.clone()
+        };
+
+        let all = select_targets(vec![lib.clone(), bin.clone()], &[]);
+        let args: Vec<Vec<String>> = all.iter().map(|t| t.cargo_args()).collect();
+        assert_eq!(
+            args,
+            [
+                vec!["--package", "core", "--doc"],
+                vec!["--package", "core", "--lib"],
+                vec!["--package", "core", "--bin", "core-cli"],
+            ]
+        );
+
+        // Without a library there are no doctests to add
+        let bins = [bin.clone()];
+        assert_eq!(select_targets(bins.to_vec(), &[]), bins);
+
+        assert!(check_doc_targets(&[lib, bin], &[TestKind::Doc]).is_ok());
+        assert!(check_doc_targets(&bins, &[TestKind::Doc]).is_err());
+        assert!(check_doc_targets(&bins, &[TestKind::Unit, TestKind::Doc]).is_ok());
+        assert!(check_doc_targets(&bins, &[]).is_ok());
+    }
+}
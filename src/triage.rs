@@ -0,0 +1,256 @@
+//! Ordering survivors by how likely they are real test gaps
+//!
+//! Not every survivor is worth the same effort. A swapped operator in a small
+//! expression usually takes one assertion to kill, while an overflow method
+//! swap or a removed logging call is often equivalent to the original. The
+//! category and complexity of a mutation are derived from its `original` and
+//! `replacement` code, so they work for reports from any config.
+
+use std::cmp::Ordering;
+
+use quote::ToTokens;
+use syn::visit::Visit;
+use syn::{Expr, Lit, UnOp};
+
+use crate::runner::MutationResult;
+
+/// What kind of change a mutation makes, from likely to least likely a real
+/// test gap
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum MutationCategory {
+    /// A binary operator replaced by another, e.g. `a + b` to `a - b`
+    OperatorSwap,
+    /// A condition forced or negated, e.g. `x > 0` to `true` or `!(x > 0)`
+    Condition,
+    /// A literal changed, e.g. `age >= 18` to `age >= 17`
+    Literal,
+    /// Code removed or replaced by a default, e.g. a block to `{}`
+    Removal,
+    /// A method call added, removed or swapped, e.g. `a + b` to
+    /// `a.wrapping_add(b)`
+    Method,
+    /// Anything else
+    Other,
+}
+
+impl MutationCategory {
+    /// Human-readable name, as shown next to sorted survivors
+    pub fn label(&self) -> &'static str {
+        match self {
+            MutationCategory::OperatorSwap => "operator swap",
+            MutationCategory::Condition => "condition",
+            MutationCategory::Literal => "literal",
+            MutationCategory::Removal => "removal",
+            MutationCategory::Method => "method call",
+            MutationCategory::Other => "other",
+        }
+    }
+
+    /// Categorize the change from `original` to `replacement`
+    pub fn of(original: &str, replacement: &str) -> Self {
+        let (Ok(original), Ok(replacement)) =
+            (syn::parse_str::<Expr>(original), syn::parse_str::<Expr>(replacement))
+        else {
+            return MutationCategory::Other;
+        };
+        categorize(&original, &replacement)
+    }
+}
+
+fn categorize(original: &Expr, replacement: &Expr) -> MutationCategory {
+    let same = |a: &Expr, b: &Expr| tokens(a) == tokens(b);
+    match (original, replacement) {
+        (Expr::Paren(original), _) => categorize(&original.expr, replacement),
+        (_, Expr::Paren(replacement)) => categorize(original, &replacement.expr),
+        (Expr::Binary(a), Expr::Binary(b)) if same(&a.left, &b.left) && same(&a.right, &b.right) => {
+            MutationCategory::OperatorSwap
+        }
+        // The same operator with one operand changed: categorize that change
+        (Expr::Binary(a), Expr::Binary(b)) if tokens(&a.op) == tokens(&b.op) => {
+            if same(&a.left, &b.left) {
+                categorize(&a.right, &b.right)
+            } else if same(&a.right, &b.right) {
+                categorize(&a.left, &b.left)
+            } else {
+                MutationCategory::Other
+            }
+        }
+        (_, Expr::Lit(lit)) if matches!(lit.lit, Lit::Bool(_)) => MutationCategory::Condition,
+        (Expr::Unary(u), other) | (other, Expr::Unary(u))
+            if matches!(u.op, UnOp::Not(_)) && same(&u.expr, other) =>
+        {
+            MutationCategory::Condition
+        }
+        (Expr::Lit(_), Expr::Lit(_)) => MutationCategory::Literal,
+        (_, replacement) if is_removal(replacement) => MutationCategory::Removal,
+        (Expr::MethodCall(_), _) | (_, Expr::MethodCall(_)) => MutationCategory::Method,
+        _ => MutationCategory::Other,
+    }
+}
+
+/// Whether `expr` stands in for removed code: `()`, an empty block,
+/// `Default::default()` or a bare `break`/`continue`
+fn is_removal(expr: &Expr) -> bool {
+    match expr {
+        Expr::Tuple(tuple) => tuple.elems.is_empty(),
+        Expr::Block(block) => block.block.stmts.is_empty(),
+        Expr::Call(call) => call.args.is_empty() && tokens(&call.func).ends_with("default"),
+        Expr::Break(_) | Expr::Continue(_) => true,
+        _ => false,
+    }
+}
+
+fn tokens(node: &impl ToTokens) -> String {
+    node.to_token_stream().to_string()
+}
+
+/// Number of expressions in `original`, counting nested ones
+///
+/// Code that doesn't parse counts as one expression per whitespace-separated
+/// word, so it still sorts after simple expressions.
+pub fn complexity(original: &str) -> usize {
+    struct Counter(usize);
+    impl<'ast> Visit<'ast> for Counter {
+        fn visit_expr(&mut self, expr: &'ast Expr) {
+            self.0 += 1;
+            syn::visit::visit_expr(self, expr);
+        }
+    }
+
+    match syn::parse_str::<Expr>(original) {
+        Ok(expr) => {
+            let mut counter = Counter(0);
+            counter.visit_expr(&expr);
+            counter.0
+        }
+        Err(_) => original.split_whitespace().count(),
+    }
+}
+
+/// How the survivors of a report are listed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SurvivorOrder {
+    /// By file, then line and column
+    Location,
+    /// Likely real gaps first (see [`MutationCategory`]), simpler expressions
+    /// first within a category
+    Category,
+    /// Simplest expressions first, by category among equally complex ones
+    Complexity,
+}
+
+/// Parse a survivor order: `location`, `category` or `complexity`
+pub fn parse_survivor_order(s: &str) -> Result<SurvivorOrder, String> {
+    match s.trim() {
+        "location" => Ok(SurvivorOrder::Location),
+        "category" => Ok(SurvivorOrder::Category),
+        "complexity" => Ok(SurvivorOrder::Complexity),
+        other => Err(format!(
+            "invalid survivor order '{}' (expected location, category or complexity)",
+            other
+        )),
+    }
+}
+
+/// Sort `survivors` in `order`; ties keep their report order
+pub fn sort_survivors(survivors: &mut [&MutationResult], order: SurvivorOrder) {
+    let key = |result: &MutationResult| {
+        (
+            MutationCategory::of(&result.original, &result.replacement),
+            complexity(&result.original),
+        )
+    };
+    survivors.sort_by(|a, b| match order {
+        SurvivorOrder::Location => location(a, b),
+        SurvivorOrder::Category => key(a).cmp(&key(b)),
+        SurvivorOrder::Complexity => {
+            let ((a_category, a_complexity), (b_category, b_complexity)) = (key(a), key(b));
+            a_complexity.cmp(&b_complexity).then(a_category.cmp(&b_category))
+        }
+    });
+}
+
+/// Order by file, then line and column, with unlocated results last
+fn location(a: &MutationResult, b: &MutationResult) -> Ordering {
+    let position = |r: &MutationResult| (r.line.unwrap_or(usize::MAX), r.column.unwrap_or(0));
+    a.file.cmp(&b.file).then(position(a).cmp(&position(b)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::MutationConfig;
+    use crate::runner::{not_run_result, MutationStatus};
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    fn survivor(file: &str, line: usize, original: &str, replacement: &str) -> MutationResult {
+        let mutation = MutationConfig {
+            id: format!("{}:{}", file, line),
+            file: PathBuf::from(file),
+            function: "f".to_string(),
+            original: original.to_string(),
+            replacement: replacement.to_string(),
+            ..Default::default()
+        };
+        MutationResult {
+            status: MutationStatus::Survived,
+            duration: Duration::from_millis(10),
+            line: Some(line),
+            column: Some(5),
+            ..not_run_result(&mutation)
+        }
+    }
+
+    #[test]
+    fn test_categories() {
+        let category = MutationCategory::of;
+        assert_eq!(category("a + b", "a - b"), MutationCategory::OperatorSwap);
+        assert_eq!(category("x > 0 && ready", "x > 0 || ready"), MutationCategory::OperatorSwap);
+        assert_eq!(category("x > 0", "true"), MutationCategory::Condition);
+        assert_eq!(category("ready", "!ready"), MutationCategory::Condition);
+        assert_eq!(category("age >= 18", "age >= 17"), MutationCategory::Literal);
+        assert_eq!(category("{ log(x); }", "{}"), MutationCategory::Removal);
+        assert_eq!(category("total", "Default::default()"), MutationCategory::Removal);
+        assert_eq!(category("a + b", "a.wrapping_add(b)"), MutationCategory::Method);
+        assert_eq!(category("a + b", "c * d"), MutationCategory::Other);
+        assert_eq!(category("a +", "a -"), MutationCategory::Other);
+
+        assert_eq!(complexity("a + b"), 3);
+        assert_eq!(complexity("f(a + b) * c"), 7);
+    }
+
+    #[test]
+    fn test_sort_survivors() {
+        let results = [
+            survivor("src/b.rs", 3, "a.len() + offset", "a.len().wrapping_add(offset)"),
+            survivor("src/b.rs", 1, "age >= 18", "age >= 17"),
+            survivor("src/a.rs", 9, "scale(x) * factor + bias", "scale(x) * factor - bias"),
+            survivor("src/a.rs", 2, "a + b", "a - b"),
+            survivor("src/a.rs", 5, "{ log(x); }", "{}"),
+        ];
+        let sorted = |order| {
+            let mut survivors: Vec<&MutationResult> = results.iter().collect();
+            sort_survivors(&mut survivors, order);
+            survivors
+                .iter()
+                .map(|r| r.mutation_id.as_str())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            sorted(SurvivorOrder::Location),
+            ["src/a.rs:2", "src/a.rs:5", "src/a.rs:9", "src/b.rs:1", "src/b.rs:3"]
+        );
+        // Operator swaps first, the simpler one ahead; the method swap last
+        assert_eq!(
+            sorted(SurvivorOrder::Category),
+            ["src/a.rs:2", "src/a.rs:9", "src/b.rs:1", "src/a.rs:5", "src/b.rs:3"]
+        );
+        // Equally complex survivors by category
+        assert_eq!(
+            sorted(SurvivorOrder::Complexity),
+            ["src/a.rs:2", "src/b.rs:1", "src/a.rs:5", "src/b.rs:3", "src/a.rs:9"]
+        );
+    }
+}
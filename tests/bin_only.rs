@@ -0,0 +1,50 @@
+//! Selecting kinds of tests in a crate without a library, where cargo
+//! rejects `--lib` and `--doc`
+
+use mutation_testing_rust::{run_mutation_tests, Config, MutationStatus};
+
+const MAIN: &str = r#"
+fn add(a: i32, b: i32) -> i32 {
+    a + b
+}
+
+fn main() {
+    println!("{}", add(2, 2));
+}
+
+#[test]
+fn test_add() {
+    assert_eq!(add(2, 2), 4);
+}
+"#;
+
+const CONFIG: &str = r#"
+version: "1.0"
+settings:
+  test_kinds: [unit]
+mutations:
+  - id: add_mul
+    file: src/main.rs
+    function: add
+    original: a + b
+    replacement: a * b
+"#;
+
+#[test]
+fn test_unit_tests_of_bin_only_crate() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("src")).unwrap();
+    std::fs::write(
+        dir.path().join("Cargo.toml"),
+        "[package]\nname = \"bin-only\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[workspace]\n",
+    )
+    .unwrap();
+    std::fs::write(dir.path().join("src/main.rs"), MAIN).unwrap();
+
+    let config = Config::from_yaml_str(CONFIG).unwrap();
+    let results = run_mutation_tests(&config, dir.path(), false).unwrap();
+
+    // `2 * 2` is still 4: the test runs and passes, rather than `--lib`
+    // failing the run and counting as a kill
+    assert_eq!(results[0].status, MutationStatus::Survived, "{:?}", results[0].details);
+}
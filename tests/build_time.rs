@@ -0,0 +1,52 @@
+//! The timeout covers running the tests, not building the mutant
+
+use mutation_testing_rust::{run_mutation_tests, Config, MutationStatus};
+
+const LIB: &str = r#"
+pub fn add(a: i32, b: i32) -> i32 {
+    a + b
+}
+
+#[test]
+fn test_add() {
+    assert_eq!(add(2, 3), 5);
+}
+"#;
+
+// Without `rerun-if-changed`, cargo runs the build script again whenever a
+// file of the package changes, so every mutant takes this long to build
+const BUILD: &str = r#"
+fn main() {
+    std::thread::sleep(std::time::Duration::from_secs(3));
+}
+"#;
+
+const CONFIG: &str = r#"
+version: "1.0"
+settings:
+  timeout: 1
+mutations:
+  - id: add_sub
+    file: src/lib.rs
+    function: add
+    original: a + b
+    replacement: a - b
+"#;
+
+#[test]
+fn test_slow_build_is_not_a_timeout() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("src")).unwrap();
+    std::fs::write(
+        dir.path().join("Cargo.toml"),
+        "[package]\nname = \"slow-build\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[workspace]\n",
+    )
+    .unwrap();
+    std::fs::write(dir.path().join("build.rs"), BUILD).unwrap();
+    std::fs::write(dir.path().join("src/lib.rs"), LIB).unwrap();
+
+    let config = Config::from_yaml_str(CONFIG).unwrap();
+    let results = run_mutation_tests(&config, dir.path(), false).unwrap();
+
+    assert_eq!(results[0].status, MutationStatus::Killed, "{:?}", results[0].details);
+}
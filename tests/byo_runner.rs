@@ -0,0 +1,78 @@
+//! Running the tests without `cargo test`: the crate only prepares mutants,
+//! and the harness here writes them, runs its own test command and restores
+//! the file.
+
+use std::path::Path;
+use std::process::Command;
+
+use mutation_testing_rust::{prepare_mutation, Config};
+
+const SOURCE: &str = r#"
+pub fn add(a: i32, b: i32) -> i32 {
+    a + b
+}
+
+pub fn is_adult(age: u32) -> bool {
+    age >= 18
+}
+
+#[test]
+fn test_add() {
+    assert_eq!(add(2, 3), 5);
+}
+"#;
+
+const CONFIG: &str = r#"
+version: "1.0"
+mutations:
+  - id: add
+    file: math.rs
+    function: add
+    original: a + b
+    replacement: a - b
+  - id: adult
+    file: math.rs
+    function: is_adult
+    original: age >= 18
+    replacement: age > 18
+"#;
+
+/// The bespoke harness: compile the file's tests with plain `rustc` and run
+/// them, returning whether they passed
+fn run_tests(file: &Path) -> bool {
+    let binary = file.with_extension("test-bin");
+    let compiled = Command::new("rustc")
+        .args(["--edition", "2021", "--test", "-o"])
+        .arg(&binary)
+        .arg(file)
+        .output()
+        .expect("rustc runs");
+    assert!(compiled.status.success(), "{}", String::from_utf8_lossy(&compiled.stderr));
+    Command::new(&binary).output().expect("test binary runs").status.success()
+}
+
+#[test]
+fn test_bring_your_own_runner() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("math.rs");
+    std::fs::write(&path, SOURCE).unwrap();
+    assert!(run_tests(&path));
+
+    let config = Config::from_yaml_str(CONFIG).unwrap();
+    let mut outcomes = Vec::new();
+    for mutation in &config.mutations {
+        let original = std::fs::read_to_string(dir.path().join(&mutation.file)).unwrap();
+        let prepared = prepare_mutation(&original, mutation).unwrap();
+
+        std::fs::write(&path, &prepared.mutated_source).unwrap();
+        let passed = run_tests(&path);
+        std::fs::write(&path, &original).unwrap();
+
+        outcomes.push((mutation.id.as_str(), prepared.site.line, passed));
+    }
+
+    // The untested comparison survives; the tested addition is killed
+    assert_eq!(outcomes, [("add", 3, false), ("adult", 7, true)]);
+    // Preparing never touched the file; restoring it was the harness's job
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), SOURCE);
+}
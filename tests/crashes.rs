@@ -0,0 +1,55 @@
+//! A crashing test binary is held to `killed_by` like a failing one
+#![cfg(unix)]
+
+use std::os::unix::fs::PermissionsExt;
+use std::process::Command;
+
+const CONFIG: &str = r#"
+version: "1.0"
+settings:
+  test_binary: tests-bin
+mutations:
+  - id: add_sub
+    file: src/lib.rs
+    function: add
+    original: a + b
+    replacement: a - b
+    killed_by: [test_add]
+  - id: add_mul
+    file: src/lib.rs
+    function: add
+    original: a + b
+    replacement: a * b
+    killed_by: [test_mul]
+"#;
+
+#[test]
+fn test_crash_is_checked_against_killed_by() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("src")).unwrap();
+    std::fs::write(
+        dir.path().join("Cargo.toml"),
+        "[package]\nname = \"crashes\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+    )
+    .unwrap();
+    let lib = "pub fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n";
+    std::fs::write(dir.path().join("src/lib.rs"), lib).unwrap();
+    std::fs::write(dir.path().join("mutations.yaml"), CONFIG).unwrap();
+    // A harness that reports test_add failing, then dies by a signal
+    let binary = dir.path().join("tests-bin");
+    let script = "#!/bin/sh\necho 'test tests::test_add ... FAILED'\nkill -SEGV $$\n";
+    std::fs::write(&binary, script).unwrap();
+    std::fs::set_permissions(&binary, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_mutation-testing-rust"))
+        .args(["test", "-c", "mutations.yaml", "-p", "."])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("add_sub - a + b -> a - b by test tests::test_add"), "{}", stdout);
+    assert!(stdout.contains("none of killed_by (test_mul)"), "{}", stdout);
+    // add_mul survives
+    assert_eq!(output.status.code(), Some(1), "{}", stdout);
+}
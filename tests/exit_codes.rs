@@ -0,0 +1,48 @@
+//! The process exit code of `test` when tests fail with an exit code that
+//! `settings.kill_on_exit_codes` doesn't count as a kill
+#![cfg(unix)]
+
+use std::os::unix::fs::PermissionsExt;
+use std::process::Command;
+
+const CONFIG: &str = r#"
+version: "1.0"
+settings:
+  test_binary: tests-bin
+  kill_on_exit_codes: [101]
+mutations:
+  - id: add_sub
+    file: src/lib.rs
+    function: add
+    original: a + b
+    replacement: a - b
+"#;
+
+#[test]
+fn test_unexpected_exit_code_is_not_a_config_error() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir(dir.path().join("src")).unwrap();
+    std::fs::write(
+        dir.path().join("Cargo.toml"),
+        "[package]\nname = \"exit-codes\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+    )
+    .unwrap();
+    let lib = "pub fn add(a: i32, b: i32) -> i32 {\n    a + b\n}\n";
+    std::fs::write(dir.path().join("src/lib.rs"), lib).unwrap();
+    std::fs::write(dir.path().join("mutations.yaml"), CONFIG).unwrap();
+    // A harness that exits with 3 when its setup fails rather than a test
+    let binary = dir.path().join("tests-bin");
+    std::fs::write(&binary, "#!/bin/sh\nexit 3\n").unwrap();
+    std::fs::set_permissions(&binary, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_mutation-testing-rust"))
+        .args(["test", "-c", "mutations.yaml", "-p", "."])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("exit code 3"), "{}", stdout);
+    // Neither a config error (2) nor a survivor (1)
+    assert_eq!(output.status.code(), Some(0), "{}", stdout);
+}